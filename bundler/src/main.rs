@@ -6,6 +6,7 @@ use std::{collections::HashMap, path::Path};
 
 #[derive(clap::Parser, Debug)]
 struct Arguments {
+    /// Source files, or a directory (e.g. `src/`) containing them.
     files: Vec<String>,
 
     #[clap(short, long)]
@@ -28,25 +29,22 @@ fn main() -> Result<()> {
         Err(e) => println!("watch error: {:?}", e),
     })?;
     for f in &args.files {
-        watcher.watch(Path::new(f), RecursiveMode::NonRecursive)?;
+        watcher.watch(Path::new(f), RecursiveMode::Recursive)?;
     }
 
     loop {
-        let files = args
-            .files
-            .iter()
-            .map(|f| {
-                (
-                    std::path::Path::new(f)
-                        .file_name()
-                        .unwrap()
-                        .to_str()
-                        .unwrap()
-                        .to_owned(),
-                    std::fs::read_to_string(f).unwrap(),
-                )
-            })
-            .collect::<HashMap<_, _>>();
+        let mut files = HashMap::new();
+        for f in &args.files {
+            let path = Path::new(f);
+            if path.is_dir() {
+                read_dir(path, "", &mut files)?;
+            } else {
+                files.insert(
+                    path.file_name().unwrap().to_str().unwrap().to_owned(),
+                    std::fs::read_to_string(path)?,
+                );
+            }
+        }
 
         let joined = oort_multifile::join(files)?;
 
@@ -62,3 +60,18 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Reads the Rust files under `dir`, keyed by their path relative to the
+/// top-level directory (e.g. "a/b.rs").
+fn read_dir(dir: &Path, prefix: &str, files: &mut HashMap<String, String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if path.is_dir() {
+            read_dir(&path, &format!("{prefix}{name}/"), files)?;
+        } else if name.ends_with(".rs") {
+            files.insert(format!("{prefix}{name}"), std::fs::read_to_string(&path)?);
+        }
+    }
+    Ok(())
+}
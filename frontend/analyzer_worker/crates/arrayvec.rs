@@ -0,0 +1,82 @@
+// Signatures from the arrayvec crate, for completions in the editor. The real
+// crate is linked by the compiler service.
+
+pub struct ArrayVec<T, const CAP: usize> {}
+
+impl<T, const CAP: usize> ArrayVec<T, CAP> {
+    pub const CAPACITY: usize = CAP;
+    pub fn new() -> ArrayVec<T, CAP> {
+        unimplemented!()
+    }
+    pub fn len(&self) -> usize {
+        unimplemented!()
+    }
+    pub fn is_empty(&self) -> bool {
+        unimplemented!()
+    }
+    pub fn capacity(&self) -> usize {
+        unimplemented!()
+    }
+    pub fn is_full(&self) -> bool {
+        unimplemented!()
+    }
+    pub fn remaining_capacity(&self) -> usize {
+        unimplemented!()
+    }
+    pub fn push(&mut self, element: T) {
+        unimplemented!()
+    }
+    pub fn insert(&mut self, index: usize, element: T) {
+        unimplemented!()
+    }
+    pub fn truncate(&mut self, new_len: usize) {
+        unimplemented!()
+    }
+    pub fn clear(&mut self) {
+        unimplemented!()
+    }
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        unimplemented!()
+    }
+    pub fn remove(&mut self, index: usize) -> T {
+        unimplemented!()
+    }
+    pub fn as_slice(&self) -> &[T] {
+        unimplemented!()
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unimplemented!()
+    }
+}
+
+pub struct ArrayString<const CAP: usize> {}
+
+impl<const CAP: usize> ArrayString<CAP> {
+    pub fn new() -> ArrayString<CAP> {
+        unimplemented!()
+    }
+    pub fn len(&self) -> usize {
+        unimplemented!()
+    }
+    pub fn is_empty(&self) -> bool {
+        unimplemented!()
+    }
+    pub fn capacity(&self) -> usize {
+        unimplemented!()
+    }
+    pub fn is_full(&self) -> bool {
+        unimplemented!()
+    }
+    pub fn push(&mut self, c: char) {
+        unimplemented!()
+    }
+    pub fn push_str(&mut self, s: &str) {
+        unimplemented!()
+    }
+    pub fn clear(&mut self) {
+        unimplemented!()
+    }
+    pub fn as_str(&self) -> &str {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,172 @@
+// Signatures for commonly used types from the nalgebra crate, for completions
+// in the editor. The real crate is linked by the compiler service.
+
+use core::ops::Deref;
+
+pub struct U1;
+pub struct U2;
+pub struct U3;
+
+pub struct ArrayStorage<T, const R: usize, const C: usize>(pub [[T; R]; C]);
+
+pub struct Matrix<T, R, C, S> {
+    pub data: S,
+    _phantoms: (T, R, C),
+}
+
+pub type Vector2<T> = Matrix<T, U2, U1, ArrayStorage<T, 2, 1>>;
+pub type Vector3<T> = Matrix<T, U3, U1, ArrayStorage<T, 3, 1>>;
+pub type Matrix1x2<T> = Matrix<T, U1, U2, ArrayStorage<T, 1, 2>>;
+pub type Matrix2<T> = Matrix<T, U2, U2, ArrayStorage<T, 2, 2>>;
+pub type Matrix3<T> = Matrix<T, U3, U3, ArrayStorage<T, 3, 3>>;
+
+pub struct XY<T> {
+    pub x: T,
+    pub y: T,
+}
+
+pub struct XYZ<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T, R, C, S> Matrix<T, R, C, S> {
+    pub fn zeros() -> Self {
+        unimplemented!()
+    }
+    pub fn dot(&self, rhs: &Self) -> T {
+        unimplemented!()
+    }
+    pub fn norm(&self) -> T {
+        unimplemented!()
+    }
+    pub fn norm_squared(&self) -> T {
+        unimplemented!()
+    }
+    pub fn normalize(&self) -> Self {
+        unimplemented!()
+    }
+    pub fn lerp(&self, rhs: &Self, t: T) -> Self {
+        unimplemented!()
+    }
+    pub fn angle(&self, other: &Self) -> T {
+        unimplemented!()
+    }
+}
+
+impl<T> Vector2<T> {
+    pub fn new(x: T, y: T) -> Vector2<T> {
+        unimplemented!()
+    }
+    pub fn x() -> Vector2<T> {
+        unimplemented!()
+    }
+    pub fn y() -> Vector2<T> {
+        unimplemented!()
+    }
+    pub fn perp(&self, rhs: &Vector2<T>) -> T {
+        unimplemented!()
+    }
+    pub fn transpose(&self) -> Matrix1x2<T> {
+        unimplemented!()
+    }
+}
+
+impl<T> Deref for Vector2<T> {
+    type Target = XY<T>;
+    fn deref(&self) -> &XY<T> {
+        unimplemented!()
+    }
+}
+
+impl<T> Vector3<T> {
+    pub fn new(x: T, y: T, z: T) -> Vector3<T> {
+        unimplemented!()
+    }
+    pub fn cross(&self, rhs: &Vector3<T>) -> Vector3<T> {
+        unimplemented!()
+    }
+}
+
+impl<T> Deref for Vector3<T> {
+    type Target = XYZ<T>;
+    fn deref(&self) -> &XYZ<T> {
+        unimplemented!()
+    }
+}
+
+impl<T> Matrix2<T> {
+    pub fn new(m11: T, m12: T, m21: T, m22: T) -> Matrix2<T> {
+        unimplemented!()
+    }
+    pub fn identity() -> Matrix2<T> {
+        unimplemented!()
+    }
+    pub fn transpose(&self) -> Matrix2<T> {
+        unimplemented!()
+    }
+    pub fn determinant(&self) -> T {
+        unimplemented!()
+    }
+}
+
+impl<T> Matrix3<T> {
+    pub fn new(
+        m11: T,
+        m12: T,
+        m13: T,
+        m21: T,
+        m22: T,
+        m23: T,
+        m31: T,
+        m32: T,
+        m33: T,
+    ) -> Matrix3<T> {
+        unimplemented!()
+    }
+    pub fn identity() -> Matrix3<T> {
+        unimplemented!()
+    }
+    pub fn transpose(&self) -> Matrix3<T> {
+        unimplemented!()
+    }
+    pub fn determinant(&self) -> T {
+        unimplemented!()
+    }
+}
+
+pub struct Point2<T> {
+    pub x: T,
+    pub y: T,
+    pub coords: Vector2<T>,
+}
+
+impl<T> Point2<T> {
+    pub fn new(x: T, y: T) -> Point2<T> {
+        unimplemented!()
+    }
+    pub fn origin() -> Point2<T> {
+        unimplemented!()
+    }
+}
+
+pub struct Rotation2<T> {}
+
+impl<T> Rotation2<T> {
+    pub fn new(angle: T) -> Rotation2<T> {
+        unimplemented!()
+    }
+    pub fn identity() -> Rotation2<T> {
+        unimplemented!()
+    }
+    pub fn angle(&self) -> T {
+        unimplemented!()
+    }
+    pub fn inverse(&self) -> Rotation2<T> {
+        unimplemented!()
+    }
+    pub fn rotation_between(a: &Vector2<T>, b: &Vector2<T>) -> Rotation2<T> {
+        unimplemented!()
+    }
+}
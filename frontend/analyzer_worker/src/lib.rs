@@ -6,13 +6,25 @@ use yew_agent::{HandlerId, Private, WorkerLink};
 
 use cfg::CfgOptions;
 use ide::{
-    AnalysisHost, Change, CompletionConfig, CrateGraph, CrateId, DiagnosticsConfig, Edition,
-    FileId, LineIndex, SourceRoot, TextSize,
+    AdjustmentHints, AdjustmentHintsMode, Analysis, AnalysisHost, Change, ClosureReturnTypeHints,
+    ClosureStyle, CompletionConfig, CrateGraph, CrateId, DiagnosticsConfig, DiscriminantHints,
+    Edition, FileId, FilePosition, FileRange, HoverConfig, HoverDocFormat, InlayHintPosition,
+    InlayHintsConfig, InlayKind, LifetimeElisionHints, LineIndex, SourceRoot, TextRange, TextSize,
 };
 use ide_db::base_db::{
     CrateDisplayName, CrateName, CrateOrigin, Dependency, Env, FileSet, LangCrateOrigin, VfsPath,
 };
 use ide_db::imports::insert_use::{ImportGranularity, InsertUseConfig, PrefixKind};
+use user_files::{UserFiles, ROOT_FILE_ID};
+
+mod user_files;
+
+/// Stubs for the third-party crates linked by the compiler service
+/// (`oort_compiler::ALLOWED_CRATES`).
+const ALLOWED_CRATES: &[(&str, &str)] = &[
+    ("arrayvec", include_str!("../crates/arrayvec.rs")),
+    ("nalgebra", include_str!("../crates/nalgebra.rs")),
+];
 
 pub fn create_source_root(name: &str, f: FileId) -> SourceRoot {
     let mut file_set = FileSet::default();
@@ -48,6 +60,9 @@ pub fn create_crate(
 pub struct AnalyzerAgent {
     link: WorkerLink<AnalyzerAgent>,
     analysis_host: ide::AnalysisHost,
+    user_files: UserFiles,
+    /// Source roots for everything except the user's files.
+    library_roots: Vec<SourceRoot>,
 }
 
 impl yew_agent::Worker for AnalyzerAgent {
@@ -63,19 +78,17 @@ impl yew_agent::Worker for AnalyzerAgent {
         let fake_std_src = include_str!("../stdlib/mini_std.rs");
 
         let mut host = AnalysisHost::default();
-        let file_id = FileId(0);
-        let std_id = FileId(1);
-        let core_id = FileId(2);
-        let alloc_id = FileId(3);
-        let oort_api_id = FileId(4);
-        let vec_id = FileId(5);
-
-        let mut file_set = FileSet::default();
-        file_set.insert(
-            file_id,
-            VfsPath::new_virtual_path("/my_crate/main.rs".to_string()),
-        );
-        let source_root = SourceRoot::new_local(file_set);
+        let user_files = UserFiles::new();
+        let file_id = ROOT_FILE_ID;
+        // Library files are numbered after the crate root. The user's other
+        // files start at a higher id, see `UserFiles`.
+        let mut library_file_ids = (ROOT_FILE_ID.0 + 1..).map(FileId);
+        let mut next_file_id = || library_file_ids.next().unwrap();
+        let std_id = next_file_id();
+        let core_id = next_file_id();
+        let alloc_id = next_file_id();
+        let oort_api_id = next_file_id();
+        let vec_id = next_file_id();
 
         let local_origin = CrateOrigin::Local {
             repo: None,
@@ -95,14 +108,24 @@ impl yew_agent::Worker for AnalyzerAgent {
             SourceRoot::new_library(file_set)
         };
 
+        let allowed_crate_ids: Vec<_> = ALLOWED_CRATES
+            .iter()
+            .map(|(name, src)| (*name, *src, next_file_id()))
+            .collect();
+
         let mut change = Change::new();
-        change.set_roots(vec![
-            source_root,
+        let mut library_roots = vec![
             create_source_root("std", std_id),
             create_source_root("core", core_id),
             create_source_root("alloc", alloc_id),
             oort_api_source_root,
-        ]);
+        ];
+        for (name, _, id) in allowed_crate_ids.iter() {
+            library_roots.push(create_source_root(name, *id));
+        }
+        let mut roots = vec![user_files.source_root()];
+        roots.extend(library_roots.iter().cloned());
+        change.set_roots(roots);
         let mut crate_graph = CrateGraph::default();
         let my_crate = create_crate(&mut crate_graph, "user", file_id, local_origin.clone());
         let std_crate = create_crate(
@@ -138,6 +161,14 @@ impl yew_agent::Worker for AnalyzerAgent {
         crate_graph.add_dep(std_crate, alloc_dep.clone()).unwrap();
         crate_graph.add_dep(alloc_crate, core_dep.clone()).unwrap();
 
+        for (name, src, id) in allowed_crate_ids.iter() {
+            let krate = create_crate(&mut crate_graph, name, *id, local_origin.clone());
+            let dep = Dependency::new(CrateName::new(name).unwrap(), krate);
+            crate_graph.add_dep(krate, core_dep.clone()).unwrap();
+            crate_graph.add_dep(my_crate, dep).unwrap();
+            change.change_file(*id, Some(Arc::from(*src)));
+        }
+
         crate_graph.add_dep(my_crate, core_dep).unwrap();
         crate_graph.add_dep(my_crate, alloc_dep).unwrap();
         crate_graph.add_dep(my_crate, std_dep).unwrap();
@@ -177,6 +208,8 @@ impl yew_agent::Worker for AnalyzerAgent {
         Self {
             link,
             analysis_host: host,
+            user_files,
+            library_roots,
         }
     }
 
@@ -187,6 +220,11 @@ impl yew_agent::Worker for AnalyzerAgent {
         let response = match request {
             Request::Diagnostics(text) => self.diagnostics(text),
             Request::Completion(line, col) => self.completion(line, col),
+            Request::Format(text) => Some(Response::Format(oort_formatter::format(&text).ok())),
+            Request::Hover(id, line, col) => self.hover(id, line, col),
+            Request::Definition(id, line, col) => self.definition(id, line, col),
+            Request::SignatureHelp(id, line, col) => self.signature_help(id, line, col),
+            Request::InlayHints(id) => self.inlay_hints(id),
         };
         if let Some(msg) = response {
             self.link.respond(who, msg);
@@ -200,37 +238,40 @@ impl yew_agent::Worker for AnalyzerAgent {
 
 impl AnalyzerAgent {
     fn diagnostics(&mut self, text: String) -> Option<Response> {
-        let file_id = ide::FileId(0);
         let mut change = ide::Change::new();
-        change.change_file(file_id, Some(Arc::from(text)));
+        if self.user_files.update(&text, &mut change) {
+            let mut roots = vec![self.user_files.source_root()];
+            roots.extend(self.library_roots.iter().cloned());
+            change.set_roots(roots);
+        }
         self.analysis_host.apply_change(change);
         let analysis = self.analysis_host.analysis();
-        let line_index = analysis.file_line_index(file_id).unwrap();
-        let diagnostics = analysis.diagnostics(
-            &DiagnosticsConfig::test_sample(),
-            ide::AssistResolveStrategy::None,
-            file_id,
-        );
-        match diagnostics {
-            Ok(diags) => {
-                log::info!("Diagnostics: {:?}", diags);
-                let diags = diags
-                    .iter()
-                    .map(|diag| translate_diagnostic(diag, line_index.as_ref()))
-                    .collect();
-                log::info!("Translated diagnostics: {:?}", diags);
-                Some(Response::Diagnostics(diags))
-            }
-            Err(e) => {
-                log::error!("Error getting diagnostics: {:?}", e);
-                None
+        let mut result = vec![];
+        for file_id in self.user_files.ids() {
+            let line_index = analysis.file_line_index(file_id).unwrap();
+            let diagnostics = analysis.diagnostics(
+                &DiagnosticsConfig::test_sample(),
+                ide::AssistResolveStrategy::None,
+                file_id,
+            );
+            match diagnostics {
+                Ok(diags) => {
+                    log::info!("Diagnostics: {:?}", diags);
+                    result.extend(diags.iter().filter_map(|diag| {
+                        self.translate_diagnostic(diag, file_id, line_index.as_ref())
+                    }));
+                }
+                Err(e) => {
+                    log::error!("Error getting diagnostics: {:?}", e);
+                    return None;
+                }
             }
         }
+        log::info!("Translated diagnostics: {:?}", result);
+        Some(Response::Diagnostics(result))
     }
 
     fn completion(&mut self, line: u32, col: u32) -> Option<Response> {
-        let file_id = ide::FileId(0);
-
         const COMPLETION_CONFIG: CompletionConfig = CompletionConfig {
             enable_postfix_completions: true,
             enable_imports_on_the_fly: true,
@@ -251,18 +292,7 @@ impl AnalyzerAgent {
         };
 
         let analysis = self.analysis_host.analysis();
-        let line_index = analysis.file_line_index(file_id).unwrap();
-
-        let line_col = ide::LineCol {
-            line: line - 1,
-            col: col - 1,
-        };
-        let file_length = analysis.file_text(file_id).unwrap().len();
-        let offset = line_index
-            .offset(line_col)
-            .unwrap_or_default()
-            .min(TextSize::from(file_length as u32));
-        let pos = ide::FilePosition { file_id, offset };
+        let pos = self.file_position(&analysis, line, col);
 
         let items = match analysis.completions(&COMPLETION_CONFIG, pos, None).unwrap() {
             Some(items) => items,
@@ -291,16 +321,181 @@ impl AnalyzerAgent {
 
         Some(Response::Completion(results))
     }
-}
 
-fn translate_diagnostic(diag: &ide::Diagnostic, line_index: &LineIndex) -> Diagnostic {
-    let start = line_index.line_col(diag.range.start());
-    let end = line_index.line_col(diag.range.end());
-    Diagnostic {
-        message: diag.message.clone(),
-        start_line: start.line,
-        start_column: start.col,
-        end_line: end.line,
-        end_column: end.col,
+    fn hover(&mut self, id: u64, line: u32, col: u32) -> Option<Response> {
+        const HOVER_CONFIG: HoverConfig = HoverConfig {
+            links_in_hover: false,
+            memory_layout: None,
+            documentation: true,
+            keywords: true,
+            format: HoverDocFormat::Markdown,
+        };
+
+        let analysis = self.analysis_host.analysis();
+        let pos = self.file_position(&analysis, line, col);
+        let line_index = analysis.file_line_index(pos.file_id).unwrap();
+        let range = FileRange {
+            file_id: pos.file_id,
+            range: TextRange::empty(pos.offset),
+        };
+        let hover = match analysis.hover(&HOVER_CONFIG, range) {
+            Ok(hover) => hover.and_then(|hover| {
+                Some(Hover {
+                    contents: hover.info.markup.to_string(),
+                    range: self.translate_range(pos.file_id, hover.range, line_index.as_ref())?,
+                })
+            }),
+            Err(e) => {
+                log::error!("Error getting hover: {:?}", e);
+                None
+            }
+        };
+        Some(Response::Hover(id, hover))
+    }
+
+    fn definition(&mut self, id: u64, line: u32, col: u32) -> Option<Response> {
+        let analysis = self.analysis_host.analysis();
+        let pos = self.file_position(&analysis, line, col);
+        let targets = match analysis.goto_definition(pos) {
+            Ok(targets) => targets.map(|x| x.info).unwrap_or_default(),
+            Err(e) => {
+                log::error!("Error getting definition: {:?}", e);
+                vec![]
+            }
+        };
+        // The editor can't open oort_api or the standard library.
+        let range = targets
+            .iter()
+            .filter(|target| self.user_files.contains(target.file_id))
+            .find_map(|target| {
+                let line_index = analysis.file_line_index(target.file_id).unwrap();
+                self.translate_range(
+                    target.file_id,
+                    target.focus_range.unwrap_or(target.full_range),
+                    line_index.as_ref(),
+                )
+            });
+        Some(Response::Definition(id, range))
+    }
+
+    fn signature_help(&mut self, id: u64, line: u32, col: u32) -> Option<Response> {
+        let analysis = self.analysis_host.analysis();
+        let pos = self.file_position(&analysis, line, col);
+        let help = match analysis.signature_help(pos) {
+            Ok(help) => help.map(|help| SignatureHelp {
+                label: help.signature.clone(),
+                documentation: help.doc.as_ref().map(|doc| doc.to_string()),
+                parameters: help.parameter_labels().map(|x| x.to_string()).collect(),
+                active_parameter: help.active_parameter.map(|x| x as u32),
+            }),
+            Err(e) => {
+                log::error!("Error getting signature help: {:?}", e);
+                None
+            }
+        };
+        Some(Response::SignatureHelp(id, help))
+    }
+
+    fn inlay_hints(&mut self, id: u64) -> Option<Response> {
+        const INLAY_HINTS_CONFIG: InlayHintsConfig = InlayHintsConfig {
+            render_colons: true,
+            type_hints: true,
+            discriminant_hints: DiscriminantHints::Never,
+            parameter_hints: true,
+            chaining_hints: false,
+            adjustment_hints: AdjustmentHints::Never,
+            adjustment_hints_mode: AdjustmentHintsMode::Prefix,
+            adjustment_hints_hide_outside_unsafe: false,
+            closure_return_type_hints: ClosureReturnTypeHints::Never,
+            closure_capture_hints: false,
+            binding_mode_hints: false,
+            lifetime_elision_hints: LifetimeElisionHints::Never,
+            param_names_for_lifetime_elision_hints: false,
+            hide_named_constructor_hints: true,
+            hide_closure_initialization_hints: true,
+            closure_style: ClosureStyle::ImplFn,
+            max_length: Some(25),
+            closing_brace_hints_min_lines: None,
+        };
+
+        let analysis = self.analysis_host.analysis();
+        let mut result = vec![];
+        for file_id in self.user_files.ids() {
+            let line_index = analysis.file_line_index(file_id).unwrap();
+            let hints = match analysis.inlay_hints(&INLAY_HINTS_CONFIG, file_id, None) {
+                Ok(hints) => hints,
+                Err(e) => {
+                    log::error!("Error getting inlay hints: {:?}", e);
+                    vec![]
+                }
+            };
+            result.extend(hints.iter().filter_map(|hint| {
+                let offset = match hint.position {
+                    InlayHintPosition::Before => hint.range.start(),
+                    InlayHintPosition::After => hint.range.end(),
+                };
+                let line_col = line_index.line_col(offset);
+                Some(InlayHint {
+                    line: self.user_files.to_editor(file_id, line_col.line)?,
+                    column: line_col.col,
+                    label: hint.label.to_string(),
+                    kind: if matches!(hint.kind, InlayKind::Parameter) {
+                        2
+                    } else {
+                        1
+                    },
+                    padding_left: hint.pad_left,
+                    padding_right: hint.pad_right,
+                })
+            }));
+        }
+        Some(Response::InlayHints(id, result))
+    }
+
+    /// Converts a one-based Monaco position to an offset in one of the
+    /// user's files.
+    fn file_position(&self, analysis: &Analysis, line: u32, col: u32) -> FilePosition {
+        let (file_id, line) = self.user_files.to_file(line - 1);
+        let line_index = analysis.file_line_index(file_id).unwrap();
+        let line_col = ide::LineCol { line, col: col - 1 };
+        let file_length = analysis.file_text(file_id).unwrap().len();
+        let offset = line_index
+            .offset(line_col)
+            .unwrap_or_default()
+            .min(TextSize::from(file_length as u32));
+        FilePosition { file_id, offset }
+    }
+
+    /// Converts a range in one of the user's files to the editor.
+    fn translate_range(
+        &self,
+        file_id: FileId,
+        range: TextRange,
+        line_index: &LineIndex,
+    ) -> Option<Range> {
+        let start = line_index.line_col(range.start());
+        let end = line_index.line_col(range.end());
+        Some(Range {
+            start_line: self.user_files.to_editor(file_id, start.line)?,
+            start_column: start.col,
+            end_line: self.user_files.to_editor(file_id, end.line)?,
+            end_column: end.col,
+        })
+    }
+
+    fn translate_diagnostic(
+        &self,
+        diag: &ide::Diagnostic,
+        file_id: FileId,
+        line_index: &LineIndex,
+    ) -> Option<Diagnostic> {
+        let range = self.translate_range(file_id, diag.range, line_index)?;
+        Some(Diagnostic {
+            message: diag.message.clone(),
+            start_line: range.start_line,
+            start_column: range.start_column,
+            end_line: range.end_line,
+            end_column: range.end_column,
+        })
     }
 }
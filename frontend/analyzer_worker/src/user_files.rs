@@ -0,0 +1,101 @@
+// The editor shows multi-file AIs joined together by oort_multifile. The
+// analyzer sees them as separate files so that `mod` declarations resolve.
+use ide::{Change, FileId, SourceRoot};
+use ide_db::base_db::{FileSet, VfsPath};
+use std::collections::HashMap;
+use triomphe::Arc;
+
+/// The crate root, lib.rs.
+pub const ROOT_FILE_ID: FileId = FileId(0);
+
+/// Ids below this are used for the standard library and other crates.
+const FIRST_MODULE_FILE_ID: u32 = 1000;
+
+pub struct UserFiles {
+    /// Id of each file, by path relative to the crate root.
+    ids: HashMap<String, FileId>,
+    next_id: u32,
+    /// The file and zero-based line in that file for each editor line.
+    lines: Vec<(FileId, u32)>,
+}
+
+impl UserFiles {
+    pub fn new() -> Self {
+        Self {
+            ids: HashMap::from([("lib.rs".to_string(), ROOT_FILE_ID)]),
+            next_id: FIRST_MODULE_FILE_ID,
+            lines: vec![],
+        }
+    }
+
+    pub fn source_root(&self) -> SourceRoot {
+        let mut file_set = FileSet::default();
+        for (path, id) in self.ids.iter() {
+            file_set.insert(*id, VfsPath::new_virtual_path(format!("/my_crate/{path}")));
+        }
+        SourceRoot::new_local(file_set)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = FileId> + '_ {
+        self.ids.values().copied()
+    }
+
+    pub fn contains(&self, file_id: FileId) -> bool {
+        self.ids.values().any(|id| *id == file_id)
+    }
+
+    /// Splits the editor text into files and records them in `change`.
+    /// Returns true if files were added or removed, in which case the source
+    /// root needs to be replaced too.
+    pub fn update(&mut self, text: &str, change: &mut Change) -> bool {
+        let files = oort_multifile::split(text);
+        let mut changed = false;
+        self.ids.retain(|path, id| {
+            let keep = files.contains_key(path);
+            if !keep {
+                change.change_file(*id, None);
+                changed = true;
+            }
+            keep
+        });
+        for (path, src) in files {
+            let id = *self.ids.entry(path).or_insert_with(|| {
+                changed = true;
+                self.next_id += 1;
+                FileId(self.next_id - 1)
+            });
+            change.change_file(id, Some(Arc::from(src)));
+        }
+        self.lines = oort_multifile::line_map(text)
+            .into_iter()
+            .map(|(path, line)| (self.ids[&path], line as u32 - 1))
+            .collect();
+        changed
+    }
+
+    /// Maps a zero-based editor line to a file and line in that file.
+    pub fn to_file(&self, line: u32) -> (FileId, u32) {
+        match self.lines.get(line as usize) {
+            Some(x) => *x,
+            // Past the last line, which may be empty.
+            None => match self.lines.last() {
+                Some((file_id, last)) => (*file_id, last + line + 1 - self.lines.len() as u32),
+                None => (ROOT_FILE_ID, line),
+            },
+        }
+    }
+
+    /// Maps a zero-based line in a file to an editor line.
+    pub fn to_editor(&self, file_id: FileId, line: u32) -> Option<u32> {
+        if let Some(i) = self.lines.iter().position(|x| *x == (file_id, line)) {
+            return Some(i as u32);
+        }
+        match self.lines.last() {
+            Some((last_file_id, last)) if *last_file_id == file_id && line > *last => {
+                Some(self.lines.len() as u32 + line - last - 1)
+            }
+            None if file_id == ROOT_FILE_ID => Some(line),
+            _ => None,
+        }
+    }
+}
@@ -247,4 +247,13 @@ impl f64 {
     }
 }
 
+pub mod ops {
+    #[lang = "deref"]
+    pub trait Deref {
+        #[lang = "deref_target"]
+        type Target;
+        fn deref(&self) -> &Self::Target;
+    }
+}
+
 pub mod prelude {}
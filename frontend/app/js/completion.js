@@ -1,3 +1,12 @@
+function toMonacoRange(range) {
+  return {
+    startLineNumber: range.start_line + 1,
+    startColumn: range.start_column + 1,
+    endLineNumber: range.end_line + 1,
+    endColumn: range.end_column + 1,
+  };
+}
+
 export function init() {
   console.log("Initializing completions");
   monaco.languages.registerCompletionItemProvider("rust", {
@@ -5,4 +14,109 @@ export function init() {
       return model.completer.complete(position);
     },
   });
+
+  monaco.languages.registerHoverProvider("rust", {
+    provideHover: async function (model, position) {
+      const hover = await model.completer.hover(position);
+      if (!hover) {
+        return null;
+      }
+      return {
+        contents: [{ value: hover.contents }],
+        range: toMonacoRange(hover.range),
+      };
+    },
+  });
+
+  monaco.languages.registerDefinitionProvider("rust", {
+    provideDefinition: async function (model, position) {
+      const range = await model.completer.definition(position);
+      if (!range) {
+        return null;
+      }
+      return { uri: model.uri, range: toMonacoRange(range) };
+    },
+  });
+
+  monaco.languages.registerSignatureHelpProvider("rust", {
+    signatureHelpTriggerCharacters: ["(", ","],
+    signatureHelpRetriggerCharacters: [","],
+    provideSignatureHelp: async function (model, position) {
+      const help = await model.completer.signature_help(position);
+      if (!help) {
+        return null;
+      }
+      return {
+        value: {
+          signatures: [
+            {
+              label: help.label,
+              documentation: help.documentation
+                ? { value: help.documentation }
+                : undefined,
+              parameters: help.parameters.map((label) => ({ label })),
+            },
+          ],
+          activeSignature: 0,
+          activeParameter: help.active_parameter ?? 0,
+        },
+        dispose: function () {},
+      };
+    },
+  });
+
+  monaco.languages.registerCodeActionProvider("rust", {
+    provideCodeActions: function (model, range) {
+      const fixes = (model.quickFixes || []).filter(
+        (fix) =>
+          fix.line_start <= range.endLineNumber &&
+          fix.line_end >= range.startLineNumber
+      );
+      return {
+        actions: fixes.map((fix) => {
+          const textEdit = {
+            range: {
+              startLineNumber: fix.line_start,
+              startColumn: fix.column_start,
+              endLineNumber: fix.line_end,
+              endColumn: fix.column_end,
+            },
+            text: fix.text,
+          };
+          return {
+            title: fix.title,
+            kind: "quickfix",
+            isPreferred: true,
+            edit: {
+              edits: [
+                {
+                  resource: model.uri,
+                  // Older Monaco releases call this "edit".
+                  edit: textEdit,
+                  textEdit,
+                },
+              ],
+            },
+          };
+        }),
+        dispose: function () {},
+      };
+    },
+  });
+
+  monaco.languages.registerInlayHintsProvider("rust", {
+    provideInlayHints: async function (model) {
+      const hints = (await model.completer.inlay_hints()) || [];
+      return {
+        hints: hints.map((hint) => ({
+          position: { lineNumber: hint.line + 1, column: hint.column + 1 },
+          label: hint.label,
+          kind: hint.kind,
+          paddingLeft: hint.padding_left,
+          paddingRight: hint.padding_right,
+        })),
+        dispose: function () {},
+      };
+    },
+  });
 }
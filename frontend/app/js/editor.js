@@ -0,0 +1,23 @@
+export function getLanguage(model) {
+  // Older Monaco releases call this getModeId.
+  return model.getLanguageId ? model.getLanguageId() : model.getModeId();
+}
+
+export function setLanguage(model, language) {
+  if (!monaco.languages.getLanguages().some((x) => x.id == language)) {
+    monaco.languages.register({ id: language });
+  }
+  monaco.editor.setModelLanguage(model, language);
+}
+
+// Replaces the whole text as a single undoable edit, which keeps the cursor
+// and scroll position.
+export function replaceText(editor, source, text) {
+  const model = editor.getModel();
+  if (model.getValue() == text) {
+    return;
+  }
+  editor.pushUndoStop();
+  editor.executeEdits(source, [{ range: model.getFullModelRange(), text }]);
+  editor.pushUndoStop();
+}
@@ -6,6 +6,18 @@ const pickerOpts = {
         "text/plain": [".rs"],
       },
     },
+    {
+      description: "C and C++",
+      accept: {
+        "text/plain": [".c", ".cc", ".cpp", ".cxx"],
+      },
+    },
+    {
+      description: "Zig",
+      accept: {
+        "text/plain": [".zig"],
+      },
+    },
   ],
   excludeAcceptAllOption: true,
   multiple: false,
@@ -16,6 +28,10 @@ export class FileHandle {
     this._handle = handle;
   }
 
+  get name() {
+    return this._handle.name;
+  }
+
   async read() {
     if ("getFile" in this._handle) {
       let file = await this._handle.getFile();
@@ -34,3 +50,30 @@ export async function open() {
   }
   return new FileHandle(handle);
 }
+
+export function download(filename, text) {
+  const blob = new Blob([text], { type: "application/json" });
+  const url = URL.createObjectURL(blob);
+  const a = document.createElement("a");
+  a.href = url;
+  a.download = filename;
+  a.click();
+  URL.revokeObjectURL(url);
+}
+
+// Resolves to the text of a file chosen by the user.
+export function openArchive() {
+  return new Promise((resolve, reject) => {
+    const input = document.createElement("input");
+    input.type = "file";
+    input.accept = ".json,application/json";
+    input.onchange = () => {
+      if (input.files.length == 0) {
+        reject("No file selected");
+      } else {
+        input.files[0].text().then(resolve, reject);
+      }
+    };
+    input.click();
+  });
+}
@@ -2,7 +2,6 @@ use oort_simulation_worker::SimAgent;
 use oort_simulator::snapshot::{Snapshot, Timing};
 use rand::Rng;
 use sha2::{Digest, Sha256};
-use std::rc::Rc;
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
 
@@ -42,12 +41,14 @@ impl Component for Benchmark {
             let link = context.link().clone();
             move |e| link.send_message(Msg::ReceivedSimAgentResponse(e))
         };
-        let mut sim_agent = SimAgent::bridge(Rc::new(cb));
+        let mut sim_agent = SimAgent::bridge(oort_simulation_worker::decode_responses(cb));
         sim_agent.send(oort_simulation_worker::Request::StartScenario {
             scenario_name: scenario_name.clone(),
+            custom_scenario: oort_simulator::scenario::custom::lookup(&scenario_name),
             seed,
             codes,
             nonce,
+            record_history: false,
         });
         sim_agent.send(oort_simulation_worker::Request::Snapshot {
             ticks: BATCH_SIZE as u32,
@@ -99,6 +100,7 @@ impl Component for Benchmark {
                     true
                 }
             }
+            Msg::ReceivedSimAgentResponse(oort_simulation_worker::Response::Seek { .. }) => false,
         }
     }
 
@@ -0,0 +1,165 @@
+// Runs the background seed sweep for two versions of the player's code and
+// summarizes which one did better on each seed.
+use oort_simulation_worker::SimAgent;
+use oort_simulator::scenario::{Status, MAX_TICKS};
+use oort_simulator::simulation::{Code, PHYSICS_TICK_LENGTH};
+use oort_simulator::snapshot::Snapshot;
+use std::collections::BTreeMap;
+use yew::prelude::*;
+use yew_agent::{Bridge, Bridged};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Variant {
+    /// The saved version being compared against.
+    Baseline,
+    /// The code in the editor.
+    Current,
+}
+
+pub struct Comparison {
+    agents: BTreeMap<(Variant, u32), Box<dyn Bridge<SimAgent>>>,
+    results: BTreeMap<(Variant, u32), Snapshot>,
+    nonce: u32,
+}
+
+impl Comparison {
+    /// Starts simulations of both versions on each seed. `on_response` is
+    /// called with the responses, which should be passed to `on_snapshot`.
+    pub fn start(
+        scenario_name: &str,
+        seeds: impl Iterator<Item = u32>,
+        current_codes: Vec<Code>,
+        baseline_codes: Vec<Code>,
+        nonce: u32,
+        on_response: Callback<(Variant, u32, oort_simulation_worker::Response)>,
+    ) -> Self {
+        let mut agents = BTreeMap::new();
+        for seed in seeds {
+            for (variant, codes) in [
+                (Variant::Baseline, &baseline_codes),
+                (Variant::Current, &current_codes),
+            ] {
+                let cb = {
+                    let on_response = on_response.clone();
+                    move |e| on_response.emit((variant, seed, e))
+                };
+                let mut agent = SimAgent::bridge(oort_simulation_worker::decode_responses(cb));
+                agent.send(oort_simulation_worker::Request::StartScenario {
+                    scenario_name: scenario_name.to_string(),
+                    custom_scenario: oort_simulator::scenario::custom::lookup(scenario_name),
+                    seed,
+                    codes: codes.clone(),
+                    nonce,
+                    record_history: false,
+                });
+                agents.insert((variant, seed), agent);
+            }
+        }
+        Self {
+            agents,
+            results: BTreeMap::new(),
+            nonce,
+        }
+    }
+
+    /// Handles a snapshot from one of the simulations. Returns true if that
+    /// simulation finished.
+    pub fn on_snapshot(&mut self, variant: Variant, seed: u32, snapshot: Snapshot) -> bool {
+        if snapshot.nonce != self.nonce {
+            return false;
+        }
+        if snapshot.status == Status::Running
+            && snapshot.time < (MAX_TICKS as f64 * PHYSICS_TICK_LENGTH)
+            && snapshot.errors.is_empty()
+        {
+            if let Some(agent) = self.agents.get_mut(&(variant, seed)) {
+                agent.send(oort_simulation_worker::Request::Snapshot {
+                    ticks: 100,
+                    nonce: self.nonce,
+                });
+            }
+            false
+        } else {
+            self.agents.remove(&(variant, seed));
+            self.results.insert((variant, seed), snapshot);
+            true
+        }
+    }
+
+    pub fn view(&self, make_seed_link: impl Fn(u32) -> Html) -> Html {
+        let seeds: Vec<u32> = self
+            .agents
+            .keys()
+            .chain(self.results.keys())
+            .map(|(_, seed)| *seed)
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut wins = [0, 0];
+        let rows = seeds
+            .iter()
+            .map(|&seed| {
+                let baseline = self.results.get(&(Variant::Baseline, seed));
+                let current = self.results.get(&(Variant::Current, seed));
+                let winner = match (baseline.map(score), current.map(score)) {
+                    (Some(a), Some(b)) if a < b => {
+                        wins[0] += 1;
+                        "Baseline"
+                    }
+                    (Some(a), Some(b)) if b < a => {
+                        wins[1] += 1;
+                        "Current"
+                    }
+                    (Some(_), Some(_)) => "Tie",
+                    _ => "",
+                };
+                html! {
+                    <tr>
+                        <td>{ make_seed_link(seed) }</td>
+                        <td>{ outcome(baseline) }</td>
+                        <td>{ outcome(current) }</td>
+                        <td>{ winner }</td>
+                    </tr>
+                }
+            })
+            .collect::<Html>();
+
+        let finished = self.results.len() / 2;
+        html! {
+            <>
+                <span>
+                    { "Current code won " }{ wins[1] }{ ", baseline won " }{ wins[0] }
+                    { " of " }{ seeds.len() }{ " seeds" }
+                    { if self.agents.is_empty() { String::new() } else { format!(" ({finished} finished)") } }
+                </span>
+                <table class="comparison">
+                    <tr><th>{ "Seed" }</th><th>{ "Baseline" }</th><th>{ "Current" }</th><th>{ "Better" }</th></tr>
+                    { rows }
+                </table>
+            </>
+        }
+    }
+}
+
+/// Orders results so that lower is better: victories by time, then draws,
+/// then defeats.
+fn score(snapshot: &Snapshot) -> (u8, i64) {
+    match snapshot.status {
+        Status::Victory { team: 0 } => (0, (snapshot.score_time / PHYSICS_TICK_LENGTH) as i64),
+        Status::Draw => (1, 0),
+        _ => (2, 0),
+    }
+}
+
+fn outcome(snapshot: Option<&Snapshot>) -> String {
+    match snapshot {
+        None => "...".to_string(),
+        Some(snapshot) if !snapshot.errors.is_empty() => "Error".to_string(),
+        Some(snapshot) => match snapshot.status {
+            Status::Victory { team: 0 } => format!("{:.3}s", snapshot.score_time),
+            Status::Draw | Status::Running => "Draw".to_string(),
+            Status::Victory { .. } | Status::Failed => "Defeat".to_string(),
+        },
+    }
+}
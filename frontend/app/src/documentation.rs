@@ -20,18 +20,23 @@ pub fn documentation(props: &DocumentationProps) -> Html {
             <h2>{ "Basics" }</h2>
             { "Select a scenario from the list in the top-right of the page." }<br/>
             { "Click the run button in the editor to start the scenario with a new version of your code." }<br/>
+            { "Click the bolt button (or press Ctrl-Alt-Enter) to swap new code into the running simulation without restarting it." }<br/>
 
             <h2>{ "Controls" }</h2>
             <ul>
                 <li>{ "W/A/S/D: Pan the camera." }</li>
                 <li>{ "Space: Pause/resume." }</li>
                 <li>{ "N: Single-step (advance time by one tick and then pause)." }</li>
+                <li>{ "Comma: Step backward one tick. Drag the timeline at the bottom of the simulation window to rewind to an earlier tick." }</li>
                 <li>{ "F: Fast-forward." }</li>
                 <li>{ "M: Slow motion." }</li>
                 <li>{ "G: Show debug lines for all ships." }</li>
                 <li>{ "C: Chase, or follow the selected ship." }</li>
+                <li>{ "P: Pilot the selected ship with the keyboard while its AI handles the rest. W/S thrust, A/D turn, Q/E strafe and Enter fires. Press P again to hand it back. Runs with a piloted ship don't count as completing the mission." }</li>
                 <li>{ "V: Toggle NLIPS, which makes smaller ships more visible when zoomed out." }</li>
                 <li>{ "B: Toggle postprocessing (blur)." }</li>
+                <li>{ "O: Toggle the minimap. Click or drag on it to move the camera." }</li>
+                <li>{ "T: Toggle tactical mode, which draws ship class icons at a constant size regardless of zoom." }</li>
                 <li>{ "Mouse wheel: Zoom." }</li>
                 <li>{ "Mouse click: Select a ship to show debugging info." }</li>
             </ul>
@@ -172,7 +177,14 @@ pub fn documentation(props: &DocumentationProps) -> Html {
               <li><code>{ "draw_square(center: Vec2, radius: f64, color: u32)" }</code>{ ": Draw a square visible when the ship is selected." }</li>
               <li><code>{ "draw_diamond(center: Vec2, radius: f64, color: u32)" }</code>{ ": Draw a diamond visible when the ship is selected." }</li>
               <li><code>{ "draw_polygon(center: Vec2, radius: f64, sides: i32, angle: f64, color: u32)" }</code>{ ": Draw a regular polygon visible when the ship is selected." }</li>
+              <li><code>{ "draw_circle(center: Vec2, radius: f64, color: u32)" }</code>{ ": Draw a circle visible when the ship is selected." }</li>
+              <li><code>{ "draw_arc(center: Vec2, radius: f64, start_angle: f64, end_angle: f64, color: u32)" }</code>{ ": Draw an arc visible when the ship is selected." }</li>
+              <li><code>{ "fill_circle" }</code>{ ", " }<code>{ "fill_polygon" }</code>{ ", " }<code>{ "fill_triangle" }</code>{ ", " }<code>{ "fill_square" }</code>{ ", " }<code>{ "fill_diamond" }</code>{ ": Filled versions of the shapes above." }</li>
+              <li><code>{ "set_layer(name: &str)" }</code>{ ": Put the following shapes on a named layer that can be hidden in the UI." }</li>
+              <li><code>{ "set_ttl(ticks: u32)" }</code>{ ": Keep the following shapes visible for several ticks instead of redrawing them every tick." }</li>
               <li><code>{ "draw_text!(topleft: Vec2, color: u32, ...)" }</code>{ ": Draw text. Works like " }<code>{ "println!" }</code>{ "." }</li>
+              <li><code>{ "watch(name: &str, value: f64)" }</code>{ ": Show the current value of a variable when the ship is selected." }</li>
+              <li><code>{ "plot(name: &str, value: f64)" }</code>{ ": Add a point to a chart of the value over time, shown when the ship is selected." }</li>
             </ul>
 
             <h2>{ "Miscellaneous" }</h2>
@@ -192,6 +204,8 @@ pub fn documentation(props: &DocumentationProps) -> Html {
                 <li><a href="https://docs.rs/byteorder/1.4.3/byteorder/index.html" target="_blank">{ "byteorder" }</a>{ ": Utilities to read and write binary data, useful for radio." }</li>
                 <li><a href="https://docs.rs/maths-rs/0.2.4/maths_rs/index.html" target="_blank">{ "maths_rs" }</a>{ ": A linear algebra library." }</li>
                 <li><a href="https://docs.rs/oorandom/11.1.3/oorandom/index.html" target="_blank">{ "oorandom" }</a>{ ": A random number generation library." }</li>
+                <li><a href="https://docs.rs/arrayvec/0.7.4/arrayvec/index.html" target="_blank">{ "arrayvec" }</a>{ ": Vectors and strings with fixed capacity." }</li>
+                <li><a href="https://docs.rs/nalgebra/0.32.3/nalgebra/index.html" target="_blank">{ "nalgebra" }</a>{ ": A general-purpose linear algebra library." }</li>
             </ul>
 
             <h2>{ "Ship Classes" }</h2>
@@ -42,6 +42,10 @@ pub enum Msg {
         resolve: Function,
         reject: Function,
     },
+    RequestLanguageFeature {
+        request: analyzer_stub::Request,
+        resolve: Function,
+    },
     LoadedCodeFromDisk(String),
     OpenedFile(FileHandle),
     LinkedFile(FileHandle),
@@ -67,6 +71,7 @@ pub struct EditorWindow {
     #[allow(dead_code)]
     analyzer_interval: Interval,
     current_completion: Option<(Function, Function)>,
+    pending_language_features: PendingLanguageFeatures,
     folded: bool,
     file_handle: Option<FileHandle>,
     linked: bool,
@@ -94,6 +99,7 @@ impl Component for EditorWindow {
             analyzer_agent,
             analyzer_interval,
             current_completion: None,
+            pending_language_features: Default::default(),
             folded: false,
             file_handle: None,
             linked: false,
@@ -168,24 +174,37 @@ impl Component for EditorWindow {
                 self.toggle_fold();
                 false
             }
-            Msg::EditorAction(action) => {
-                context.props().on_editor_action.emit(action);
+            Msg::EditorAction(ref action) if action == "oort-format" && is_online() => {
+                // Use rustfmt on the compiler service.
+                context.props().on_editor_action.emit(action.clone());
                 false
             }
-            Msg::RequestAnalyzer => {
+            Msg::EditorAction(ref action) if action == "oort-format" => {
+                // The offline formatter only understands Rust.
+                let language = get_editor_language(&context.props().editor_link);
+                if language != "rust" {
+                    log::warn!("Offline formatting isn't supported for {language}");
+                    return false;
+                }
                 let text = self
                     .editor_link
                     .with_editor(|editor| editor.get_model().unwrap().get_value())
                     .unwrap();
-                if text != self.last_analyzed_text {
-                    if crate::game::is_encrypted(&Code::Rust(text.clone())) {
-                        self.display_analyzer_diagnostics(context, &[]);
-                    } else {
-                        self.analyzer_agent
-                            .send(analyzer_stub::Request::Diagnostics(text.clone()));
-                    }
-                    self.last_analyzed_text = text;
-                }
+                self.analyzer_agent
+                    .send(analyzer_stub::Request::Format(text));
+                false
+            }
+            Msg::EditorAction(ref action) if action.starts_with(LANGUAGE_ACTION_PREFIX) => {
+                let language = &action[LANGUAGE_ACTION_PREFIX.len()..];
+                set_editor_language(&context.props().editor_link, language);
+                false
+            }
+            Msg::EditorAction(action) => {
+                context.props().on_editor_action.emit(action);
+                false
+            }
+            Msg::RequestAnalyzer => {
+                self.update_analyzer(context);
                 false
             }
             Msg::AnalyzerResponse(analyzer_stub::Response::Diagnostics(diags)) => {
@@ -216,6 +235,56 @@ impl Component for EditorWindow {
                 }
                 false
             }
+            Msg::AnalyzerResponse(analyzer_stub::Response::Format(Some(text))) => {
+                self.editor_link.with_editor(|editor| {
+                    let ed: &monaco::sys::editor::IStandaloneCodeEditor = editor.as_ref();
+                    js::editor::replace_text(ed, "oort-format", &text);
+                });
+                false
+            }
+            Msg::AnalyzerResponse(analyzer_stub::Response::Format(None)) => {
+                log::warn!("Offline formatter failed");
+                false
+            }
+            Msg::RequestLanguageFeature { request, resolve } => {
+                let (id, slot) = self.pending_language_features.slot(&request);
+                // Monaco has moved on from the previous request.
+                if let Some((_, previous)) = slot.replace((id, resolve)) {
+                    previous.call1(&JsValue::null(), &JsValue::null()).unwrap();
+                }
+                // Make sure the analyzer has the latest text.
+                self.update_analyzer(context);
+                self.analyzer_agent.send(request);
+                false
+            }
+            Msg::AnalyzerResponse(analyzer_stub::Response::Hover(id, hover)) => {
+                resolve_language_feature(&mut self.pending_language_features.hover, id, &hover);
+                false
+            }
+            Msg::AnalyzerResponse(analyzer_stub::Response::Definition(id, range)) => {
+                resolve_language_feature(
+                    &mut self.pending_language_features.definition,
+                    id,
+                    &range,
+                );
+                false
+            }
+            Msg::AnalyzerResponse(analyzer_stub::Response::SignatureHelp(id, help)) => {
+                resolve_language_feature(
+                    &mut self.pending_language_features.signature_help,
+                    id,
+                    &help,
+                );
+                false
+            }
+            Msg::AnalyzerResponse(analyzer_stub::Response::InlayHints(id, hints)) => {
+                resolve_language_feature(
+                    &mut self.pending_language_features.inlay_hints,
+                    id,
+                    &hints,
+                );
+                false
+            }
             Msg::LoadedCodeFromDisk(text) => {
                 let editor_link = context.props().editor_link.clone();
                 editor_link.with_editor(|editor| {
@@ -227,11 +296,13 @@ impl Component for EditorWindow {
                 false
             }
             Msg::OpenedFile(file_handle) => {
+                set_language_from_file_name(&context.props().editor_link, &file_handle.name());
                 self.file_handle = Some(file_handle);
                 self.linked = false;
                 false
             }
             Msg::LinkedFile(file_handle) => {
+                set_language_from_file_name(&context.props().editor_link, &file_handle.name());
                 self.file_handle = Some(file_handle);
                 self.linked = true;
                 context.link().send_message(Msg::CheckLinkedFile);
@@ -313,6 +384,10 @@ impl Component for EditorWindow {
             .props()
             .on_editor_action
             .reform(|_| "oort-replay-paused".to_string());
+        let hot_reload_cb = context
+            .props()
+            .on_editor_action
+            .reform(|_| "oort-hot-reload".to_string());
         let cmd_or_ctrl = if is_mac() { "Cmd" } else { "Ctrl" };
 
         create_portal(
@@ -336,6 +411,11 @@ impl Component for EditorWindow {
                         class="material-symbols-outlined"
                         title={"Replay paused"}
                     >{ "autopause" }</span></div>
+                    <div class="hot_reload_button"><span
+                        onclick={hot_reload_cb}
+                        class="material-symbols-outlined"
+                        title={format!("Reload code into the running simulation ({cmd_or_ctrl}-Alt-Enter)")}
+                    >{ "bolt" }</span></div>
                     <form>
                         <div class="drop_target display_none" ref={self.drop_target_ref.clone()}>
                             <span for="file" ondrop={context.link().callback(Msg::Drop)}>
@@ -423,6 +503,16 @@ impl Component for EditorWindow {
 
                 add_action("oort-replay-paused", "Replay paused", None);
 
+                add_action(
+                    "oort-hot-reload",
+                    "Reload code into the running simulation",
+                    Some(
+                        monaco::sys::KeyMod::ctrl_cmd() as u32
+                            | monaco::sys::KeyMod::alt() as u32
+                            | monaco::sys::KeyCode::Enter as u32,
+                    ),
+                );
+
                 add_action("oort-restore-initial-code", "Restore initial code", None);
 
                 add_action("oort-load-solution", "Load solution", None);
@@ -461,6 +551,11 @@ impl Component for EditorWindow {
                     "Submit to tournament",
                 );
 
+                add_action_without_context_menu("oort-language-rust", "Set language: Rust");
+                add_action_without_context_menu("oort-language-c", "Set language: C");
+                add_action_without_context_menu("oort-language-cpp", "Set language: C++");
+                add_action_without_context_menu("oort-language-zig", "Set language: Zig");
+
                 {
                     let ed: &monaco::sys::editor::IStandaloneCodeEditor = editor.as_ref();
                     let options = monaco::sys::editor::IEditorOptions::from(empty());
@@ -493,6 +588,22 @@ impl Component for EditorWindow {
 }
 
 impl EditorWindow {
+    fn update_analyzer(&mut self, context: &Context<Self>) {
+        let text = self
+            .editor_link
+            .with_editor(|editor| editor.get_model().unwrap().get_value())
+            .unwrap();
+        if text != self.last_analyzed_text {
+            if crate::game::is_encrypted(&Code::Rust(text.clone())) {
+                self.display_analyzer_diagnostics(context, &[]);
+            } else {
+                self.analyzer_agent
+                    .send(analyzer_stub::Request::Diagnostics(text.clone()));
+            }
+            self.last_analyzed_text = text;
+        }
+    }
+
     pub fn display_analyzer_diagnostics(
         &mut self,
         _context: &Context<Self>,
@@ -587,16 +698,116 @@ struct CompletionList {
     pub suggestions: Vec<CompletionItem>,
 }
 
+/// Promises returned to Monaco's language feature providers, waiting on the
+/// analyzer, with the ids of the requests they're waiting on.
+#[derive(Default)]
+struct PendingLanguageFeatures {
+    hover: Option<(u64, Function)>,
+    definition: Option<(u64, Function)>,
+    signature_help: Option<(u64, Function)>,
+    inlay_hints: Option<(u64, Function)>,
+}
+
+impl PendingLanguageFeatures {
+    /// Returns the request id and the slot for its promise.
+    fn slot(&mut self, request: &analyzer_stub::Request) -> (u64, &mut Option<(u64, Function)>) {
+        match *request {
+            analyzer_stub::Request::Hover(id, ..) => (id, &mut self.hover),
+            analyzer_stub::Request::Definition(id, ..) => (id, &mut self.definition),
+            analyzer_stub::Request::SignatureHelp(id, ..) => (id, &mut self.signature_help),
+            analyzer_stub::Request::InlayHints(id) => (id, &mut self.inlay_hints),
+            _ => unreachable!("not a language feature request: {:?}", request),
+        }
+    }
+}
+
+/// Editor actions with this prefix set the language to the rest of the id.
+const LANGUAGE_ACTION_PREFIX: &str = "oort-language-";
+
+/// Property of the editor model holding the language the player picked.
+const CHOSEN_LANGUAGE_PROPERTY: &str = "oortChosenLanguage";
+
+/// Returns the Monaco language id of the editor, which is also the language
+/// name the compiler service accepts.
+pub fn get_editor_language(editor_link: &CodeEditorLink) -> String {
+    editor_link
+        .with_editor(|editor| {
+            let ed: &monaco::sys::editor::IStandaloneCodeEditor = editor.as_ref();
+            js::editor::get_language(&ed.get_model().unwrap())
+        })
+        .unwrap_or_else(|| "rust".to_string())
+}
+
+/// Returns the language the player picked with an action or by opening a
+/// file, if any. Otherwise the compiler service guesses from the code.
+pub fn get_chosen_language(editor_link: &CodeEditorLink) -> Option<String> {
+    editor_link
+        .with_editor(|editor| {
+            let ed: &monaco::sys::editor::IStandaloneCodeEditor = editor.as_ref();
+            js_sys::Reflect::get(
+                &ed.get_model().unwrap(),
+                &JsValue::from_str(CHOSEN_LANGUAGE_PROPERTY),
+            )
+            .ok()
+            .and_then(|language| language.as_string())
+        })
+        .flatten()
+}
+
+fn set_editor_language(editor_link: &CodeEditorLink, language: &str) {
+    editor_link.with_editor(|editor| {
+        let ed: &monaco::sys::editor::IStandaloneCodeEditor = editor.as_ref();
+        let model = ed.get_model().unwrap();
+        js::editor::set_language(&model, language);
+        js_sys::Reflect::set(
+            &model,
+            &JsValue::from_str(CHOSEN_LANGUAGE_PROPERTY),
+            &JsValue::from_str(language),
+        )
+        .unwrap();
+    });
+}
+
+fn set_language_from_file_name(editor_link: &CodeEditorLink, name: &str) {
+    let language = match name.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("rs") => "rust",
+        Some("c") => "c",
+        Some("cc" | "cpp" | "cxx") => "cpp",
+        Some("zig") => "zig",
+        _ => return,
+    };
+    set_editor_language(editor_link, language);
+}
+
+fn resolve_language_feature<T: Serialize>(slot: &mut Option<(u64, Function)>, id: u64, value: &T) {
+    // Drop responses to requests that have been superseded.
+    if !matches!(slot, Some((pending_id, _)) if *pending_id == id) {
+        return;
+    }
+    if let Some((_, resolve)) = slot.take() {
+        resolve
+            .call1(
+                &JsValue::null(),
+                &serde_wasm_bindgen::to_value(value).unwrap(),
+            )
+            .unwrap();
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct Completer {
     link: Scope<EditorWindow>,
+    next_request_id: u64,
 }
 
 #[wasm_bindgen]
 impl Completer {
     fn new(link: Scope<EditorWindow>) -> Self {
-        Self { link }
+        Self {
+            link,
+            next_request_id: 0,
+        }
     }
 
     pub fn complete(&mut self, position: Position) -> js_sys::Promise {
@@ -609,6 +820,44 @@ impl Completer {
             })
         })
     }
+
+    pub fn hover(&mut self, position: Position) -> js_sys::Promise {
+        let (line, col) = (position.line_number() as u32, position.column() as u32);
+        let id = self.next_request_id();
+        self.request(analyzer_stub::Request::Hover(id, line, col))
+    }
+
+    pub fn definition(&mut self, position: Position) -> js_sys::Promise {
+        let (line, col) = (position.line_number() as u32, position.column() as u32);
+        let id = self.next_request_id();
+        self.request(analyzer_stub::Request::Definition(id, line, col))
+    }
+
+    pub fn signature_help(&mut self, position: Position) -> js_sys::Promise {
+        let (line, col) = (position.line_number() as u32, position.column() as u32);
+        let id = self.next_request_id();
+        self.request(analyzer_stub::Request::SignatureHelp(id, line, col))
+    }
+
+    pub fn inlay_hints(&mut self) -> js_sys::Promise {
+        let id = self.next_request_id();
+        self.request(analyzer_stub::Request::InlayHints(id))
+    }
+
+    fn next_request_id(&mut self) -> u64 {
+        self.next_request_id += 1;
+        self.next_request_id
+    }
+
+    fn request(&mut self, request: analyzer_stub::Request) -> js_sys::Promise {
+        let mut request = Some(request);
+        js_sys::Promise::new(&mut |resolve, _reject| {
+            self.link.send_message(Msg::RequestLanguageFeature {
+                request: request.take().unwrap(),
+                resolve,
+            })
+        })
+    }
 }
 
 fn is_mac() -> bool {
@@ -619,6 +868,10 @@ fn is_mac() -> bool {
         .contains("Mac")
 }
 
+fn is_online() -> bool {
+    gloo_utils::window().navigator().on_line()
+}
+
 fn has_open_file_picker() -> bool {
     gloo_utils::window().has_own_property(&"showOpenFilePicker".into())
 }
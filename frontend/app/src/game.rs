@@ -1,7 +1,8 @@
 use crate::codestorage;
+use crate::comparison::{Comparison, Variant};
 use crate::compiler_output_window::CompilerOutputWindow;
 use crate::documentation::Documentation;
-use crate::editor_window::EditorWindow;
+use crate::editor_window::{self, EditorWindow};
 use crate::gtag;
 use crate::js;
 use crate::leaderboard::Leaderboard;
@@ -15,7 +16,10 @@ use crate::userid;
 use crate::versions_window::VersionsWindow;
 use crate::welcome::Welcome;
 use monaco::yew::CodeEditorLink;
-use oort_proto::{LeaderboardSubmission, Telemetry};
+use oort_proto::{
+    CompileFailure, CompileSuccess, CompilerDiagnostic, DiagnosticLevel, LeaderboardSubmission,
+    Telemetry,
+};
 use oort_simulation_worker::SimAgent;
 use oort_simulator::scenario::{self, Status, MAX_TICKS};
 use oort_simulator::simulation;
@@ -24,9 +28,9 @@ use oort_simulator::snapshot::Snapshot;
 use rand::Rng;
 use regex::Regex;
 use reqwasm::http::Request;
+use serde::Serialize;
 use simulation::PHYSICS_TICK_LENGTH;
 use std::collections::HashMap;
-use std::rc::Rc;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
@@ -52,7 +56,7 @@ pub enum Msg {
     EditorAction { team: usize, action: String },
     ShowFeedback,
     DismissOverlay,
-    CompileFinished(Vec<Result<Code, String>>, ExecutionMode),
+    CompileFinished(Vec<Result<CompileOutput, CompileFailure>>, ExecutionMode),
     SubmitToTournament,
     UploadShortcode,
     FormattedCode { team: usize, text: String },
@@ -61,6 +65,11 @@ pub enum Msg {
     Resized,
     LoadVersion(String),
     SaveVersion(String),
+    SwitchBranch(String),
+    CompareVersion(String),
+    StartComparison(String),
+    ReplayComparison(u32),
+    ReceivedComparisonResponse(Variant, u32, oort_simulation_worker::Response),
     RefreshVersions,
     Nop,
 }
@@ -70,6 +79,7 @@ enum Overlay {
     MissionComplete,
     Compiling,
     Feedback,
+    Comparison,
     Error(String),
 }
 
@@ -77,7 +87,25 @@ enum Overlay {
 pub enum ExecutionMode {
     Initial,
     Run,
-    Replay { paused: bool },
+    Replay {
+        paused: bool,
+    },
+    /// Runs the editor code with a saved version shown as a ghost. Without a
+    /// seed, also compares them on the background seeds.
+    Compare {
+        seed: Option<u32>,
+    },
+    /// Swaps a team's code into the running simulation. A run changed this
+    /// way doesn't count as a mission completion.
+    HotReload {
+        team: usize,
+    },
+}
+
+/// A saved version of the player's code to compare against.
+struct Baseline {
+    source_code: Code,
+    compiled_code: Code,
 }
 
 pub struct Game {
@@ -92,11 +120,16 @@ pub struct Game {
     last_snapshot: Option<Snapshot>,
     simulation_window_link: Option<Scope<SimulationWindow>>,
     teams: Vec<Team>,
+    /// Builtin AIs for teams without an editor, which only custom scenarios
+    /// have.
+    extra_team_codes: Vec<Code>,
     editor_links: Vec<CodeEditorLink>,
     compilation_cache: HashMap<Code, Code>,
     previous_seed: Option<u32>,
     versions_update_timestamp: chrono::DateTime<chrono::Utc>,
     execution_mode: ExecutionMode,
+    baseline: Option<Baseline>,
+    comparison: Option<Comparison>,
 }
 
 pub struct Team {
@@ -106,6 +139,8 @@ pub struct Team {
     running_source_code: Code,
     running_compiled_code: Code,
     current_compiler_decorations: js_sys::Array,
+    /// Crash errors currently shown in the editor.
+    displayed_crash_errors: Vec<CompilerError>,
 }
 
 #[derive(Properties, PartialEq, Eq, Debug)]
@@ -147,11 +182,14 @@ impl Component for Game {
             last_snapshot: None,
             simulation_window_link: None,
             teams: Vec::new(),
+            extra_team_codes: Vec::new(),
             editor_links: vec![CodeEditorLink::default(), CodeEditorLink::default()],
             compilation_cache,
             previous_seed: None,
             versions_update_timestamp: chrono::Utc::now(),
             execution_mode: ExecutionMode::Initial,
+            baseline: None,
+            comparison: None,
         }
     }
 
@@ -229,6 +267,13 @@ impl Component for Game {
                 self.start_compile(context, ExecutionMode::Replay { paused: true });
                 true
             }
+            Msg::EditorAction { team, ref action } if action == "oort-hot-reload" => {
+                self.save_current_code(context, &context.props().scenario, None);
+                let code = self.team(team).get_editor_code();
+                self.team_mut(team).running_source_code = code;
+                self.start_compile(context, ExecutionMode::HotReload { team });
+                true
+            }
             Msg::EditorAction { team, ref action } if action == "oort-restore-initial-code" => {
                 let mut code = scenario::load(&context.props().scenario)
                     .initial_code()
@@ -329,6 +374,10 @@ impl Component for Game {
                     false
                 }
             }
+            Msg::ReceivedBackgroundSimAgentResponse(
+                oort_simulation_worker::Response::Seek { .. },
+                _,
+            ) => false,
             Msg::ShowFeedback => {
                 self.overlay = Some(Overlay::Feedback);
                 true
@@ -342,28 +391,49 @@ impl Component for Game {
                 self.background_agents.clear();
                 self.background_snapshots.clear();
                 self.background_nonce = 0;
+                self.comparison = None;
                 self.focus_editor(0);
                 true
             }
-            Msg::CompileFinished(results, execution_mode) => {
+            Msg::CompileFinished(mut results, execution_mode) => {
                 if matches!(self.overlay, Some(Overlay::Compiling)) {
                     self.overlay = None;
                 }
                 if self.compilation_cache.len() > 10 {
                     self.compilation_cache.clear();
                 }
+                let mut baseline_error = None;
+                if matches!(execution_mode, ExecutionMode::Compare { .. }) {
+                    let result = results.pop().expect("missing baseline compile result");
+                    let baseline = self.baseline.as_mut().expect("missing baseline");
+                    match result {
+                        Ok((code, _)) => {
+                            self.compilation_cache
+                                .insert(baseline.source_code.clone(), code.clone());
+                            baseline.compiled_code = code;
+                        }
+                        Err(failure) => {
+                            baseline_error = Some(format!("Baseline version: {}", failure.message));
+                        }
+                    }
+                }
                 let mut teams_with_errors = vec![];
                 for (team, result) in results.iter().enumerate() {
                     match result {
-                        Ok(code) => {
-                            self.team_mut(team).display_compiler_errors(&[]);
+                        Ok((code, diagnostics)) => {
+                            self.team_mut(team)
+                                .display_compiler_errors(&make_diagnostic_errors(diagnostics));
                             self.team_mut(team).running_compiled_code = code.clone();
                             self.compilation_cache
                                 .insert(self.team(team).running_source_code.clone(), code.clone());
                         }
-                        Err(error) => {
-                            self.team_mut(team)
-                                .display_compiler_errors(&make_editor_errors(error));
+                        Err(failure) => {
+                            let errors = if failure.diagnostics.is_empty() {
+                                make_editor_errors(&failure.message)
+                            } else {
+                                make_diagnostic_errors(&failure.diagnostics)
+                            };
+                            self.team_mut(team).display_compiler_errors(&errors);
                             self.team_mut(team).running_compiled_code = Code::None;
                             teams_with_errors.push(team);
                         }
@@ -372,9 +442,17 @@ impl Component for Game {
                 let errors: Vec<_> = results
                     .iter()
                     .filter_map(|x| x.as_ref().err())
-                    .cloned()
+                    .map(|x| x.message.clone())
                     .collect();
-                if errors.is_empty() {
+                if errors.is_empty() && baseline_error.is_some() {
+                    self.compiler_errors = baseline_error;
+                    js::golden_layout::select_tab("compiler_output");
+                } else if let (true, ExecutionMode::HotReload { team }) =
+                    (errors.is_empty(), execution_mode)
+                {
+                    self.hot_reload(team);
+                    self.focus_simulation();
+                } else if errors.is_empty() {
                     services::send_telemetry(Telemetry::StartScenario {
                         scenario_name: context.props().scenario.clone(),
                         code: code_to_string(&self.player_team().running_source_code),
@@ -414,6 +492,86 @@ impl Component for Game {
                 self.save_current_code(context, &context.props().scenario, Some(label));
                 false
             }
+            Msg::SwitchBranch(branch) => {
+                if self.teams.is_empty() {
+                    return false;
+                }
+                // Save the current code on the old branch, then load the
+                // newest version on the new branch. A new branch starts with
+                // the current code.
+                let code = self.player_team().get_editor_code();
+                let encrypted = is_encrypted(&code);
+                let code = code_to_string(&code);
+                let scenario_name = context.props().scenario.clone();
+                try_send_future(context.link(), async move {
+                    let version_control = oort_version_control::VersionControl::new().await?;
+                    if !encrypted && !version_control.check_code_exists(&code).await? {
+                        version_control
+                            .create_version(&oort_version_control::CreateVersionParams {
+                                code: code.clone(),
+                                scenario_name: scenario_name.clone(),
+                                label: None,
+                            })
+                            .await?;
+                    }
+                    version_control
+                        .set_current_branch(&scenario_name, &branch)
+                        .await?;
+                    let newest = version_control
+                        .list_versions(&scenario_name)
+                        .await?
+                        .into_iter()
+                        .filter(|version| version.branch == branch)
+                        .max_by_key(|version| version.timestamp);
+                    let text = match newest {
+                        Some(version) => version_control.get_code(&version.digest).await?,
+                        None => code,
+                    };
+                    Ok::<_, oort_version_control::Error>(Msg::ReplaceCode { team: 0, text })
+                });
+                false
+            }
+            Msg::CompareVersion(id) => {
+                try_send_future(context.link(), async move {
+                    let version_control = oort_version_control::VersionControl::new().await?;
+                    let version = version_control.get_version(&id).await?;
+                    let code = version_control.get_code(&version.digest).await?;
+                    Ok::<_, oort_version_control::Error>(Msg::StartComparison(code))
+                });
+                false
+            }
+            Msg::StartComparison(text) => {
+                self.save_current_code(context, &context.props().scenario, None);
+                for team in self.teams.iter_mut() {
+                    team.running_source_code = team.get_editor_code();
+                }
+                self.baseline = Some(Baseline {
+                    source_code: Code::Rust(text),
+                    compiled_code: Code::None,
+                });
+                self.start_compile(context, ExecutionMode::Compare { seed: None });
+                true
+            }
+            Msg::ReplayComparison(seed) => {
+                self.overlay = None;
+                self.comparison = None;
+                self.run(context, ExecutionMode::Compare { seed: Some(seed) });
+                self.focus_simulation();
+                true
+            }
+            Msg::ReceivedComparisonResponse(
+                variant,
+                seed,
+                oort_simulation_worker::Response::Snapshot { snapshot },
+            ) => match self.comparison.as_mut() {
+                Some(comparison) => comparison.on_snapshot(variant, seed, snapshot),
+                None => false,
+            },
+            Msg::ReceivedComparisonResponse(
+                _,
+                _,
+                oort_simulation_worker::Response::Seek { .. },
+            ) => false,
             Msg::RefreshVersions => {
                 self.versions_update_timestamp = chrono::Utc::now();
                 true
@@ -565,6 +723,8 @@ impl Component for Game {
             .expect("a #versions-window element");
         let load_cb = context.link().callback(Msg::LoadVersion);
         let save_cb = context.link().callback(Msg::SaveVersion);
+        let branch_cb = context.link().callback(Msg::SwitchBranch);
+        let compare_cb = context.link().callback(Msg::CompareVersion);
 
         // For SeedWindow.
         let seed_window_host = gloo_utils::document()
@@ -603,7 +763,7 @@ impl Component for Game {
             <Documentation host={documentation_window_host} {show_feedback_cb} />
             <CompilerOutputWindow host={compiler_output_window_host} {compiler_errors} />
             <LeaderboardWindow host={leaderboard_window_host} scenario_name={context.props().scenario.clone()} {play_cb} />
-            <VersionsWindow host={versions_window_host} scenario_name={context.props().scenario.clone()} {load_cb} {save_cb} update_timestamp={self.versions_update_timestamp} />
+            <VersionsWindow host={versions_window_host} scenario_name={context.props().scenario.clone()} {load_cb} {save_cb} {branch_cb} {compare_cb} update_timestamp={self.versions_update_timestamp} />
             <SeedWindow host={seed_window_host} {current_seed} change_cb={change_seed_cb} />
             { self.render_overlay(context) }
         </>
@@ -669,27 +829,32 @@ impl Game {
             return false;
         }
 
-        if self.execution_mode == ExecutionMode::Run {
+        let entry_line = entry_line(&code_to_string(&self.team(0).running_source_code));
+        let crash_errors = make_crash_errors(&snapshot, entry_line);
+        if !crash_errors.is_empty() {
+            self.team_mut(0).display_crash_errors(crash_errors);
+        }
+
+        if self.execution_mode == ExecutionMode::Run && !snapshot.cheats {
             if let Status::Victory { team: 0 } = status {
                 self.background_agents.clear();
                 self.background_snapshots.clear();
                 self.background_nonce = rand::thread_rng().gen();
-                let codes: Vec<_> = self
-                    .teams
-                    .iter()
-                    .map(|x| x.running_compiled_code.clone())
-                    .collect();
+                let codes = self.running_codes();
                 for seed in 0..NUM_BACKGROUND_SIMULATIONS {
                     let cb = {
                         let link = context.link().clone();
                         move |e| link.send_message(Msg::ReceivedBackgroundSimAgentResponse(e, seed))
                     };
-                    let mut sim_agent = SimAgent::bridge(Rc::new(cb));
+                    let mut sim_agent =
+                        SimAgent::bridge(oort_simulation_worker::decode_responses(cb));
                     sim_agent.send(oort_simulation_worker::Request::StartScenario {
                         scenario_name: context.props().scenario.clone(),
+                        custom_scenario: scenario::custom::lookup(&context.props().scenario),
                         seed,
                         codes: codes.clone(),
                         nonce: self.background_nonce,
+                        record_history: false,
                     });
                     self.background_agents.push(sim_agent);
                 }
@@ -699,6 +864,22 @@ impl Game {
             }
         }
 
+        if self.execution_mode == (ExecutionMode::Compare { seed: None }) {
+            let current_codes = self.running_codes();
+            let baseline_codes = self.baseline_codes().unwrap_or_default();
+            self.comparison = Some(Comparison::start(
+                &context.props().scenario,
+                0..NUM_BACKGROUND_SIMULATIONS,
+                current_codes,
+                baseline_codes,
+                rand::thread_rng().gen(),
+                context.link().callback(|(variant, seed, response)| {
+                    Msg::ReceivedComparisonResponse(variant, seed, response)
+                }),
+            ));
+            self.overlay = Some(Overlay::Comparison);
+        }
+
         self.last_snapshot = Some(snapshot);
         true
     }
@@ -733,6 +914,7 @@ impl Game {
                         Some(Overlay::MissionComplete) => self.render_mission_complete_overlay(context),
                         Some(Overlay::Compiling) => html! { <h1 class="compiling">{ "Compiling..." }</h1> },
                         Some(Overlay::Feedback) => html! { <crate::feedback::Feedback {close_overlay_cb} /> },
+                        Some(Overlay::Comparison) => self.render_comparison_overlay(context),
                         Some(Overlay::Error(e)) => html! { <><h1>{ "Error" }</h1><span>{ e }</span></> },
                         None => unreachable!(),
                     }
@@ -855,7 +1037,12 @@ impl Game {
         };
         let source_code = code_to_string(&self.player_team().running_source_code);
         let code_size = crate::code_size::calculate(&source_code);
-        let leaderboard_eligible = self.leaderboard_eligible();
+        // Custom scenarios from the scenario editor have no leaderboards.
+        let leaderboard_eligible = self.leaderboard_eligible()
+            && !context
+                .props()
+                .scenario
+                .starts_with(scenario::custom::PREFIX);
 
         let next_scenario = scenario::load(&context.props().scenario).next_scenario();
 
@@ -1032,6 +1219,25 @@ impl Game {
         }
     }
 
+    fn render_comparison_overlay(&self, context: &yew::Context<Self>) -> Html {
+        let Some(comparison) = self.comparison.as_ref() else {
+            return html! {};
+        };
+        let make_seed_link = |seed: u32| {
+            let onclick = context
+                .link()
+                .callback(move |_| Msg::ReplayComparison(seed));
+            html! { <a href="#" {onclick}>{ seed }</a> }
+        };
+        html! {
+            <div class="centered">
+                <h1>{ "Comparison" }</h1>
+                <p>{ "Click a seed to watch both versions on it." }</p>
+                { comparison.view(make_seed_link) }
+            </div>
+        }
+    }
+
     pub fn start_compile(&mut self, context: &Context<Self>, execution_mode: ExecutionMode) {
         self.compiler_errors = None;
         self.overlay = Some(Overlay::Compiling);
@@ -1040,39 +1246,56 @@ impl Game {
             .link()
             .callback(move |results| Msg::CompileFinished(results, execution_mode));
 
-        async fn compile(text: String) -> Result<Code, String> {
+        fn failure(message: String) -> CompileFailure {
+            CompileFailure {
+                message,
+                diagnostics: vec![],
+            }
+        }
+
+        async fn compile(
+            text: String,
+            language: Option<String>,
+        ) -> Result<CompileOutput, CompileFailure> {
             if text.trim().is_empty() {
-                return Ok(Code::None);
+                return Ok((Code::None, vec![]));
             }
 
             let start_time = instant::Instant::now();
 
-            let url = format!("{}/compile", services::compiler_url());
+            let url = compile_url(&services::compiler_url(), language.as_deref());
             let result = Request::post(&url).body(text).send().await;
             if let Err(e) = result {
                 log::error!("Compile error: {}", e);
-                return Err(e.to_string());
+                return Err(failure(e.to_string()));
             }
 
             let response = result.unwrap();
             if !response.ok() {
                 let error = response.text().await.unwrap();
                 log::error!("Compile error: {}", error);
-                return Err(error);
+                return Err(serde_json::from_str(&error).unwrap_or_else(|_| failure(error)));
             }
 
-            let wasm = response.binary().await;
-            if let Err(e) = wasm {
-                log::error!("Compile error: {}", e);
-                return Err(e.to_string());
-            }
+            let success: CompileSuccess = match response.json().await {
+                Ok(success) => success,
+                Err(e) => {
+                    log::error!("Compile error: {}", e);
+                    return Err(failure(e.to_string()));
+                }
+            };
 
             let elapsed = instant::Instant::now() - start_time;
             log::info!("Compile succeeded in {:?}", elapsed);
-            Ok(Code::Wasm(wasm.unwrap()))
+            Ok((Code::Wasm(success.wasm), success.diagnostics))
         }
 
-        let source_codes: Vec<_> = self
+        let mut languages: Vec<_> = self
+            .teams
+            .iter()
+            .map(|team| team.get_chosen_language())
+            .collect();
+        let mut source_codes: Vec<_> = self
             .teams
             .iter()
             .map(|team| {
@@ -1091,14 +1314,31 @@ impl Game {
                 }
             })
             .collect();
+        if matches!(execution_mode, ExecutionMode::Compare { .. }) {
+            let baseline = &self
+                .baseline
+                .as_ref()
+                .expect("missing baseline")
+                .source_code;
+            source_codes.push(
+                self.compilation_cache
+                    .get(baseline)
+                    .unwrap_or(baseline)
+                    .clone(),
+            );
+            // The baseline is an earlier version of the player's code.
+            languages.push(self.player_team().get_chosen_language());
+        }
 
         wasm_bindgen_futures::spawn_local(async move {
             let mut results = vec![];
-            for source_code in source_codes {
+            for (source_code, language) in source_codes.into_iter().zip(languages) {
                 let result = match source_code {
-                    Code::Rust(text) => compile(text).await,
-                    Code::Builtin(name) => oort_simulator::vm::builtin::load_compiled(&name),
-                    other => Ok(other),
+                    Code::Rust(text) => compile(text, language).await,
+                    Code::Builtin(name) => oort_simulator::vm::builtin::load_compiled(&name)
+                        .map(|code| (code, vec![]))
+                        .map_err(failure),
+                    other => Ok((other, vec![])),
                 };
                 results.push(result);
             }
@@ -1109,17 +1349,14 @@ impl Game {
     pub fn run(&mut self, context: &Context<Self>, execution_mode: ExecutionMode) {
         self.compiler_errors = None;
 
-        let codes: Vec<_> = self
-            .teams
-            .iter()
-            .map(|x| x.running_compiled_code.clone())
-            .collect();
+        let codes = self.running_codes();
         let rand_seed = rand::thread_rng().gen();
         let seed = match execution_mode {
-            ExecutionMode::Initial | ExecutionMode::Run => {
+            ExecutionMode::Initial | ExecutionMode::Run | ExecutionMode::Compare { seed: None } => {
                 self.configured_seed(context).unwrap_or(rand_seed)
             }
-            ExecutionMode::Replay { .. } => self
+            ExecutionMode::Compare { seed: Some(seed) } => seed,
+            ExecutionMode::Replay { .. } | ExecutionMode::HotReload { .. } => self
                 .configured_seed(context)
                 .unwrap_or(self.previous_seed.unwrap_or(rand_seed)),
         };
@@ -1133,6 +1370,26 @@ impl Game {
                 seed,
                 start_paused,
                 codes: codes.to_vec(),
+                baseline_codes: match execution_mode {
+                    ExecutionMode::Compare { .. } => self.baseline_codes(),
+                    _ => None,
+                },
+            });
+        } else {
+            log::error!("Missing SimulationWindow");
+        }
+        self.background_agents.clear();
+        self.background_snapshots.clear();
+        self.background_nonce = 0;
+    }
+
+    fn hot_reload(&mut self, team: usize) {
+        self.compiler_errors = None;
+        self.execution_mode = ExecutionMode::HotReload { team };
+        if let Some(link) = self.simulation_window_link.as_ref() {
+            link.send_message(crate::simulation_window::Msg::ReloadCode {
+                team: team as i32,
+                code: self.team(team).running_compiled_code.clone(),
             });
         } else {
             log::error!("Missing SimulationWindow");
@@ -1194,6 +1451,7 @@ impl Game {
         enemy_team.running_compiled_code = enemy_code;
         enemy_team.set_editor_text(&code_to_string(&enemy_team.initial_source_code));
         self.teams.push(enemy_team);
+        self.extra_team_codes = codes.iter().skip(self.teams.len()).cloned().collect();
 
         if scenario_name == "welcome" {
             crate::js::golden_layout::show_welcome(true);
@@ -1205,6 +1463,22 @@ impl Game {
         self.run(context, ExecutionMode::Initial);
     }
 
+    fn running_codes(&self) -> Vec<Code> {
+        self.teams
+            .iter()
+            .map(|x| x.running_compiled_code.clone())
+            .chain(self.extra_team_codes.iter().cloned())
+            .collect()
+    }
+
+    /// The running codes with the player's code replaced by the baseline.
+    fn baseline_codes(&self) -> Option<Vec<Code>> {
+        let baseline = self.baseline.as_ref()?;
+        let mut codes = self.running_codes();
+        codes[0] = baseline.compiled_code.clone();
+        Some(codes)
+    }
+
     pub fn team(&self, index: usize) -> &Team {
         self.teams.get(index).expect("Invalid team")
     }
@@ -1275,6 +1549,7 @@ impl Team {
             initial_compiled_code: Code::None,
             running_compiled_code: Code::None,
             current_compiler_decorations: js_sys::Array::new(),
+            displayed_crash_errors: vec![],
         }
     }
 
@@ -1288,6 +1563,10 @@ impl Team {
         str_to_code(&self.get_editor_text())
     }
 
+    pub fn get_chosen_language(&self) -> Option<String> {
+        editor_window::get_chosen_language(&self.editor_link)
+    }
+
     pub fn set_editor_text(&self, text: &str) {
         self.editor_link.with_editor(|editor| {
             editor.get_model().unwrap().set_value(text);
@@ -1316,7 +1595,17 @@ impl Team {
         // TODO trigger analyzer run
     }
 
+    /// Shows crash errors, unless the same ones are already shown.
+    pub fn display_crash_errors(&mut self, errors: Vec<CompilerError>) {
+        if errors == self.displayed_crash_errors {
+            return;
+        }
+        self.display_compiler_errors(&errors);
+        self.displayed_crash_errors = errors;
+    }
+
     pub fn display_compiler_errors(&mut self, errors: &[CompilerError]) {
+        self.displayed_crash_errors.clear();
         use monaco::sys::{
             editor::IModelDecorationOptions, editor::IModelDeltaDecoration, IMarkdownString, Range,
         };
@@ -1329,7 +1618,14 @@ impl Team {
                 );
                 let options: IModelDecorationOptions = empty().into();
                 options.set_is_whole_line(Some(true));
-                options.set_class_name("errorDecoration".into());
+                options.set_class_name(
+                    if error.level == DiagnosticLevel::Error {
+                        "errorDecoration"
+                    } else {
+                        "warningDecoration"
+                    }
+                    .into(),
+                );
                 let hover_message: IMarkdownString = empty().into();
                 js_sys::Reflect::set(
                     &hover_message,
@@ -1354,6 +1650,18 @@ impl Team {
                     .delta_decorations(&self.current_compiler_decorations, &decorations_jsarray)
             })
             .unwrap();
+
+        // Read by the code action provider in completion.js.
+        let fixes: Vec<&QuickFix> = errors.iter().flat_map(|error| error.fixes.iter()).collect();
+        self.editor_link.with_editor(|editor| {
+            let ed: &monaco::sys::editor::IStandaloneCodeEditor = editor.as_ref();
+            js_sys::Reflect::set(
+                &ed.get_model().unwrap(),
+                &JsValue::from_str("quickFixes"),
+                &serde_wasm_bindgen::to_value(&fixes).unwrap(),
+            )
+            .unwrap();
+        });
     }
 }
 
@@ -1377,10 +1685,27 @@ pub fn str_to_code(s: &str) -> Code {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Compiled code and any warnings.
+pub type CompileOutput = (Code, Vec<CompilerDiagnostic>);
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CompilerError {
     pub line: usize,
     pub msg: String,
+    pub level: DiagnosticLevel,
+    pub fixes: Vec<QuickFix>,
+}
+
+/// A replacement offered as a code action in the editor. Lines and columns
+/// start at 1.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuickFix {
+    pub title: String,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+    pub text: String,
 }
 
 fn make_editor_errors(error: &str) -> Vec<CompilerError> {
@@ -1389,10 +1714,92 @@ fn make_editor_errors(error: &str) -> Vec<CompilerError> {
         .map(|m| CompilerError {
             line: m[2].parse().unwrap(),
             msg: m[1].to_string(),
+            level: DiagnosticLevel::Error,
+            fixes: vec![],
         })
         .collect()
 }
 
+fn make_diagnostic_errors(diagnostics: &[CompilerDiagnostic]) -> Vec<CompilerError> {
+    diagnostics
+        .iter()
+        .filter_map(|diagnostic| {
+            let span = diagnostic.span.as_ref()?;
+            let mut msg = diagnostic.message.clone();
+            for note in diagnostic.notes.iter() {
+                msg.push_str(&format!("\n\n{note}"));
+            }
+            let mut fixes = vec![];
+            for suggestion in diagnostic.suggestions.iter() {
+                if suggestion.machine_applicable {
+                    let span = &suggestion.span;
+                    fixes.push(QuickFix {
+                        title: format!("{}: `{}`", suggestion.message, suggestion.replacement),
+                        line_start: span.line_start,
+                        column_start: span.column_start,
+                        line_end: span.line_end,
+                        column_end: span.column_end,
+                        text: suggestion.replacement.clone(),
+                    });
+                } else {
+                    msg.push_str(&format!(
+                        "\n\nSuggestion: replace with `{}`",
+                        suggestion.replacement
+                    ));
+                }
+            }
+            Some(CompilerError {
+                line: span.line_start,
+                msg,
+                level: diagnostic.level,
+                fixes,
+            })
+        })
+        .collect()
+}
+
+/// Crashes without symbolicated frames are shown at `entry_line`.
+fn make_crash_errors(snapshot: &Snapshot, entry_line: usize) -> Vec<CompilerError> {
+    let mut errors: Vec<CompilerError> = snapshot
+        .ships
+        .iter()
+        .filter(|ship| ship.team == 0)
+        .filter_map(|ship| snapshot.crashes.get(&ship.id))
+        .map(|crash| CompilerError {
+            line: crash
+                .frames
+                .first()
+                .map_or(entry_line, |frame| frame.line as usize),
+            msg: crash.msg.clone(),
+            level: DiagnosticLevel::Error,
+            fixes: vec![],
+        })
+        .collect();
+    errors.sort_by_key(|e| e.line);
+    errors.dedup_by_key(|e| e.line);
+    errors
+}
+
+/// Returns the line of the `tick` function the simulator calls into, or the
+/// first line if there isn't one.
+fn entry_line(source: &str) -> usize {
+    source
+        .lines()
+        .position(|line| line.contains("fn tick("))
+        .map_or(1, |i| i + 1)
+}
+
+/// Leaves out the language unless the player picked one, so that the
+/// compiler service detects it from the code.
+fn compile_url(base: &str, language: Option<&str>) -> String {
+    let mut url = format!("{base}/compile?debug=true&diagnostics=true");
+    if let Some(language) = language {
+        url.push_str("&language=");
+        url.push_str(language);
+    }
+    url
+}
+
 pub(crate) fn is_encrypted(code: &Code) -> bool {
     match code {
         Code::Rust(src) => src.starts_with("ENCRYPTED:"),
@@ -1415,3 +1822,20 @@ where
         }
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::compile_url;
+
+    #[test]
+    fn test_compile_url() {
+        assert_eq!(
+            compile_url("https://compiler", None),
+            "https://compiler/compile?debug=true&diagnostics=true"
+        );
+        assert_eq!(
+            compile_url("https://compiler", Some("cpp")),
+            "https://compiler/compile?debug=true&diagnostics=true&language=cpp"
+        );
+    }
+}
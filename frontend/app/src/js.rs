@@ -14,8 +14,16 @@ pub mod filesystem {
         #[wasm_bindgen(method, catch)]
         pub async fn read(this: &FileHandle) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(method, getter)]
+        pub fn name(this: &FileHandle) -> String;
+
         #[wasm_bindgen(catch)]
         pub async fn open() -> Result<JsValue, JsValue>;
+
+        pub fn download(filename: &str, text: &str);
+
+        #[wasm_bindgen(js_name = openArchive, catch)]
+        pub async fn open_archive() -> Result<JsValue, JsValue>;
     }
 }
 
@@ -40,6 +48,22 @@ pub mod completion {
     }
 }
 
+pub mod editor {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen(module = "/js/editor.js")]
+    extern "C" {
+        #[wasm_bindgen(js_name = getLanguage)]
+        pub fn get_language(model: &JsValue) -> String;
+
+        #[wasm_bindgen(js_name = setLanguage)]
+        pub fn set_language(model: &JsValue, language: &str);
+
+        #[wasm_bindgen(js_name = replaceText)]
+        pub fn replace_text(editor: &JsValue, source: &str, text: &str);
+    }
+}
+
 pub mod clipboard {
     use wasm_bindgen::prelude::*;
 
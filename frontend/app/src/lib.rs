@@ -2,6 +2,7 @@ mod analyzer_stub;
 pub mod benchmark;
 pub mod code_size;
 pub mod codestorage;
+pub mod comparison;
 pub mod compiler_output_window;
 pub mod documentation;
 pub mod editor_window;
@@ -11,6 +12,7 @@ pub mod gtag;
 pub mod js;
 pub mod leaderboard;
 pub mod leaderboard_window;
+pub mod scenario_editor;
 pub mod seed_window;
 pub mod services;
 pub mod simulation_window;
@@ -40,6 +42,11 @@ pub enum Route {
     Benchmark { scenario: String },
     #[at("/tournament/:id")]
     Tournament { id: String },
+    #[at("/scenario_editor")]
+    ScenarioEditor,
+    /// A custom scenario shared by shortcode.
+    #[at("/custom/:shortcode")]
+    CustomScenario { shortcode: String },
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -69,6 +76,15 @@ struct GameWrapperProps {
 fn game_wrapper(props: &GameWrapperProps) -> Html {
     let location = use_location().expect("use_location");
     let q = query_params(&location);
+    if props
+        .scenario
+        .starts_with(oort_simulator::scenario::custom::PREFIX)
+        && !scenario_editor::restore(&props.scenario)
+    {
+        return html! {
+            <div id="tournament_results">{ "Unknown custom scenario" }</div>
+        };
+    }
     html! {
         <game::Game
             version={version()}
@@ -79,6 +95,44 @@ fn game_wrapper(props: &GameWrapperProps) -> Html {
     }
 }
 
+#[derive(Properties, PartialEq, Eq, Debug)]
+struct CustomScenarioWrapperProps {
+    shortcode: String,
+}
+
+/// Fetches a shared custom scenario and plays it.
+#[function_component(CustomScenarioWrapper)]
+fn custom_scenario_wrapper(props: &CustomScenarioWrapperProps) -> Html {
+    let scenario = use_state(|| None::<Result<String, String>>);
+    {
+        let scenario = scenario.clone();
+        let shortcode = props.shortcode.clone();
+        use_effect_with_deps(
+            move |_| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = match services::get_shortcode(&shortcode).await {
+                        Ok(json) => {
+                            oort_simulator::scenario::custom::ScenarioDescription::from_json(&json)
+                                .and_then(scenario_editor::register)
+                        }
+                        Err(e) => Err(e.to_string()),
+                    };
+                    scenario.set(Some(result));
+                });
+                || ()
+            },
+            props.shortcode.clone(),
+        );
+    }
+    match scenario.as_ref() {
+        None => html! { <div id="tournament_results">{ "Loading scenario..." }</div> },
+        Some(Err(e)) => html! {
+            <div id="tournament_results">{ "Failed to load scenario: " }{ e }</div>
+        },
+        Some(Ok(name)) => html! { <GameWrapper scenario={name.clone()} /> },
+    }
+}
+
 fn switch(routes: Route) -> Html {
     match routes {
         Route::Home => html! {
@@ -96,6 +150,12 @@ fn switch(routes: Route) -> Html {
         Route::Tournament { id } => html! {
             <tournament::Tournament id={id} />
         },
+        Route::ScenarioEditor => html! {
+            <scenario_editor::ScenarioEditor />
+        },
+        Route::CustomScenario { shortcode } => html! {
+            <CustomScenarioWrapper shortcode={shortcode} />
+        },
     }
 }
 
@@ -0,0 +1,482 @@
+use crate::services;
+use crate::ui::setting;
+use nalgebra::vector;
+use oort_renderer::ship_renderer::ShipRenderer;
+use oort_simulator::scenario::custom::{
+    self, ScenarioDescription, ShipPlacement, VictoryCondition, CLASSES, ENEMY_AIS, MAX_TEAMS,
+};
+use oort_simulator::ship::ShipClass;
+use oort_simulator::simulation::PHYSICS_TICK_LENGTH;
+use std::f64::consts::PI;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlInputElement};
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+const DRAFT_SETTING: &str = "scenario_editor_draft";
+
+/// Registers a custom scenario and saves its description, so that it can
+/// still be loaded by name after a reload. Returns the scenario name.
+pub fn register(description: ScenarioDescription) -> Result<String, String> {
+    let name = custom::register(description.clone())?;
+    setting::write(&saved_setting(&name), &description);
+    Ok(name)
+}
+
+/// Registers a custom scenario saved by `register`. Returns false if there
+/// is no such scenario.
+pub fn restore(name: &str) -> bool {
+    if custom::lookup(name).is_some() {
+        return true;
+    }
+    let Some(description) = setting::read(&saved_setting(name), None) else {
+        return false;
+    };
+    custom::register(description).as_deref() == Ok(name)
+}
+
+fn saved_setting(name: &str) -> String {
+    format!("/custom_scenario/{name}")
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ShipField {
+    Team,
+    X,
+    Y,
+    VelocityX,
+    VelocityY,
+    /// Heading in degrees.
+    Heading,
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    SetName(String),
+    SetWorldSize(f64),
+    /// Time limit in seconds.
+    SetTimeLimit(f64),
+    SetVictory(VictoryCondition),
+    SetEnemyAi(i32, String),
+    SelectClass(usize),
+    SelectTeam(i32),
+    AddShip(f64, f64),
+    SelectShip(usize),
+    UpdateShip(usize, ShipField, f64),
+    RemoveShip(usize),
+    Clear,
+    Play,
+    Share,
+    Shared(Result<String, String>),
+    LoadShortcode(String),
+    Loaded(Result<ScenarioDescription, String>),
+}
+
+pub struct ScenarioEditor {
+    description: ScenarioDescription,
+    class_index: usize,
+    team: i32,
+    selected: Option<usize>,
+    error: Option<String>,
+    share_link: Option<String>,
+    shortcode_ref: NodeRef,
+}
+
+impl Component for ScenarioEditor {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_context: &yew::Context<Self>) -> Self {
+        let description = setting::read(DRAFT_SETTING, ScenarioDescription::default());
+        Self {
+            error: description.validate().err(),
+            description,
+            class_index: 0,
+            team: 0,
+            selected: None,
+            share_link: None,
+            shortcode_ref: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, context: &yew::Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetName(name) => self.description.name = name,
+            Msg::SetWorldSize(world_size) => self.description.world_size = world_size,
+            Msg::SetTimeLimit(seconds) => {
+                self.description.max_ticks = (seconds / PHYSICS_TICK_LENGTH).round() as u32
+            }
+            Msg::SetVictory(victory) => self.description.victory = victory,
+            Msg::SetEnemyAi(team, ai) => {
+                self.description.enemy_ais.insert(team, ai);
+            }
+            Msg::SelectClass(index) => {
+                self.class_index = index;
+                return true;
+            }
+            Msg::SelectTeam(team) => {
+                self.team = team;
+                return true;
+            }
+            Msg::AddShip(x, y) => {
+                self.description.ships.push(ShipPlacement {
+                    class: CLASSES[self.class_index],
+                    team: self.team,
+                    position: vector![x, y],
+                    velocity: vector![0.0, 0.0],
+                    heading: if x > 0.0 { PI } else { 0.0 },
+                });
+                self.selected = Some(self.description.ships.len() - 1);
+            }
+            Msg::SelectShip(index) => {
+                self.selected = Some(index);
+                return true;
+            }
+            Msg::UpdateShip(index, field, value) => {
+                let Some(ship) = self.description.ships.get_mut(index) else {
+                    return false;
+                };
+                match field {
+                    ShipField::Team => ship.team = value as i32,
+                    ShipField::X => ship.position.x = value,
+                    ShipField::Y => ship.position.y = value,
+                    ShipField::VelocityX => ship.velocity.x = value,
+                    ShipField::VelocityY => ship.velocity.y = value,
+                    ShipField::Heading => ship.heading = value.to_radians(),
+                }
+            }
+            Msg::RemoveShip(index) => {
+                if index < self.description.ships.len() {
+                    self.description.ships.remove(index);
+                }
+                self.selected = None;
+            }
+            Msg::Clear => {
+                self.description = ScenarioDescription::default();
+                self.selected = None;
+            }
+            Msg::Play => match register(self.description.clone()) {
+                Ok(scenario) => {
+                    let navigator = context.link().navigator().unwrap();
+                    navigator.push(&crate::Route::Scenario { scenario });
+                }
+                Err(e) => self.error = Some(e),
+            },
+            Msg::Share => {
+                if self.error.is_some() {
+                    return false;
+                }
+                let json = self.description.to_json();
+                context.link().send_future(async move {
+                    Msg::Shared(
+                        services::upload_shortcode(&json)
+                            .await
+                            .map_err(|e| e.to_string()),
+                    )
+                });
+                return false;
+            }
+            Msg::Shared(result) => {
+                match result {
+                    Ok(shortcode) => {
+                        let origin = gloo_utils::window().location().origin().unwrap();
+                        let link = format!("{origin}/custom/{shortcode}");
+                        crate::js::clipboard::write(&link);
+                        self.share_link = Some(link);
+                    }
+                    Err(e) => self.error = Some(format!("Failed to share scenario: {e}")),
+                }
+                return true;
+            }
+            Msg::LoadShortcode(shortcode) => {
+                context.link().send_future(async move {
+                    Msg::Loaded(match services::get_shortcode(&shortcode).await {
+                        Ok(json) => ScenarioDescription::from_json(&json),
+                        Err(e) => Err(e.to_string()),
+                    })
+                });
+                return false;
+            }
+            Msg::Loaded(result) => match result {
+                Ok(description) => {
+                    self.description = description;
+                    self.selected = None;
+                }
+                Err(e) => {
+                    self.error = Some(format!("Failed to load scenario: {e}"));
+                    return true;
+                }
+            },
+        }
+        self.error = self.description.validate().err();
+        self.share_link = None;
+        setting::write(DRAFT_SETTING, &self.description);
+        true
+    }
+
+    fn view(&self, context: &yew::Context<Self>) -> Html {
+        let link = context.link();
+        let description = &self.description;
+        let world_size = description.world_size;
+
+        let map_click_cb = link.batch_callback(move |e: MouseEvent| {
+            let target = e.current_target()?.dyn_into::<Element>().ok()?;
+            let rect = target.get_bounding_client_rect();
+            let fx = (e.client_x() as f64 - rect.x()) / rect.width() - 0.5;
+            let fy = 0.5 - (e.client_y() as f64 - rect.y()) / rect.height();
+            Some(Msg::AddShip(
+                (fx * world_size).round(),
+                (fy * world_size).round(),
+            ))
+        });
+        let unit = world_size / 200.0;
+        let ships_svg = description
+            .ships
+            .iter()
+            .enumerate()
+            .map(|(i, ship)| {
+                let radius = unit
+                    * match ship.class {
+                        ShipClass::Frigate | ShipClass::BigAsteroid { .. } => 2.0,
+                        ShipClass::Cruiser => 3.0,
+                        ShipClass::Missile | ShipClass::Torpedo => 0.5,
+                        _ => 1.0,
+                    };
+                let (x, y) = (ship.position.x, -ship.position.y);
+                let heading_end = (
+                    x + 2.0 * radius * ship.heading.cos(),
+                    y - 2.0 * radius * ship.heading.sin(),
+                );
+                let velocity_end = (x + ship.velocity.x, y - ship.velocity.y);
+                let select_cb = link.callback(move |e: MouseEvent| {
+                    e.stop_propagation();
+                    Msg::SelectShip(i)
+                });
+                let class = if self.selected == Some(i) {
+                    "selected"
+                } else {
+                    ""
+                };
+                html! {
+                    <g class={class} onclick={select_cb}>
+                        <circle cx={x.to_string()} cy={y.to_string()} r={radius.to_string()}
+                            fill={team_color(ship.team)} />
+                        <line x1={x.to_string()} y1={y.to_string()}
+                            x2={heading_end.0.to_string()} y2={heading_end.1.to_string()}
+                            stroke={team_color(ship.team)} stroke-width={(unit / 4.0).to_string()} />
+                        <line x1={x.to_string()} y1={y.to_string()}
+                            x2={velocity_end.0.to_string()} y2={velocity_end.1.to_string()}
+                            stroke="#888" stroke-width={(unit / 4.0).to_string()} />
+                    </g>
+                }
+            })
+            .collect::<Html>();
+
+        let number_input = |value: f64, make_msg: fn(f64) -> Msg| {
+            let cb = link.batch_callback(move |e: Event| {
+                let target = e.target()?.unchecked_into::<HtmlInputElement>();
+                let value = target.value_as_number();
+                value.is_finite().then(|| make_msg(value))
+            });
+            html! { <input type="number" value={value.to_string()} onchange={cb} /> }
+        };
+        let ship_input = |index: usize, field: ShipField, value: f64| {
+            let cb = link.batch_callback(move |e: Event| {
+                let target = e.target()?.unchecked_into::<HtmlInputElement>();
+                let value = target.value_as_number();
+                value
+                    .is_finite()
+                    .then_some(Msg::UpdateShip(index, field, value))
+            });
+            html! { <input type="number" value={format!("{:.0}", value)} onchange={cb} /> }
+        };
+
+        let ship_rows = description
+            .ships
+            .iter()
+            .enumerate()
+            .map(|(i, ship)| {
+                let remove_cb = link.callback(move |_| Msg::RemoveShip(i));
+                let select_cb = link.callback(move |_| Msg::SelectShip(i));
+                let class = if self.selected == Some(i) {
+                    "selected"
+                } else {
+                    ""
+                };
+                html! {
+                    <tr class={class} onclick={select_cb}>
+                        <td>{ class_name(ship.class) }</td>
+                        <td>{ ship_input(i, ShipField::Team, ship.team as f64) }</td>
+                        <td>{ ship_input(i, ShipField::X, ship.position.x) }</td>
+                        <td>{ ship_input(i, ShipField::Y, ship.position.y) }</td>
+                        <td>{ ship_input(i, ShipField::VelocityX, ship.velocity.x) }</td>
+                        <td>{ ship_input(i, ShipField::VelocityY, ship.velocity.y) }</td>
+                        <td>{ ship_input(i, ShipField::Heading, ship.heading.to_degrees()) }</td>
+                        <td><a href="#" onclick={remove_cb}>{ "remove" }</a></td>
+                    </tr>
+                }
+            })
+            .collect::<Html>();
+
+        let select_class_cb = link.callback(|e: Event| {
+            let target = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+            Msg::SelectClass(target.value().parse().unwrap_or(0))
+        });
+        let select_team_cb = link.callback(|e: Event| {
+            let target = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+            Msg::SelectTeam(target.value().parse().unwrap_or(0))
+        });
+        let name_cb = link.callback(|e: Event| {
+            let target = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+            Msg::SetName(target.value())
+        });
+        let victory_cb = link.callback(|e: Event| {
+            let target = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+            Msg::SetVictory(match target.value().as_str() {
+                "LastTeamStanding" => VictoryCondition::LastTeamStanding,
+                "Survive" => VictoryCondition::Survive,
+                _ => VictoryCondition::DestroyEnemies,
+            })
+        });
+        let victory_option = |victory: VictoryCondition, label: &str| {
+            html! {
+                <option value={format!("{victory:?}")} selected={description.victory == victory}>
+                    { label }
+                </option>
+            }
+        };
+
+        let mut enemy_teams: Vec<i32> = description
+            .ships
+            .iter()
+            .map(|ship| ship.team)
+            .filter(|&team| team > 0)
+            .collect();
+        enemy_teams.sort();
+        enemy_teams.dedup();
+        let enemy_ai_selects = enemy_teams
+            .iter()
+            .map(|&team| {
+                let current = description
+                    .enemy_ais
+                    .get(&team)
+                    .map(|x| x.as_str())
+                    .unwrap_or("empty");
+                let cb = link.callback(move |e: Event| {
+                    let target = e.target().unwrap().unchecked_into::<HtmlInputElement>();
+                    Msg::SetEnemyAi(team, target.value())
+                });
+                html! {
+                    <label>
+                        { format!("Team {team} AI ") }
+                        <select onchange={cb}>
+                            { for ENEMY_AIS.iter().map(|&ai| html! {
+                                <option value={ai} selected={ai == current}>{ ai }</option>
+                            }) }
+                        </select>
+                    </label>
+                }
+            })
+            .collect::<Html>();
+
+        let load_cb = {
+            let shortcode_ref = self.shortcode_ref.clone();
+            link.batch_callback(move |e: SubmitEvent| {
+                e.prevent_default();
+                let shortcode = shortcode_ref.cast::<HtmlInputElement>()?.value();
+                (!shortcode.is_empty()).then_some(Msg::LoadShortcode(shortcode))
+            })
+        };
+
+        let error = self
+            .error
+            .as_ref()
+            .map(|error| html! { <p class="error">{ error }</p> })
+            .unwrap_or_default();
+        let share_link = self
+            .share_link
+            .as_ref()
+            .map(|link| {
+                html! { <p>{ "Copied link to clipboard: " }<a href={link.clone()}>{ link }</a></p> }
+            })
+            .unwrap_or_default();
+
+        let half = world_size / 2.0;
+        html! {
+            <div id="scenario_editor">
+                <h1>{ "Scenario Editor" }</h1>
+                <div class="scenario_settings">
+                    <label>{ "Name " }<input type="text" value={description.name.clone()} onchange={name_cb} /></label>
+                    <label>{ "World size " }{ number_input(world_size, Msg::SetWorldSize) }</label>
+                    <label>{ "Time limit (s) " }{ number_input(description.max_ticks as f64 * PHYSICS_TICK_LENGTH, Msg::SetTimeLimit) }</label>
+                    <label>
+                        { "Victory " }
+                        <select onchange={victory_cb}>
+                            { victory_option(VictoryCondition::DestroyEnemies, "Destroy all enemies") }
+                            { victory_option(VictoryCondition::LastTeamStanding, "Last team standing") }
+                            { victory_option(VictoryCondition::Survive, "Survive until time runs out") }
+                        </select>
+                    </label>
+                    { enemy_ai_selects }
+                </div>
+                <div class="scenario_placement">
+                    { "Click the map to place a " }
+                    <select onchange={select_class_cb}>
+                        { for CLASSES.iter().enumerate().map(|(i, &class)| html! {
+                            <option value={i.to_string()} selected={i == self.class_index}>{ class_name(class) }</option>
+                        }) }
+                    </select>
+                    { " for team " }
+                    <select onchange={select_team_cb}>
+                        { for (0..MAX_TEAMS).map(|team| html! {
+                            <option value={team.to_string()} selected={team == self.team}>
+                                { if team == 0 { "0 (player)".to_string() } else { team.to_string() } }
+                            </option>
+                        }) }
+                    </select>
+                </div>
+                <svg class="scenario_map" viewBox={format!("{} {} {} {}", -half, -half, world_size, world_size)}
+                    onclick={map_click_cb}>
+                    <rect x={(-half).to_string()} y={(-half).to_string()}
+                        width={world_size.to_string()} height={world_size.to_string()} fill="#111" />
+                    { ships_svg }
+                </svg>
+                <table class="scenario_ships">
+                    <tr>
+                        <th>{ "Class" }</th><th>{ "Team" }</th><th>{ "X" }</th><th>{ "Y" }</th>
+                        <th>{ "VX" }</th><th>{ "VY" }</th><th>{ "Heading (°)" }</th><th></th>
+                    </tr>
+                    { ship_rows }
+                </table>
+                { error }
+                <p>
+                    <button onclick={link.callback(|_| Msg::Play)} disabled={self.error.is_some()}>{ "Play" }</button>
+                    <button onclick={link.callback(|_| Msg::Share)} disabled={self.error.is_some()}>{ "Share" }</button>
+                    <button onclick={link.callback(|_| Msg::Clear)}>{ "Clear" }</button>
+                </p>
+                { share_link }
+                <form onsubmit={load_cb}>
+                    <input type="text" placeholder="shortcode" ref={self.shortcode_ref.clone()} />
+                    <button type="submit">{ "Load shared scenario" }</button>
+                </form>
+            </div>
+        }
+    }
+}
+
+fn class_name(class: ShipClass) -> &'static str {
+    match class {
+        ShipClass::BigAsteroid { .. } => "big asteroid",
+        _ => class.name(),
+    }
+}
+
+fn team_color(team: i32) -> String {
+    let c = ShipRenderer::team_color(team);
+    format!(
+        "rgb({}, {}, {})",
+        (c.x * 255.0) as u8,
+        (c.y * 255.0) as u8,
+        (c.z * 255.0) as u8
+    )
+}
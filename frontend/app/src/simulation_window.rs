@@ -1,9 +1,10 @@
 use crate::ui::UI;
 use gloo_render::{request_animation_frame, AnimationFrame};
 use oort_simulation_worker::SimAgent;
-use oort_simulator::{scenario, simulation::Code, snapshot::Snapshot};
+use oort_simulator::{pilot::PilotInput, scenario, simulation::Code, snapshot::Snapshot};
 use rand::Rng;
-use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
 use yew::html::Scope;
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
@@ -15,6 +16,9 @@ pub enum Msg {
         start_paused: bool,
         seed: u32,
         codes: Vec<Code>,
+        /// Codes for a baseline simulation shown as a ghost, when comparing
+        /// two versions.
+        baseline_codes: Option<Vec<Code>>,
     },
     Render,
     KeyEvent(web_sys::KeyboardEvent),
@@ -22,7 +26,23 @@ pub enum Msg {
     PointerEvent(web_sys::PointerEvent),
     BlurEvent(web_sys::FocusEvent),
     RequestSnapshot,
+    RequestSeek(u32),
+    Seek(u32),
+    StepBackward,
+    /// Replaces a team's code in the running simulation.
+    ReloadCode {
+        team: i32,
+        code: Code,
+    },
+    /// Takes or releases keyboard control of a ship.
+    SetPilotInput {
+        ship: u64,
+        input: Option<PilotInput>,
+    },
+    /// Shows or hides a debug layer.
+    ToggleLayer(String),
     ReceivedSimAgentResponse(oort_simulation_worker::Response),
+    ReceivedGhostAgentResponse(oort_simulation_worker::Response),
 }
 
 #[derive(Properties, Clone, PartialEq)]
@@ -39,10 +59,16 @@ pub struct SimulationWindow {
     render_handle: Option<AnimationFrame>,
     nonce: u32,
     sim_agent: Box<dyn Bridge<SimAgent>>,
+    ghost_agent: Option<Box<dyn Bridge<SimAgent>>>,
     last_status: scenario::Status,
     canvas_ref: NodeRef,
     status_ref: NodeRef,
     picked_ref: NodeRef,
+    plots_ref: NodeRef,
+    timeline_ref: NodeRef,
+    jump_ref: NodeRef,
+    /// Debug layers shown in the layer toggles.
+    layers: Vec<String>,
 }
 
 impl Component for SimulationWindow {
@@ -55,7 +81,7 @@ impl Component for SimulationWindow {
             let link = context.link().clone();
             move |e| link.send_message(Msg::ReceivedSimAgentResponse(e))
         };
-        let sim_agent = SimAgent::bridge(Rc::new(cb));
+        let sim_agent = SimAgent::bridge(oort_simulation_worker::decode_responses(cb));
         let render_handle = {
             let link2 = context.link().clone();
             Some(request_animation_frame(move |_ts| {
@@ -67,10 +93,15 @@ impl Component for SimulationWindow {
             render_handle,
             nonce: 0,
             sim_agent,
+            ghost_agent: None,
             last_status: scenario::Status::Running,
             canvas_ref: context.props().canvas_ref.clone(),
             status_ref: NodeRef::default(),
             picked_ref: NodeRef::default(),
+            plots_ref: NodeRef::default(),
+            timeline_ref: NodeRef::default(),
+            jump_ref: NodeRef::default(),
+            layers: Vec::new(),
         }
     }
 
@@ -81,41 +112,128 @@ impl Component for SimulationWindow {
                 seed,
                 start_paused,
                 codes,
+                baseline_codes,
             } => {
                 self.nonce = rand::thread_rng().gen();
                 self.ui = Some(Box::new(UI::new(
                     context.link().callback(|_| Msg::RequestSnapshot),
+                    context.link().callback(Msg::RequestSeek),
+                    context
+                        .link()
+                        .callback(|(ship, input)| Msg::SetPilotInput { ship, input }),
                     seed,
                     self.nonce,
                     context.props().version.clone(),
                     self.canvas_ref.clone(),
                     self.status_ref.clone(),
                     self.picked_ref.clone(),
+                    self.plots_ref.clone(),
+                    self.timeline_ref.clone(),
                     start_paused,
                 )));
+                self.ghost_agent = baseline_codes.map(|baseline_codes| {
+                    let cb = {
+                        let link = context.link().clone();
+                        move |e| link.send_message(Msg::ReceivedGhostAgentResponse(e))
+                    };
+                    let mut ghost_agent =
+                        SimAgent::bridge(oort_simulation_worker::decode_responses(cb));
+                    ghost_agent.send(oort_simulation_worker::Request::StartScenario {
+                        scenario_name: scenario_name.clone(),
+                        custom_scenario: scenario::custom::lookup(&scenario_name),
+                        seed,
+                        codes: baseline_codes,
+                        nonce: self.nonce,
+                        record_history: true,
+                    });
+                    ghost_agent
+                });
+                if self.ghost_agent.is_some() {
+                    self.ui.as_mut().unwrap().enable_ghost();
+                }
                 self.sim_agent
                     .send(oort_simulation_worker::Request::StartScenario {
+                        custom_scenario: scenario::custom::lookup(&scenario_name),
                         scenario_name,
                         seed,
                         codes: codes.to_vec(),
                         nonce: self.nonce,
+                        record_history: true,
                     });
                 false
             }
             Msg::Render => {
+                let mut layers_changed = false;
                 if let Some(ui) = self.ui.as_mut() {
                     ui.render();
+                    if ui.debug_layers() != self.layers.as_slice() {
+                        self.layers = ui.debug_layers().to_vec();
+                        layers_changed = true;
+                    }
                 }
-                self.check_status(context)
+                self.check_status(context) || layers_changed
+            }
+            Msg::ToggleLayer(name) => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.toggle_layer(&name);
+                }
+                true
             }
             Msg::RequestSnapshot => {
+                let request = || oort_simulation_worker::Request::Snapshot {
+                    ticks: 1,
+                    nonce: self.nonce,
+                };
+                self.sim_agent.send(request());
+                if let Some(ghost_agent) = self.ghost_agent.as_mut() {
+                    ghost_agent.send(request());
+                }
+                false
+            }
+            Msg::RequestSeek(tick) => {
+                let request = || oort_simulation_worker::Request::Seek {
+                    tick,
+                    nonce: self.nonce,
+                };
+                self.sim_agent.send(request());
+                if let Some(ghost_agent) = self.ghost_agent.as_mut() {
+                    ghost_agent.send(request());
+                }
+                false
+            }
+            Msg::Seek(tick) => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.seek(tick);
+                }
+                false
+            }
+            Msg::StepBackward => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.step_backward();
+                }
+                false
+            }
+            Msg::SetPilotInput { ship, input } => {
                 self.sim_agent
-                    .send(oort_simulation_worker::Request::Snapshot {
-                        ticks: 1,
+                    .send(oort_simulation_worker::Request::SetPilotInput {
+                        ship,
+                        input,
                         nonce: self.nonce,
                     });
                 false
             }
+            Msg::ReloadCode { team, code } => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.expect_seek();
+                    self.sim_agent
+                        .send(oort_simulation_worker::Request::ReloadCode {
+                            team,
+                            code,
+                            nonce: self.nonce,
+                        });
+                }
+                false
+            }
             Msg::KeyEvent(e) => {
                 if let Some(ui) = self.ui.as_mut() {
                     ui.on_key_event(e);
@@ -148,6 +266,28 @@ impl Component for SimulationWindow {
                 }
                 false
             }
+            Msg::ReceivedSimAgentResponse(oort_simulation_worker::Response::Seek { snapshot }) => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.on_seek_snapshot(snapshot);
+                }
+                false
+            }
+            Msg::ReceivedGhostAgentResponse(oort_simulation_worker::Response::Snapshot {
+                snapshot,
+            }) => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.on_ghost_snapshot(snapshot);
+                }
+                false
+            }
+            Msg::ReceivedGhostAgentResponse(oort_simulation_worker::Response::Seek {
+                snapshot,
+            }) => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.on_ghost_seek_snapshot(snapshot);
+                }
+                false
+            }
         };
 
         if let Some(ui) = self.ui.as_ref() {
@@ -169,6 +309,23 @@ impl Component for SimulationWindow {
         let wheel_event_cb = context.link().callback(Msg::WheelEvent);
         let pointer_event_cb = context.link().callback(Msg::PointerEvent);
         let blur_event_cb = context.link().callback(Msg::BlurEvent);
+        let timeline_cb = context.link().callback(|e: InputEvent| {
+            let target: web_sys::EventTarget = e.target().unwrap();
+            Msg::Seek(
+                target
+                    .unchecked_into::<HtmlInputElement>()
+                    .value_as_number() as u32,
+            )
+        });
+        let step_backward_cb = context.link().callback(|_| Msg::StepBackward);
+        let jump_cb = {
+            let jump_ref = self.jump_ref.clone();
+            context.link().batch_callback(move |e: SubmitEvent| {
+                e.prevent_default();
+                let tick = jump_ref.cast::<HtmlInputElement>()?.value().parse().ok()?;
+                Some(Msg::Seek(tick))
+            })
+        };
 
         create_portal(
             html! {
@@ -186,6 +343,17 @@ impl Component for SimulationWindow {
                     <div class="status" ref={self.status_ref.clone()} />
                     <div class="picked">
                         <pre ref={self.picked_ref.clone()}></pre>
+                        <div class="plots" ref={self.plots_ref.clone()} />
+                    </div>
+                    { self.render_layers(context) }
+                    <div class="timeline">
+                        <button title="Step backward (,)" onclick={step_backward_cb}>{ "◀" }</button>
+                        <input type="range" min="0" max="0" value="0"
+                            ref={self.timeline_ref.clone()}
+                            oninput={timeline_cb} />
+                        <form onsubmit={jump_cb}>
+                            <input type="number" min="0" placeholder="Tick" ref={self.jump_ref.clone()} />
+                        </form>
                     </div>
                 </>
             },
@@ -195,6 +363,31 @@ impl Component for SimulationWindow {
 }
 
 impl SimulationWindow {
+    fn render_layers(&self, context: &Context<Self>) -> Html {
+        let Some(ui) = self.ui.as_ref() else {
+            return html! {};
+        };
+        if self.layers.is_empty() {
+            return html! {};
+        }
+        html! {
+            <div class="layers">
+                { for self.layers.iter().map(|name| {
+                    let toggle_cb = {
+                        let name = name.clone();
+                        context.link().callback(move |_| Msg::ToggleLayer(name.clone()))
+                    };
+                    html! {
+                        <label>
+                            <input type="checkbox" checked={!ui.layer_hidden(name)} onchange={toggle_cb} />
+                            { name }
+                        </label>
+                    }
+                }) }
+            </div>
+        }
+    }
+
     fn check_status(&mut self, context: &Context<Self>) -> bool {
         if let Some(ui) = self.ui.as_ref() {
             let status = ui.status();
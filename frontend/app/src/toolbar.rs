@@ -69,6 +69,21 @@ impl Component for Toolbar {
             }
         };
 
+        let custom_scenario_option = if context
+            .props()
+            .scenario_name
+            .starts_with(scenario::custom::PREFIX)
+        {
+            let scenario = scenario::load(&context.props().scenario_name);
+            html! {
+                <optgroup label="Custom">
+                    <option value={context.props().scenario_name.clone()} selected=true>{ scenario.human_name() }</option>
+                </optgroup>
+            }
+        } else {
+            html! {}
+        };
+
         let username = crate::userid::get_username();
         let select_scenario_cb = context.props().select_scenario_cb.clone();
         let show_feedback_cb = context.props().show_feedback_cb.clone();
@@ -96,8 +111,10 @@ impl Component for Toolbar {
                     <div class="toolbar-elem right">
                         <select onchange={select_scenario_cb}>
                             { for scenario::list().iter().map(|x| render_scenario_category(&x.0, &x.1)) }
+                            { custom_scenario_option }
                         </select>
                     </div>
+                    <div class="toolbar-elem right"><a href="/scenario_editor">{ "Scenario Editor" }</a></div>
                     <div class="toolbar-elem right"><a href="#" onclick={show_feedback_cb}>{ "Feedback" }</a></div>
                     <div class="toolbar-elem right"><a href="https://docs.rs/oort_api" target="_blank">{ "API Reference" }</a></div>
                     <div class="toolbar-elem right"><a href="http://github.com/rlane/oort3/wiki" target="_blank">{ "Wiki" }</a></div>
@@ -1,17 +1,19 @@
 pub mod fps;
 pub mod frame_timer;
+pub mod plot;
 pub mod setting;
 
 use log::{debug, info};
 use nalgebra::{point, vector, Point2, Vector2};
 use oort_renderer::Renderer;
 use oort_simulator::model;
+use oort_simulator::pilot::PilotInput;
 use oort_simulator::scenario::Status;
 use oort_simulator::simulation::{self, PHYSICS_TICK_LENGTH};
 use oort_simulator::snapshot::{self, ShipSnapshot, Snapshot};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Duration;
-use web_sys::{Element, HtmlCanvasElement};
+use web_sys::{Element, HtmlCanvasElement, HtmlInputElement};
 use yew::NodeRef;
 
 const ZOOM_SPEED: f32 = 0.02;
@@ -26,6 +28,10 @@ pub struct UI {
     seed: u32,
     snapshot: Option<Snapshot>,
     pending_snapshots: VecDeque<Snapshot>,
+    /// Snapshots from the baseline simulation when comparing two versions.
+    pending_ghost_snapshots: VecDeque<Snapshot>,
+    ghost_status: Option<(Status, f64)>,
+    ghost_seeks_in_flight: usize,
     renderer: Renderer,
     canvas: HtmlCanvasElement,
     zoom: f32,
@@ -48,10 +54,25 @@ pub struct UI {
     snapshot_requests_in_flight: usize,
     nonce: u32,
     request_snapshot: yew::Callback<()>,
+    request_seek: yew::Callback<u32>,
+    request_pilot_input: yew::Callback<(u64, Option<PilotInput>)>,
+    /// Seek requests sent to the worker and not yet answered. Snapshots
+    /// received in the meantime are from before the seek.
+    seeks_in_flight: usize,
+    /// The furthest tick received, used as the end of the timeline.
+    max_tick: u32,
     picked_ship_id: Option<u64>,
     chasing_ship_id: Option<u64>,
+    /// The ship under keyboard control, and the last input sent for it.
+    piloted_ship_id: Option<u64>,
+    pilot_input: PilotInput,
     status_ref: NodeRef,
     picked_ref: NodeRef,
+    plots_ref: NodeRef,
+    timeline_ref: NodeRef,
+    plotted_ship: Option<u64>,
+    /// Names of debug layers not drawn.
+    hidden_layers: HashSet<String>,
     touches: HashMap<i32, Touch>,
     drag_start: Option<Point2<i32>>,
     needs_render: bool,
@@ -63,12 +84,16 @@ impl UI {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         request_snapshot: yew::Callback<()>,
+        request_seek: yew::Callback<u32>,
+        request_pilot_input: yew::Callback<(u64, Option<PilotInput>)>,
         seed: u32,
         nonce: u32,
         version: String,
         canvas_ref: NodeRef,
         status_ref: NodeRef,
         picked_ref: NodeRef,
+        plots_ref: NodeRef,
+        timeline_ref: NodeRef,
         paused: bool,
     ) -> Self {
         if let Some(elem) = status_ref.cast::<Element>() {
@@ -93,12 +118,19 @@ impl UI {
         renderer.set_debug(debug);
         renderer.set_blur(setting::read("blur", true));
         renderer.set_nlips(setting::read("nlips", false));
+        renderer.set_minimap(setting::read("minimap", true));
+        renderer.set_tactical(setting::read("tactical", false));
+        let hidden_layers: HashSet<String> = setting::read("hidden_debug_layers", HashSet::new());
+        renderer.set_hidden_layers(hidden_layers.clone());
 
         UI {
             version,
             seed,
             snapshot: None,
             pending_snapshots: VecDeque::new(),
+            pending_ghost_snapshots: VecDeque::new(),
+            ghost_status: None,
+            ghost_seeks_in_flight: 0,
             renderer,
             canvas,
             zoom,
@@ -121,10 +153,20 @@ impl UI {
             snapshot_requests_in_flight: 0,
             nonce,
             request_snapshot,
+            request_seek,
+            request_pilot_input,
+            seeks_in_flight: 0,
+            max_tick: 0,
             picked_ship_id: None,
             chasing_ship_id: None,
+            piloted_ship_id: None,
+            pilot_input: PilotInput::default(),
             status_ref,
             picked_ref,
+            plots_ref,
+            timeline_ref,
+            plotted_ship: None,
+            hidden_layers,
             touches: HashMap::new(),
             drag_start: None,
             needs_render: true,
@@ -151,17 +193,21 @@ impl UI {
         let mut status_msgs: Vec<String> = Vec::new();
 
         let camera_step = 0.01 / self.zoom;
-        if self.keys_down.contains("w") {
-            self.camera_offset.y += camera_step;
-        }
-        if self.keys_down.contains("s") {
-            self.camera_offset.y -= camera_step;
-        }
-        if self.keys_down.contains("a") {
-            self.camera_offset.x -= camera_step;
-        }
-        if self.keys_down.contains("d") {
-            self.camera_offset.x += camera_step;
+        if self.piloted_ship_id.is_some() {
+            self.update_pilot_input();
+        } else {
+            if self.keys_down.contains("w") {
+                self.camera_offset.y += camera_step;
+            }
+            if self.keys_down.contains("s") {
+                self.camera_offset.y -= camera_step;
+            }
+            if self.keys_down.contains("a") {
+                self.camera_offset.x -= camera_step;
+            }
+            if self.keys_down.contains("d") {
+                self.camera_offset.x += camera_step;
+            }
         }
         if self.keys_down.contains("z") && self.zoom > MIN_ZOOM {
             self.zoom /= 1.0 + ZOOM_SPEED;
@@ -179,13 +225,21 @@ impl UI {
             self.paused = true;
             self.single_steps += 1;
         }
+        if self.keys_down.contains(",") && !self.keys_ignored.contains(",") {
+            self.keys_ignored.insert(",".to_string());
+            self.step_backward();
+        }
         if self.keys_down.contains("g") && !self.keys_ignored.contains("g") {
             self.keys_ignored.insert("g".to_string());
             self.debug = !self.debug;
             self.renderer.set_debug(self.debug);
             setting::write("debug", &self.debug);
         }
-        if self.keys_down.contains("q") {
+        if self.keys_down.contains("p") && !self.keys_ignored.contains("p") {
+            self.keys_ignored.insert("p".to_string());
+            self.toggle_piloting();
+        }
+        if self.keys_down.contains("q") && self.piloted_ship_id.is_none() {
             self.set_status_message("EXITED");
             self.quit = true;
         }
@@ -201,6 +255,16 @@ impl UI {
             self.renderer.set_nlips(!self.renderer.get_nlips());
             setting::write("nlips", &self.renderer.get_nlips());
         }
+        if self.keys_down.contains("o") && !self.keys_ignored.contains("o") {
+            self.keys_ignored.insert("o".to_string());
+            self.renderer.set_minimap(!self.renderer.get_minimap());
+            setting::write("minimap", &self.renderer.get_minimap());
+        }
+        if self.keys_down.contains("t") && !self.keys_ignored.contains("t") {
+            self.keys_ignored.insert("t".to_string());
+            self.renderer.set_tactical(!self.renderer.get_tactical());
+            setting::write("tactical", &self.renderer.get_tactical());
+        }
         if self.keys_down.contains("c") && !self.keys_ignored.contains("c") {
             self.keys_ignored.insert("c".to_string());
             self.chasing_ship_id = match self.chasing_ship_id {
@@ -217,6 +281,7 @@ impl UI {
         }
 
         if self.status == Status::Running
+            && self.seeks_in_flight == 0
             && (!self.paused
                 || self.single_steps > 0
                 || fast_forward
@@ -244,21 +309,33 @@ impl UI {
         }
 
         if self.snapshot.is_some() {
-            let chasing_ship = self
-                .chasing_ship_id
-                .and_then(|id| {
-                    self.snapshot
-                        .as_ref()
-                        .unwrap()
-                        .ships
-                        .iter()
-                        .find(|s| s.id == id)
-                });
+            let chasing_ship = self.chasing_ship_id.and_then(|id| {
+                self.snapshot
+                    .as_ref()
+                    .unwrap()
+                    .ships
+                    .iter()
+                    .find(|s| s.id == id)
+            });
             if let Some(s) = chasing_ship {
                 self.camera_focus = s.position.cast();
             } else {
                 self.chasing_ship_id = None;
             }
+            if let Some(id) = self.piloted_ship_id {
+                if self
+                    .snapshot
+                    .as_ref()
+                    .unwrap()
+                    .ships
+                    .iter()
+                    .any(|s| s.id == id)
+                {
+                    status_msgs.push("PILOTING".to_string());
+                } else {
+                    self.piloted_ship_id = None;
+                }
+            }
             self.renderer.render(
                 self.camera_target(),
                 self.zoom,
@@ -289,20 +366,33 @@ impl UI {
             _ => {}
         }
 
+        if let Some((ghost_status, ghost_score_time)) = self.ghost_status {
+            status_msgs.push(format!(
+                "BASELINE {}",
+                outcome(ghost_status, ghost_score_time)
+            ));
+            if let (Status::Victory { team: 0 }, Status::Victory { team: 0 }, Some(snapshot)) =
+                (self.status, ghost_status, self.snapshot.as_ref())
+            {
+                status_msgs.push(format!("Δ {:+.3}s", snapshot.score_time - ghost_score_time));
+            }
+        }
+
         if self.pending_snapshots.len() <= 1 && !fast_forward {
             status_msgs.push("SLOW SIM".to_owned());
         }
 
         if self.debug {
             status_msgs.push(format!("SEED {}", self.seed));
-            if let Some(snapshot) = self.snapshot.as_ref() {
-                status_msgs.push(format!(
-                    "TICK {}",
-                    (snapshot.time / PHYSICS_TICK_LENGTH).round() as i64
-                ));
+            if let Some(tick) = self.tick() {
+                status_msgs.push(format!("TICK {}", tick));
             }
         }
 
+        if self.seeks_in_flight > 0 {
+            status_msgs.push("SEEKING".to_string());
+        }
+
         if self.frame % 10 == 0 || self.paused || self.status != Status::Running {
             if self.status == Status::Running {
                 status_msgs.push(format!("{:.0} fps", self.fps.fps()));
@@ -323,6 +413,7 @@ impl UI {
             }
 
             self.update_picked();
+            self.update_timeline();
         }
 
         if self.frame == 600 {
@@ -344,14 +435,144 @@ impl UI {
             return;
         }
 
-        self.pending_snapshots.push_back(snapshot);
         if self.snapshot_requests_in_flight > 0 {
             self.snapshot_requests_in_flight -= 1;
         }
+        if self.seeks_in_flight > 0 {
+            return;
+        }
+        self.max_tick = self.max_tick.max(tick_of(&snapshot));
+        self.pending_snapshots.push_back(snapshot);
 
         self.needs_render = true;
     }
 
+    /// Expects snapshots from a baseline simulation to show as a ghost.
+    pub fn enable_ghost(&mut self) {
+        self.ghost_status = Some((Status::Running, 0.0));
+    }
+
+    pub fn on_ghost_snapshot(&mut self, snapshot: Snapshot) {
+        if snapshot.nonce != self.nonce || self.ghost_seeks_in_flight > 0 {
+            return;
+        }
+        self.pending_ghost_snapshots.push_back(snapshot);
+    }
+
+    pub fn on_ghost_seek_snapshot(&mut self, snapshot: Snapshot) {
+        if snapshot.nonce != self.nonce || self.ghost_seeks_in_flight == 0 {
+            return;
+        }
+        self.ghost_seeks_in_flight -= 1;
+        self.pending_ghost_snapshots.clear();
+        self.pending_ghost_snapshots.push_back(snapshot);
+        self.update_ghost();
+        self.needs_render = true;
+    }
+
+    /// Shows the latest ghost snapshot that isn't ahead of the main snapshot.
+    fn update_ghost(&mut self) {
+        let Some(time) = self.snapshot.as_ref().map(|s| s.time) else {
+            return;
+        };
+        let mut ghost = None;
+        while self
+            .pending_ghost_snapshots
+            .front()
+            .map(|s| s.time <= time)
+            .unwrap_or(false)
+        {
+            ghost = self.pending_ghost_snapshots.pop_front();
+        }
+        if let Some(ghost) = ghost {
+            self.ghost_status = Some((ghost.status, ghost.score_time));
+            self.renderer.set_ghost(Some(ghost));
+        }
+    }
+
+    /// Pauses and moves the simulation to the given tick.
+    pub fn seek(&mut self, tick: u32) {
+        if self.snapshot.is_none() {
+            return;
+        }
+        self.paused = true;
+        self.single_steps = 0;
+        self.expect_seek();
+        if self.ghost_status.is_some() {
+            self.ghost_seeks_in_flight += 1;
+            self.pending_ghost_snapshots.clear();
+        }
+        self.request_seek.emit(tick);
+        self.needs_render = true;
+    }
+
+    /// Discards queued snapshots until the worker responds to a seek or code
+    /// reload.
+    pub fn expect_seek(&mut self) {
+        self.seeks_in_flight += 1;
+        self.pending_snapshots.clear();
+    }
+
+    /// Takes keyboard control of the picked ship, or hands the piloted ship
+    /// back to its AI.
+    fn toggle_piloting(&mut self) {
+        if let Some(id) = self.piloted_ship_id.take() {
+            self.send_pilot_input(id, None);
+        } else if let Some(id) = self.picked_ship_id {
+            self.piloted_ship_id = Some(id);
+            self.chasing_ship_id = Some(id);
+            self.camera_offset = vector![0.0, 0.0];
+            self.pilot_input = PilotInput::default();
+            self.send_pilot_input(id, Some(self.pilot_input));
+        }
+    }
+
+    fn update_pilot_input(&mut self) {
+        let Some(id) = self.piloted_ship_id else {
+            return;
+        };
+        let axis = |positive: &str, negative: &str| {
+            self.keys_down.contains(positive) as i32 as f64
+                - self.keys_down.contains(negative) as i32 as f64
+        };
+        let input = PilotInput {
+            thrust: axis("w", "s"),
+            strafe: axis("q", "e"),
+            turn: axis("a", "d"),
+            fire: self.keys_down.contains("Enter"),
+        };
+        if input != self.pilot_input {
+            self.pilot_input = input;
+            self.send_pilot_input(id, Some(input));
+        }
+    }
+
+    fn send_pilot_input(&mut self, id: u64, input: Option<PilotInput>) {
+        self.expect_seek();
+        self.request_pilot_input.emit((id, input));
+    }
+
+    pub fn step_backward(&mut self) {
+        if let Some(tick) = self.tick() {
+            self.seek(tick.saturating_sub(1));
+        }
+    }
+
+    pub fn on_seek_snapshot(&mut self, snapshot: Snapshot) {
+        if snapshot.nonce != self.nonce || self.seeks_in_flight == 0 {
+            return;
+        }
+        self.seeks_in_flight -= 1;
+        self.max_tick = self.max_tick.max(tick_of(&snapshot));
+        self.physics_time = std::time::Duration::from_secs_f64(snapshot.time);
+        self.pending_snapshots.clear();
+        self.pending_snapshots.push_back(snapshot);
+        self.update_snapshot();
+        self.update_picked();
+        self.update_timeline();
+        self.needs_render = true;
+    }
+
     pub fn update_snapshot(&mut self) {
         while self.pending_snapshots.len() > SNAPSHOT_PRELOAD / 2
             && std::time::Duration::from_secs_f64(self.pending_snapshots[1].time)
@@ -415,6 +636,8 @@ impl UI {
             }
 
             self.status = snapshot.status;
+            self.update_ghost();
+            self.update_plots();
         }
 
         if let Some(snapshot) = self.snapshot.as_mut() {
@@ -484,6 +707,20 @@ impl UI {
             world_position
         );
 
+        if e.buttons() != 0 && self.drag_start.is_none() {
+            if let Some(target) = self
+                .renderer
+                .minimap_target(canvas_position.x, canvas_position.y)
+            {
+                // Jump the camera to the clicked position on the minimap.
+                self.chasing_ship_id = None;
+                self.camera_offset = target.cast::<f32>() - self.camera_focus;
+                self.needs_render = true;
+                let _ = self.canvas.focus();
+                return;
+            }
+        }
+
         if e.buttons() == 0 {
             self.touches.remove(&e.pointer_id());
             if let Some(start_canvas_position) = std::mem::take(&mut self.drag_start) {
@@ -535,8 +772,12 @@ impl UI {
             self.camera_offset += diff;
             self.renderer.set_view(self.zoom, self.camera_target());
         } else {
-            self.touches
-                .insert(e.pointer_id(), Touch { world_camera_offset });
+            self.touches.insert(
+                e.pointer_id(),
+                Touch {
+                    world_camera_offset,
+                },
+            );
         }
 
         if self.drag_start.is_none() {
@@ -556,6 +797,39 @@ impl UI {
         self.status
     }
 
+    /// The tick of the displayed snapshot.
+    pub fn tick(&self) -> Option<u32> {
+        self.snapshot.as_ref().map(tick_of)
+    }
+
+    fn update_timeline(&self) {
+        if let Some(elem) = self.timeline_ref.cast::<HtmlInputElement>() {
+            elem.set_max(&self.max_tick.to_string());
+            elem.set_value_as_number(self.tick().unwrap_or(0) as f64);
+        }
+    }
+
+    /// Names of the debug layers drawn on so far.
+    pub fn debug_layers(&self) -> &[String] {
+        self.snapshot
+            .as_ref()
+            .map(|s| s.debug_layers.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn layer_hidden(&self, name: &str) -> bool {
+        self.hidden_layers.contains(name)
+    }
+
+    pub fn toggle_layer(&mut self, name: &str) {
+        if !self.hidden_layers.remove(name) {
+            self.hidden_layers.insert(name.to_string());
+        }
+        setting::write("hidden_debug_layers", &self.hidden_layers);
+        self.renderer.set_hidden_layers(self.hidden_layers.clone());
+        self.needs_render = true;
+    }
+
     pub fn snapshot(&self) -> Option<Snapshot> {
         self.snapshot.clone()
     }
@@ -572,20 +846,32 @@ impl UI {
                 health,
                 fuel,
                 active_abilities,
+                peak_memory,
                 ..
             } = ship;
-            let debug_text = self
-                .snapshot
-                .as_ref()
-                .and_then(|s| s.debug_text.get(&self.picked_ship_id.unwrap()))
+            let snapshot = self.snapshot.as_ref().unwrap();
+            let debug_text = snapshot
+                .debug_text
+                .get(&ship.id)
                 .cloned()
                 .unwrap_or_default();
+            let watch_text = plot::watch_text(ship.id, snapshot);
             if let Some(elem) = self.picked_ref.cast::<Element>() {
                 let fuel_text = if let Some(fuel) = fuel {
                     format!("Fuel: {:.0}\n", fuel)
                 } else {
                     "".to_string()
                 };
+                let memory_text = if let Some(peak_memory) = peak_memory {
+                    let memory_limit = self.snapshot.as_ref().unwrap().memory_limit;
+                    format!(
+                        "Memory: {} / {} KB\n",
+                        peak_memory.div_ceil(1024),
+                        memory_limit / 1024
+                    )
+                } else {
+                    "".to_string()
+                };
                 let active_abilities_text = if !active_abilities.is_empty() {
                     format!(
                         "Active abilities: {}\n",
@@ -599,15 +885,29 @@ impl UI {
                     "".to_string()
                 };
                 elem.set_text_content(Some(&format!(
-                    "{class:?}\nTeam: {team:?}\nHealth: {health:.0}\n{fuel_text}{active_abilities_text}{debug_text}"
+                    "{class:?}\nTeam: {team:?}\nHealth: {health:.0}\n{fuel_text}{memory_text}{active_abilities_text}{watch_text}{debug_text}"
                 )));
             }
         } else if let Some(elem) = self.picked_ref.cast::<Element>() {
             elem.set_text_content(Some(""));
         }
+        if self.plotted_ship != self.picked_ship_id {
+            self.update_plots();
+        }
         self.renderer.set_picked_ship(self.picked_ship_id);
     }
 
+    fn update_plots(&mut self) {
+        self.plotted_ship = self.picked_ship_id;
+        let html = match (self.picked_ship_id, self.snapshot.as_ref()) {
+            (Some(ship), Some(snapshot)) => plot::plots_html(ship, tick_of(snapshot), snapshot),
+            _ => String::new(),
+        };
+        if let Some(elem) = self.plots_ref.cast::<Element>() {
+            elem.set_inner_html(&html);
+        }
+    }
+
     pub fn set_status_message(&self, text: &str) {
         if let Some(elem) = self.status_ref.cast::<Element>() {
             elem.set_text_content(Some(text));
@@ -629,6 +929,19 @@ impl UI {
     }
 }
 
+fn outcome(status: Status, score_time: f64) -> String {
+    match status {
+        Status::Running => "RUNNING".to_string(),
+        Status::Victory { team: 0 } => format!("VICTORY in {:.3}s", score_time),
+        Status::Victory { .. } | Status::Failed => "DEFEAT".to_string(),
+        Status::Draw => "DRAW".to_string(),
+    }
+}
+
+fn tick_of(snapshot: &Snapshot) -> u32 {
+    (snapshot.time / PHYSICS_TICK_LENGTH).round() as u32
+}
+
 #[derive(Debug)]
 struct Touch {
     world_camera_offset: Point2<f64>,
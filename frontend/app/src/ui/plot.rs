@@ -0,0 +1,61 @@
+use oort_simulator::debug::{ValueKind, MAX_PLOT_POINTS};
+use oort_simulator::snapshot::Snapshot;
+use std::fmt::Write;
+
+const WIDTH: f64 = 300.0;
+const HEIGHT: f64 = 60.0;
+
+/// Renders one chart per `dbg::plot` series for `ship` as SVG, ending at
+/// `tick`.
+pub fn plots_html(ship: u64, tick: u32, snapshot: &Snapshot) -> String {
+    let Some(series) = snapshot.plots.get(&ship) else {
+        return String::new();
+    };
+    let first_tick = tick.saturating_sub(MAX_PLOT_POINTS - 1);
+    let mut html = String::new();
+    for (name, points) in series.iter() {
+        let Some(&(_, latest)) = points.back() else {
+            continue;
+        };
+        let (min, max) = points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(_, v)| {
+                (lo.min(v), hi.max(v))
+            });
+        let range = if max > min { max - min } else { 1.0 };
+        let mut polyline = String::new();
+        for &(t, v) in points.iter() {
+            let x = t.saturating_sub(first_tick) as f64 / (MAX_PLOT_POINTS - 1) as f64 * WIDTH;
+            let y = HEIGHT - (v - min) / range * HEIGHT;
+            let _ = write!(polyline, "{x:.1},{y:.1} ");
+        }
+        let _ = write!(
+            html,
+            r#"<div class="plot"><div>{} = {latest:.3} <span>[{min:.3}, {max:.3}]</span></div><svg width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" preserveAspectRatio="none"><polyline points="{polyline}" /></svg></div>"#,
+            escape(name)
+        );
+    }
+    html
+}
+
+/// Lists the `dbg::watch` values for `ship` in a snapshot.
+pub fn watch_text(ship: u64, snapshot: &Snapshot) -> String {
+    let mut text = String::new();
+    for value in snapshot
+        .debug_values
+        .get(&ship)
+        .iter()
+        .flat_map(|values| values.iter())
+        .filter(|v| v.kind == ValueKind::Watch)
+    {
+        let _ = writeln!(text, "{}: {:.3}", value.name, value.value);
+    }
+    text
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,14 +1,35 @@
 use oort_simulator::scenario;
-use oort_version_control::Version;
+use oort_version_control::diff::{self, Change, DiffLine};
+use oort_version_control::{Tag, Version, VersionControl};
 use wasm_bindgen::JsCast;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+/// Unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
 #[derive(Debug)]
 pub enum Msg {
     StartFetch(String),
-    FetchFinished(Vec<Version>),
+    FetchFinished(Box<Fetched>),
     FetchFailed,
+    SelectBranch(String),
+    NewBranch,
+    CreateTag(String),
+    DeleteTag(String),
+    SelectDiff { new: bool, version_id: String },
+    DiffFinished(Vec<DiffLine>),
+    Export,
+    Import,
+}
+
+#[derive(Debug)]
+pub struct Fetched {
+    scenario_name: String,
+    versions: Vec<Version>,
+    tags: Vec<Tag>,
+    branches: Vec<String>,
+    current_branch: String,
 }
 
 #[derive(Properties, Clone, PartialEq)]
@@ -17,11 +38,20 @@ pub struct VersionsWindowProps {
     pub scenario_name: String,
     pub load_cb: Callback<String>,
     pub save_cb: Callback<String>,
+    pub branch_cb: Callback<String>,
+    pub compare_cb: Callback<String>,
     pub update_timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 pub struct VersionsWindow {
+    scenario_name: String,
     versions: Vec<Version>,
+    tags: Vec<Tag>,
+    branches: Vec<String>,
+    current_branch: String,
+    diff_old: Option<String>,
+    diff_new: Option<String>,
+    diff: Option<Vec<DiffLine>>,
 }
 
 impl Component for VersionsWindow {
@@ -33,7 +63,14 @@ impl Component for VersionsWindow {
             .link()
             .send_message(Msg::StartFetch(context.props().scenario_name.clone()));
         Self {
+            scenario_name: context.props().scenario_name.clone(),
             versions: Vec::new(),
+            tags: Vec::new(),
+            branches: Vec::new(),
+            current_branch: oort_version_control::DEFAULT_BRANCH.to_string(),
+            diff_old: None,
+            diff_new: None,
+            diff: None,
         }
     }
 
@@ -41,10 +78,8 @@ impl Component for VersionsWindow {
         match msg {
             Msg::StartFetch(scenario_name) => {
                 context.link().send_future(async move {
-                    let version_control =
-                        oort_version_control::VersionControl::new().await.unwrap();
-                    match version_control.list_versions(&scenario_name).await {
-                        Ok(versions) => Msg::FetchFinished(versions),
+                    match fetch(scenario_name).await {
+                        Ok(fetched) => Msg::FetchFinished(Box::new(fetched)),
                         Err(e) => {
                             log::error!("Error fetching versions: {:?}", e);
                             Msg::FetchFailed
@@ -53,11 +88,163 @@ impl Component for VersionsWindow {
                 });
                 false
             }
-            Msg::FetchFinished(versions) => {
-                self.versions = versions;
+            Msg::FetchFinished(fetched) => {
+                if fetched.scenario_name != self.scenario_name {
+                    self.diff_old = None;
+                    self.diff_new = None;
+                    self.diff = None;
+                }
+                self.scenario_name = fetched.scenario_name;
+                self.versions = fetched.versions;
+                self.tags = fetched.tags;
+                self.branches = fetched.branches;
+                self.current_branch = fetched.current_branch;
                 true
             }
             Msg::FetchFailed => false,
+            Msg::SelectBranch(branch) => {
+                if branch == self.current_branch {
+                    return false;
+                }
+                if !self.branches.contains(&branch) {
+                    self.branches.push(branch.clone());
+                    self.branches.sort();
+                }
+                self.current_branch = branch.clone();
+                if self.scenario_name == context.props().scenario_name {
+                    // The game saves the editor code and loads the branch.
+                    context.props().branch_cb.emit(branch);
+                } else {
+                    let scenario_name = self.scenario_name.clone();
+                    context.link().send_future(async move {
+                        let result = async {
+                            let version_control = VersionControl::new().await?;
+                            version_control
+                                .set_current_branch(&scenario_name, &branch)
+                                .await
+                        }
+                        .await;
+                        if let Err(e) = result {
+                            log::error!("Error switching branch: {:?}", e);
+                        }
+                        Msg::StartFetch(scenario_name)
+                    });
+                }
+                true
+            }
+            Msg::NewBranch => {
+                if let Some(branch) = prompt("Name of the new branch:") {
+                    context.link().send_message(Msg::SelectBranch(branch));
+                }
+                false
+            }
+            Msg::CreateTag(version_id) => {
+                let Some(name) = prompt("Name of the tag:") else {
+                    return false;
+                };
+                let scenario_name = self.scenario_name.clone();
+                context.link().send_future(async move {
+                    let result = async {
+                        let version_control = VersionControl::new().await?;
+                        version_control
+                            .create_tag(&scenario_name, &name, &version_id)
+                            .await
+                    }
+                    .await;
+                    if let Err(e) = result {
+                        log::error!("Error creating tag: {:?}", e);
+                    }
+                    Msg::StartFetch(scenario_name)
+                });
+                false
+            }
+            Msg::DeleteTag(name) => {
+                let scenario_name = self.scenario_name.clone();
+                context.link().send_future(async move {
+                    let result = async {
+                        let version_control = VersionControl::new().await?;
+                        version_control.delete_tag(&scenario_name, &name).await
+                    }
+                    .await;
+                    if let Err(e) = result {
+                        log::error!("Error deleting tag: {:?}", e);
+                    }
+                    Msg::StartFetch(scenario_name)
+                });
+                false
+            }
+            Msg::SelectDiff { new, version_id } => {
+                if new {
+                    self.diff_new = Some(version_id);
+                } else {
+                    self.diff_old = Some(version_id);
+                }
+                if let (Some(old_id), Some(new_id)) = (self.diff_old.clone(), self.diff_new.clone())
+                {
+                    context.link().send_future(async move {
+                        let result = async {
+                            let version_control = VersionControl::new().await?;
+                            version_control.diff_versions(&old_id, &new_id).await
+                        }
+                        .await;
+                        match result {
+                            Ok(lines) => Msg::DiffFinished(lines),
+                            Err(e) => {
+                                log::error!("Error comparing versions: {:?}", e);
+                                Msg::FetchFailed
+                            }
+                        }
+                    });
+                }
+                true
+            }
+            Msg::DiffFinished(lines) => {
+                self.diff = Some(lines);
+                true
+            }
+            Msg::Export => {
+                let scenario_name = self.scenario_name.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = async {
+                        let version_control = VersionControl::new().await?;
+                        version_control.export_scenario(&scenario_name).await
+                    }
+                    .await;
+                    match result {
+                        Ok(text) => crate::js::filesystem::download(
+                            &format!("{scenario_name}.versions.json"),
+                            &text,
+                        ),
+                        Err(e) => log::error!("Error exporting versions: {:?}", e),
+                    }
+                });
+                false
+            }
+            Msg::Import => {
+                let fallback = self.scenario_name.clone();
+                context.link().send_future(async move {
+                    let text = match crate::js::filesystem::open_archive().await {
+                        Ok(text) => text.as_string().unwrap_or_default(),
+                        Err(e) => {
+                            log::error!("Error opening archive: {:?}", e);
+                            return Msg::FetchFailed;
+                        }
+                    };
+                    let result = async {
+                        let version_control = VersionControl::new().await?;
+                        version_control.import_archive(&text).await
+                    }
+                    .await;
+                    match result {
+                        Ok(scenario_name) => Msg::StartFetch(scenario_name),
+                        Err(e) => {
+                            log::error!("Error importing versions: {:?}", e);
+                            Msg::StartFetch(fallback)
+                        }
+                    }
+                });
+                false
+            }
         }
     }
 
@@ -79,30 +266,19 @@ impl Component for VersionsWindow {
             })
         };
 
-        let versions_html = if self.versions.is_empty() {
+        let versions: Vec<&Version> = self
+            .versions
+            .iter()
+            .filter(|version| version.branch == self.current_branch)
+            .collect();
+        let versions_html = if versions.is_empty() {
             html! {
                 <li>{ "No previous versions found." }</li>
             }
         } else {
-            self.versions
+            versions
                 .iter()
-                .map(|version| {
-                    let onclick = {
-                        let version_id = version.id.clone();
-                        context.props().load_cb.reform(move |_| version_id.clone())
-                    };
-                    let ts = version
-                        .timestamp
-                        .with_timezone(&chrono::Local)
-                        .format("%Y-%m-%d %H:%M:%S")
-                        .to_string();
-                    let text = if let Some(label) = &version.label {
-                        format!("{ts} ({label})")
-                    } else {
-                        ts
-                    };
-                    html! { <li><a href="#" {onclick}>{ text }</a></li> }
-                })
+                .map(|version| self.render_version(context, version))
                 .collect::<Html>()
         };
 
@@ -113,7 +289,7 @@ impl Component for VersionsWindow {
         let scenario_options = scenario_names
             .iter()
             .map(|scenario_name| {
-                let selected = scenario_name == &context.props().scenario_name;
+                let selected = scenario_name == &self.scenario_name;
                 html! { <option value={scenario_name.clone()} {selected}>{ scenario_name }</option> }
             })
             .collect::<Html>();
@@ -124,6 +300,20 @@ impl Component for VersionsWindow {
             Msg::StartFetch(scenario_name)
         });
 
+        let branch_options = self
+            .branches
+            .iter()
+            .map(|branch| {
+                let selected = branch == &self.current_branch;
+                html! { <option value={branch.clone()} {selected}>{ branch }</option> }
+            })
+            .collect::<Html>();
+
+        let branch_select_cb = context.link().callback(move |e: Event| {
+            let target: web_sys::EventTarget = e.target().unwrap();
+            Msg::SelectBranch(target.unchecked_into::<HtmlInputElement>().value())
+        });
+
         create_portal(
             html! {
                 <div class="versions">
@@ -132,14 +322,166 @@ impl Component for VersionsWindow {
                         <input type="text" ref={input_ref} />
                         <button type="submit">{ "Save" }</button>
                     </form>
-                    <p>{ "This list shows previous versions of your code for this scenario. Click on a version to load it." }</p>
+                    <p>{ "This list shows previous versions of your code for this scenario. Click on a version to load it. Select versions in the two columns of buttons to see the changes between them, or click \"compare\" to run a version against the current code." }</p>
                     <p><select onchange={scenario_select_cb}>{scenario_options}</select></p>
+                    <p>
+                        { "Branch: " }
+                        <select onchange={branch_select_cb}>{branch_options}</select>
+                        <button onclick={context.link().callback(|_| Msg::NewBranch)}>{ "New branch" }</button>
+                    </p>
+                    <p>
+                        <button onclick={context.link().callback(|_| Msg::Export)}>{ "Export" }</button>
+                        <button onclick={context.link().callback(|_| Msg::Import)}>{ "Import" }</button>
+                    </p>
                     <ul>
                     { versions_html }
                     </ul>
+                    { self.render_diff() }
                 </div>
             },
             context.props().host.clone(),
         )
     }
 }
+
+impl VersionsWindow {
+    fn render_version(&self, context: &yew::Context<Self>, version: &Version) -> Html {
+        let onclick = {
+            let version_id = version.id.clone();
+            context.props().load_cb.reform(move |_| version_id.clone())
+        };
+        let ts = version
+            .timestamp
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let text = if let Some(label) = &version.label {
+            format!("{ts} ({label})")
+        } else {
+            ts
+        };
+
+        let diff_radio = |new: bool| {
+            let selected = if new { &self.diff_new } else { &self.diff_old };
+            let checked = selected.as_ref() == Some(&version.id);
+            let version_id = version.id.clone();
+            let onchange = context.link().callback(move |_| Msg::SelectDiff {
+                new,
+                version_id: version_id.clone(),
+            });
+            let name = if new { "diff-new" } else { "diff-old" };
+            html! { <input type="radio" {name} {checked} {onchange} /> }
+        };
+
+        let tags = self
+            .tags
+            .iter()
+            .filter(|tag| tag.version_id == version.id)
+            .map(|tag| {
+                let name = tag.name.clone();
+                let ondelete = context
+                    .link()
+                    .callback(move |_| Msg::DeleteTag(name.clone()));
+                html! {
+                    <span class="tag">
+                        { &tag.name }
+                        <a href="#" title="Delete tag" onclick={ondelete}>{ "×" }</a>
+                    </span>
+                }
+            })
+            .collect::<Html>();
+
+        // Comparisons run in the current scenario.
+        let compare = if self.scenario_name == context.props().scenario_name {
+            let version_id = version.id.clone();
+            let onclick = context
+                .props()
+                .compare_cb
+                .reform(move |_| version_id.clone());
+            html! { <a href="#" class="compare" title="Run this version against the current code" {onclick}>{ "compare" }</a> }
+        } else {
+            html! {}
+        };
+
+        let ontag = {
+            let version_id = version.id.clone();
+            context
+                .link()
+                .callback(move |_| Msg::CreateTag(version_id.clone()))
+        };
+
+        html! {
+            <li>
+                { diff_radio(false) }
+                { diff_radio(true) }
+                <a href="#" {onclick}>{ text }</a>
+                { tags }
+                <a href="#" class="add-tag" title="Add tag" onclick={ontag}>{ "+tag" }</a>
+                { compare }
+            </li>
+        }
+    }
+
+    fn render_diff(&self) -> Html {
+        let Some(lines) = &self.diff else {
+            return html! {};
+        };
+        let hunks = diff::hunks(lines, DIFF_CONTEXT);
+        if hunks.is_empty() {
+            return html! { <p>{ "The selected versions are identical." }</p> };
+        }
+        let hunks_html = hunks
+            .iter()
+            .map(|hunk| {
+                let first = &hunk[0];
+                let header = format!(
+                    "@@ -{} +{} @@",
+                    first.old_line.unwrap_or(0),
+                    first.new_line.unwrap_or(0)
+                );
+                let lines_html = hunk
+                    .iter()
+                    .map(|line| {
+                        let (class, prefix) = match line.change {
+                            Change::Equal => ("", ' '),
+                            Change::Insert => ("added", '+'),
+                            Change::Delete => ("removed", '-'),
+                        };
+                        html! { <div {class}>{ format!("{prefix}{}", line.text) }</div> }
+                    })
+                    .collect::<Html>();
+                html! {
+                    <>
+                        <div class="hunk">{ header }</div>
+                        { lines_html }
+                    </>
+                }
+            })
+            .collect::<Html>();
+        html! { <pre class="diff">{ hunks_html }</pre> }
+    }
+}
+
+async fn fetch(scenario_name: String) -> Result<Fetched, oort_version_control::Error> {
+    let version_control = VersionControl::new().await?;
+    Ok(Fetched {
+        versions: version_control.list_versions(&scenario_name).await?,
+        tags: version_control.list_tags(&scenario_name).await?,
+        branches: version_control.list_branches(&scenario_name).await?,
+        current_branch: version_control.get_current_branch(&scenario_name).await?,
+        scenario_name,
+    })
+}
+
+fn prompt(message: &str) -> Option<String> {
+    let value = gloo_utils::window()
+        .prompt_with_message(message)
+        .ok()
+        .flatten()?;
+    let value = value.trim().to_string();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
@@ -0,0 +1,251 @@
+use super::{buffer_arena, geometry, glutil};
+use glutil::VertexAttribBuilder;
+use nalgebra::{vector, Matrix4, Point2, Vector2, Vector4};
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
+use WebGl2RenderingContext as gl;
+
+/// Draws circles and arcs by evaluating the distance to the outline in the
+/// fragment shader, one quad per circle.
+pub struct CircleRenderer {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    transform_loc: WebGlUniformLocation,
+    line_width_loc: WebGlUniformLocation,
+    buffer_arena: buffer_arena::BufferArena,
+    vao: WebGlVertexArrayObject,
+}
+
+pub struct DrawSet {
+    projection_matrix: Matrix4<f32>,
+    line_width: f32,
+    draws: Vec<Draw>,
+}
+
+pub struct Draw {
+    num_instances: usize,
+    vertices_token: buffer_arena::Token,
+    num_vertices: usize,
+    attribs_token: buffer_arena::Token,
+}
+
+/// A circle, or an arc if `span` is less than a full turn.
+#[derive(Copy, Clone, Debug)]
+pub struct Circle {
+    pub center: Point2<f64>,
+    pub radius: f64,
+    /// Angle where the arc begins, counterclockwise from the x axis.
+    pub start_angle: f64,
+    /// Angle covered by the arc, up to TAU.
+    pub span: f64,
+    pub color: Vector4<f32>,
+    /// Opacity of the interior relative to `color`, or zero for an outline.
+    pub fill_alpha: f32,
+}
+
+struct Attribs {
+    center: Vector2<f32>,
+    radius: f32,
+    start_angle: f32,
+    span: f32,
+    fill_alpha: f32,
+    color: Vector4<f32>,
+}
+
+impl CircleRenderer {
+    pub fn new(context: WebGl2RenderingContext) -> Result<Self, JsValue> {
+        let vert_shader = glutil::compile_shader(
+            &context,
+            gl::VERTEX_SHADER,
+            r#"#version 300 es
+uniform mat4 transform;
+uniform float line_width;
+layout(location = 0) in vec4 vertex;
+layout(location = 1) in vec2 center;
+layout(location = 2) in float radius;
+layout(location = 3) in float start_angle;
+layout(location = 4) in float span;
+layout(location = 5) in float fill_alpha;
+layout(location = 6) in vec4 color;
+out vec2 varying_offset;
+flat out float varying_radius;
+flat out float varying_start_angle;
+flat out float varying_span;
+flat out float varying_fill_alpha;
+flat out vec4 varying_color;
+
+void main() {
+    varying_offset = vertex.xy * 2.0 * (radius + 2.0 * line_width);
+    gl_Position = transform * vec4(center + varying_offset, 0.0, 1.0);
+    varying_radius = radius;
+    varying_start_angle = start_angle;
+    varying_span = span;
+    varying_fill_alpha = fill_alpha;
+    varying_color = color;
+}
+    "#,
+        )?;
+        let frag_shader = glutil::compile_shader(
+            &context,
+            gl::FRAGMENT_SHADER,
+            r#"#version 300 es
+precision highp float;
+uniform float line_width;
+in vec2 varying_offset;
+flat in float varying_radius;
+flat in float varying_start_angle;
+flat in float varying_span;
+flat in float varying_fill_alpha;
+flat in vec4 varying_color;
+out vec4 fragmentColor;
+const float TAU = 6.283185307179586;
+
+void main() {
+    float distance = length(varying_offset);
+    float angle = mod(atan(varying_offset.y, varying_offset.x) - varying_start_angle, TAU);
+    float stroke = 1.0 - smoothstep(0.5 * line_width, 1.5 * line_width, abs(distance - varying_radius));
+    if (angle > varying_span) {
+        stroke = 0.0;
+    }
+    float fill = distance < varying_radius ? varying_fill_alpha : 0.0;
+    float alpha = max(stroke, fill);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    fragmentColor = vec4(varying_color.rgb, varying_color.a * alpha);
+}
+    "#,
+        )?;
+        let program = glutil::link_program(&context, &vert_shader, &frag_shader)?;
+
+        let transform_loc = context
+            .get_uniform_location(&program, "transform")
+            .ok_or("did not find uniform")?;
+
+        let line_width_loc = context
+            .get_uniform_location(&program, "line_width")
+            .ok_or("did not find uniform")?;
+
+        let vao = context
+            .create_vertex_array()
+            .ok_or("failed to create vertex array")?;
+
+        assert_eq!(context.get_error(), gl::NO_ERROR);
+
+        Ok(Self {
+            context: context.clone(),
+            program,
+            transform_loc,
+            line_width_loc,
+            buffer_arena: buffer_arena::BufferArena::new(
+                "circle_renderer",
+                context,
+                gl::ARRAY_BUFFER,
+                1024 * 1024,
+            )?,
+            vao,
+        })
+    }
+
+    /// `line_width` is the width of the outline in meters.
+    pub fn upload(
+        &mut self,
+        projection_matrix: &Matrix4<f32>,
+        circles: &[Circle],
+        line_width: f32,
+    ) -> DrawSet {
+        let vertices = geometry::quad();
+        let vertices_token = self.buffer_arena.write(&vertices);
+
+        let mut draws = vec![];
+        for circles in circles.chunks(1000) {
+            let attribs: Vec<Attribs> = circles
+                .iter()
+                .map(|circle| Attribs {
+                    center: vector![circle.center.x as f32, circle.center.y as f32],
+                    radius: circle.radius as f32,
+                    start_angle: circle.start_angle as f32,
+                    span: circle.span as f32,
+                    fill_alpha: circle.fill_alpha,
+                    color: circle.color,
+                })
+                .collect();
+            draws.push(Draw {
+                num_instances: circles.len(),
+                vertices_token: vertices_token.clone(),
+                num_vertices: vertices.len(),
+                attribs_token: self.buffer_arena.write(&attribs),
+            });
+        }
+
+        DrawSet {
+            projection_matrix: *projection_matrix,
+            line_width,
+            draws,
+        }
+    }
+
+    pub fn draw(&mut self, drawset: &DrawSet) {
+        if drawset.draws.is_empty() {
+            return;
+        }
+
+        self.context.use_program(Some(&self.program));
+        self.context.bind_vertex_array(Some(&self.vao));
+
+        self.context.uniform_matrix4fv_with_f32_array(
+            Some(&self.transform_loc),
+            false,
+            drawset.projection_matrix.data.as_slice(),
+        );
+        self.context
+            .uniform1f(Some(&self.line_width_loc), drawset.line_width);
+
+        for draw in &drawset.draws {
+            // vertex
+            VertexAttribBuilder::new(&self.context)
+                .data_token(&draw.vertices_token)
+                .index(0)
+                .size(2)
+                .build();
+
+            // attribs
+            let vab = VertexAttribBuilder::new(&self.context)
+                .data_token(&draw.attribs_token)
+                .divisor(1);
+            vab.index(1)
+                .size(2)
+                .offset(offset_of!(Attribs, center))
+                .build();
+            vab.index(2)
+                .size(1)
+                .offset(offset_of!(Attribs, radius))
+                .build();
+            vab.index(3)
+                .size(1)
+                .offset(offset_of!(Attribs, start_angle))
+                .build();
+            vab.index(4)
+                .size(1)
+                .offset(offset_of!(Attribs, span))
+                .build();
+            vab.index(5)
+                .size(1)
+                .offset(offset_of!(Attribs, fill_alpha))
+                .build();
+            vab.index(6)
+                .size(4)
+                .offset(offset_of!(Attribs, color))
+                .build();
+
+            self.context.draw_arrays_instanced(
+                gl::TRIANGLE_STRIP,
+                0,
+                draw.num_vertices as i32,
+                draw.num_instances as i32,
+            );
+        }
+
+        self.context.bind_vertex_array(None);
+    }
+}
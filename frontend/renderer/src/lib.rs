@@ -1,11 +1,13 @@
 pub mod blur;
 pub mod buffer_arena;
 pub mod bullet_renderer;
+pub mod circle_renderer;
 pub mod flare_renderer;
 pub mod geometry;
 pub mod glutil;
 pub mod grid_renderer;
 pub mod line_renderer;
+pub mod minimap;
 pub mod particle_renderer;
 pub mod ship_renderer;
 pub mod text_renderer;
@@ -16,15 +18,18 @@ extern crate memoffset;
 
 use blur::Blur;
 use bullet_renderer::BulletRenderer;
+use circle_renderer::{Circle, CircleRenderer};
 use flare_renderer::FlareRenderer;
 use grid_renderer::GridRenderer;
 use line_renderer::LineRenderer;
 use nalgebra::{point, vector, Matrix4, Point2};
 use oort_api::Text;
+use oort_simulator::debug::Geometry;
 use oort_simulator::simulation::Line;
 use oort_simulator::snapshot::Snapshot;
 use particle_renderer::ParticleRenderer;
 use ship_renderer::ShipRenderer;
+use std::collections::HashSet;
 use text_renderer::TextRenderer;
 use trail_renderer::TrailRenderer;
 use wasm_bindgen::prelude::*;
@@ -32,11 +37,18 @@ use wasm_bindgen::JsCast;
 use web_sys::{HtmlCanvasElement, WebGl2RenderingContext};
 use WebGl2RenderingContext as gl;
 
+/// Opacity of ships in the ghost snapshot.
+const GHOST_ALPHA: f32 = 0.3;
+
+/// Opacity of filled debug shapes.
+const FILL_ALPHA: f32 = 0.25;
+
 pub struct Renderer {
     canvas: HtmlCanvasElement,
     context: WebGl2RenderingContext,
     grid_renderer: GridRenderer,
     line_renderer: LineRenderer,
+    circle_renderer: CircleRenderer,
     ship_renderer: ShipRenderer,
     bullet_renderer: BulletRenderer,
     particle_renderer: ParticleRenderer,
@@ -50,6 +62,15 @@ pub struct Renderer {
     picked_ship: Option<u64>,
     blur_enabled: bool,
     nlips_enabled: bool,
+    ghost: Option<Snapshot>,
+    hidden_layers: HashSet<String>,
+    minimap_enabled: bool,
+    tactical_enabled: bool,
+    /// Bottom-left and top-right corners of the view in world coordinates.
+    view_bounds: [Point2<f64>; 2],
+    /// Size of a CSS pixel in meters.
+    pixel_size: f64,
+    world_size: f64,
 }
 
 impl Renderer {
@@ -77,6 +98,7 @@ impl Renderer {
             context: context.clone(),
             grid_renderer: GridRenderer::new(context.clone())?,
             line_renderer: LineRenderer::new(context.clone())?,
+            circle_renderer: CircleRenderer::new(context.clone())?,
             ship_renderer: ShipRenderer::new(context.clone())?,
             bullet_renderer: BulletRenderer::new(context.clone())?,
             particle_renderer: ParticleRenderer::new(context.clone())?,
@@ -90,6 +112,13 @@ impl Renderer {
             picked_ship: None,
             blur_enabled: true,
             nlips_enabled: false,
+            ghost: None,
+            hidden_layers: HashSet::new(),
+            minimap_enabled: true,
+            tactical_enabled: false,
+            view_bounds: [point![0.0, 0.0], point![0.0, 0.0]],
+            pixel_size: 1.0,
+            world_size: 0.0,
         })
     }
 
@@ -105,8 +134,13 @@ impl Renderer {
         let znear = -1.0;
         let zfar = 1.0;
         self.projection_matrix = Matrix4::new_orthographic(left, right, bottom, top, znear, zfar);
+        self.view_bounds = [
+            point![left as f64, bottom as f64],
+            point![right as f64, top as f64],
+        ];
 
         let pixel_size = (self.unproject(1, 0) - self.unproject(0, 0)).x as f32;
+        self.pixel_size = pixel_size as f64;
         let zoom_factor = 2e-3 / zoom;
         self.base_line_width = (zoom_factor - 0.01 * zoom_factor * zoom_factor)
             .clamp(pixel_size * 2.0, 3.0 * pixel_size);
@@ -120,6 +154,11 @@ impl Renderer {
         self.picked_ship = id;
     }
 
+    /// Sets the names of debug layers that shouldn't be drawn.
+    pub fn set_hidden_layers(&mut self, layers: HashSet<String>) {
+        self.hidden_layers = layers;
+    }
+
     pub fn unproject(&self, x: i32, y: i32) -> Point2<f64> {
         let inverse_matrix = self.projection_matrix.try_inverse().unwrap();
         let dpr = gloo_utils::window().device_pixel_ratio() as f32;
@@ -168,7 +207,18 @@ impl Renderer {
             self.base_line_width,
             zoom,
             self.nlips_enabled,
+            1.0,
         );
+        let ghost_ship_drawset = self.ghost.as_ref().map(|ghost| {
+            self.ship_renderer.upload(
+                &self.projection_matrix,
+                ghost,
+                self.base_line_width,
+                zoom,
+                self.nlips_enabled,
+                GHOST_ALPHA,
+            )
+        });
         let bullet_drawset =
             self.bullet_renderer
                 .upload(&self.projection_matrix, snapshot, self.base_line_width);
@@ -200,6 +250,20 @@ impl Renderer {
             .line_renderer
             .upload(&self.projection_matrix, &snapshot.scenario_lines);
 
+        let shapes = snapshot
+            .debug_shapes
+            .iter()
+            .filter(|(ship, _)| self.debug || self.picked_ship == Some(**ship))
+            .flat_map(|(_, shapes)| shapes.iter())
+            .filter(|shape| {
+                shape.layer == 0
+                    || snapshot
+                        .debug_layers
+                        .get(shape.layer as usize - 1)
+                        .map_or(true, |name| !self.hidden_layers.contains(name))
+            })
+            .collect::<Vec<_>>();
+
         let debug_line_drawset = {
             let mut lines: Vec<Line> = Vec::new();
             if self.debug {
@@ -213,9 +277,48 @@ impl Renderer {
                     }
                 }
             }
+            for shape in shapes.iter() {
+                lines.extend(shape.lines());
+            }
             self.line_renderer.upload(&self.projection_matrix, &lines)
         };
 
+        let debug_circle_drawset = {
+            let circles = shapes
+                .iter()
+                .filter_map(|shape| {
+                    let (center, radius, start_angle, span) = shape.arc()?;
+                    Some(Circle {
+                        center,
+                        radius,
+                        start_angle,
+                        span,
+                        color: shape.color,
+                        fill_alpha: if shape.filled
+                            && matches!(shape.geometry, Geometry::Circle { .. })
+                        {
+                            FILL_ALPHA
+                        } else {
+                            0.0
+                        },
+                    })
+                })
+                .collect::<Vec<_>>();
+            self.circle_renderer
+                .upload(&self.projection_matrix, &circles, self.pixel_size as f32)
+        };
+
+        let debug_fill_drawset = {
+            let mut triangles = Vec::new();
+            for shape in shapes.iter() {
+                let mut color = shape.color;
+                color.w *= FILL_ALPHA;
+                triangles.extend(shape.triangles().into_iter().map(|t| (t, color)));
+            }
+            self.line_renderer
+                .upload_triangles(&self.projection_matrix, &triangles)
+        };
+
         self.context.viewport(0, 0, screen_width, screen_height);
 
         if self.blur_enabled {
@@ -254,10 +357,97 @@ impl Renderer {
             self.particle_renderer
                 .draw(&particle_drawset, 5.0 * self.base_line_width);
             self.line_renderer.draw(&scenario_line_drawset);
+            self.line_renderer.draw(&debug_fill_drawset);
+            self.circle_renderer.draw(&debug_circle_drawset);
             self.line_renderer.draw(&debug_line_drawset);
+            if let Some(ghost_ship_drawset) = ghost_ship_drawset.as_ref() {
+                self.ship_renderer.draw(ghost_ship_drawset);
+            }
             self.ship_renderer.draw(&ship_drawset);
+            if self.tactical_enabled {
+                let icons = minimap::tactical_icons(snapshot, self.pixel_size);
+                let drawset = self.line_renderer.upload(&self.projection_matrix, &icons);
+                self.line_renderer.draw(&drawset);
+            }
             self.text_renderer.draw(&text_drawset);
         }
+
+        self.world_size = snapshot.world_size;
+        if self.minimap_enabled {
+            self.render_minimap(snapshot, dpr);
+        }
+    }
+
+    /// Returns the minimap's left, top and size in CSS pixels.
+    fn minimap_rect(&self) -> (f64, f64, f64) {
+        let width = self.canvas.client_width() as f64;
+        let height = self.canvas.client_height() as f64;
+        let size = minimap::SIZE.min(width / 3.0).min(height / 3.0);
+        (
+            width - size - minimap::MARGIN_RIGHT,
+            height - size - minimap::MARGIN_BOTTOM,
+            size,
+        )
+    }
+
+    fn render_minimap(&mut self, snapshot: &Snapshot, dpr: f64) {
+        let (left, _, size) = self.minimap_rect();
+        let r = (snapshot.world_size / 2.0) as f32;
+        let projection_matrix = Matrix4::new_orthographic(-r, r, -r, r, -1.0, 1.0);
+        let (lines, triangles) = minimap::geometry(snapshot, self.view_bounds);
+        let fill_drawset = self
+            .line_renderer
+            .upload_triangles(&projection_matrix, &triangles);
+        let line_drawset = self.line_renderer.upload(&projection_matrix, &lines);
+
+        self.context.viewport(
+            (left * dpr) as i32,
+            (minimap::MARGIN_BOTTOM * dpr) as i32,
+            (size * dpr) as i32,
+            (size * dpr) as i32,
+        );
+        self.line_renderer.draw(&fill_drawset);
+        self.line_renderer.draw(&line_drawset);
+        self.context.viewport(
+            0,
+            0,
+            self.context.drawing_buffer_width(),
+            self.context.drawing_buffer_height(),
+        );
+    }
+
+    /// Converts a position on the canvas in CSS pixels to world coordinates if
+    /// it's on the minimap.
+    pub fn minimap_target(&self, x: i32, y: i32) -> Option<Point2<f64>> {
+        if !self.minimap_enabled || self.world_size == 0.0 {
+            return None;
+        }
+        let (left, top, size) = self.minimap_rect();
+        let fx = (x as f64 - left) / size;
+        let fy = (y as f64 - top) / size;
+        if !(0.0..=1.0).contains(&fx) || !(0.0..=1.0).contains(&fy) {
+            return None;
+        }
+        Some(point![
+            (fx - 0.5) * self.world_size,
+            (0.5 - fy) * self.world_size
+        ])
+    }
+
+    pub fn set_minimap(&mut self, enabled: bool) {
+        self.minimap_enabled = enabled;
+    }
+
+    pub fn get_minimap(&self) -> bool {
+        self.minimap_enabled
+    }
+
+    pub fn set_tactical(&mut self, enabled: bool) {
+        self.tactical_enabled = enabled;
+    }
+
+    pub fn get_tactical(&self) -> bool {
+        self.tactical_enabled
     }
 
     pub fn update(&mut self, snapshot: &Snapshot) {
@@ -280,4 +470,10 @@ impl Renderer {
     pub fn get_nlips(&self) -> bool {
         self.nlips_enabled
     }
+
+    /// Sets a snapshot from another simulation whose ships are drawn
+    /// translucently behind the main snapshot.
+    pub fn set_ghost(&mut self, ghost: Option<Snapshot>) {
+        self.ghost = ghost;
+    }
 }
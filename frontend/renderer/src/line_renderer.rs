@@ -1,6 +1,6 @@
 use super::{buffer_arena, glutil};
 use glutil::VertexAttribBuilder;
-use nalgebra::{vector, Matrix4, Vector4};
+use nalgebra::{vector, Matrix4, Point2, Vector4};
 use oort_simulator::simulation::Line;
 use wasm_bindgen::prelude::*;
 use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
@@ -17,6 +17,8 @@ pub struct LineRenderer {
 pub struct DrawSet {
     projection_matrix: Matrix4<f32>,
     draws: Vec<Draw>,
+    /// gl::LINES or gl::TRIANGLES.
+    mode: u32,
 }
 
 pub struct Draw {
@@ -109,6 +111,40 @@ void main() {
         DrawSet {
             projection_matrix: *projection_matrix,
             draws,
+            mode: gl::LINES,
+        }
+    }
+
+    /// Uploads filled triangles, drawn with the same shader as lines.
+    pub fn upload_triangles(
+        &mut self,
+        projection_matrix: &Matrix4<f32>,
+        triangles: &[([Point2<f64>; 3], Vector4<f32>)],
+    ) -> DrawSet {
+        let mut draws = vec![];
+        for triangles in triangles.chunks(1000) {
+            let mut attribs = vec![];
+            attribs.reserve(3 * triangles.len());
+            for (vertices, color) in triangles {
+                for position in vertices {
+                    let p = position.coords.cast();
+                    attribs.push(Attribs {
+                        vertex: vector![p.x, p.y, 0.0, 1.0],
+                        color: *color,
+                    });
+                }
+            }
+
+            let attribs_token = self.buffer_arena.write(&attribs);
+            draws.push(Draw {
+                num_vertices: attribs.len(),
+                attribs_token,
+            });
+        }
+        DrawSet {
+            projection_matrix: *projection_matrix,
+            draws,
+            mode: gl::TRIANGLES,
         }
     }
 
@@ -121,8 +157,9 @@ void main() {
         self.context.bind_vertex_array(Some(&self.vao));
 
         let mut line_width = 1.0;
+        let passes = if drawset.mode == gl::LINES { 2 } else { 1 };
 
-        for _ in 0..2 {
+        for _ in 0..passes {
             self.context.line_width(line_width);
 
             self.context.uniform_matrix4fv_with_f32_array(
@@ -143,7 +180,7 @@ void main() {
                     .build();
 
                 self.context
-                    .draw_arrays(gl::LINES, 0, draw.num_vertices as i32);
+                    .draw_arrays(drawset.mode, 0, draw.num_vertices as i32);
 
                 line_width *= 2.0;
             }
@@ -0,0 +1,136 @@
+// Geometry for the minimap overlay and the tactical icons. Both are drawn with
+// the line renderer.
+use nalgebra::{point, vector, Point2, Vector4};
+use oort_simulator::color;
+use oort_simulator::debug::{Geometry, Shape};
+use oort_simulator::ship::ShipClass;
+use oort_simulator::simulation::Line;
+use oort_simulator::snapshot::{ShipSnapshot, Snapshot};
+
+/// Side length of the minimap in CSS pixels.
+pub const SIZE: f64 = 200.0;
+/// Distance from the right and bottom edges of the canvas in CSS pixels,
+/// leaving room for the status line.
+pub const MARGIN_RIGHT: f64 = 20.0;
+pub const MARGIN_BOTTOM: f64 = 50.0;
+
+const BULLET_GRID: usize = 40;
+const BACKGROUND: Vector4<f32> = Vector4::new(0.0, 0.0, 0.0, 0.7);
+const BORDER: Vector4<f32> = Vector4::new(0.5, 0.5, 0.5, 1.0);
+const CAMERA: Vector4<f32> = Vector4::new(1.0, 1.0, 1.0, 0.8);
+const BULLET_DENSITY: Vector4<f32> = Vector4::new(1.0, 0.4, 0.2, 0.0);
+
+pub type Triangle = ([Point2<f64>; 3], Vector4<f32>);
+
+/// Builds the minimap in world coordinates. `camera` is the bottom-left and
+/// top-right corners of the main view.
+pub fn geometry(snapshot: &Snapshot, camera: [Point2<f64>; 2]) -> (Vec<Line>, Vec<Triangle>) {
+    let r = snapshot.world_size / 2.0;
+    let mut lines = vec![];
+    let mut triangles = vec![];
+
+    rect(&mut triangles, point![-r, -r], point![r, r], BACKGROUND);
+
+    // Bullets as density, so thousands of them stay readable.
+    let cell = snapshot.world_size / BULLET_GRID as f64;
+    let mut counts = vec![0u32; BULLET_GRID * BULLET_GRID];
+    for bullet in snapshot.bullets.iter() {
+        let x = ((bullet.position.x + r) / cell) as usize;
+        let y = ((bullet.position.y + r) / cell) as usize;
+        if x < BULLET_GRID && y < BULLET_GRID {
+            counts[y * BULLET_GRID + x] += 1;
+        }
+    }
+    for (i, &count) in counts.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let min = point![
+            -r + (i % BULLET_GRID) as f64 * cell,
+            -r + (i / BULLET_GRID) as f64 * cell
+        ];
+        let mut color = BULLET_DENSITY;
+        color.w = (0.15 + count as f32 / 20.0).min(0.8);
+        rect(&mut triangles, min, min + vector![cell, cell], color);
+    }
+
+    // Ships as icons of a fixed size on the minimap.
+    let scale = snapshot.world_size / SIZE;
+    for ship in snapshot.ships.iter() {
+        if let Some(shape) = icon(ship, scale) {
+            triangles.extend(
+                Shape {
+                    filled: true,
+                    ..shape
+                }
+                .triangles()
+                .into_iter()
+                .map(|t| (t, color::team(ship.team))),
+            );
+        }
+    }
+
+    let corners = [point![-r, -r], point![r, -r], point![r, r], point![-r, r]];
+    outline(&mut lines, &corners, BORDER);
+    let [min, max] = camera;
+    let min = point![min.x.max(-r), min.y.max(-r)];
+    let max = point![max.x.min(r), max.y.min(r)];
+    if min.x < max.x && min.y < max.y {
+        let corners = [min, point![max.x, min.y], max, point![min.x, max.y]];
+        outline(&mut lines, &corners, CAMERA);
+    }
+
+    (lines, triangles)
+}
+
+/// Builds class icons that are the same size on screen regardless of zoom.
+/// `pixel_size` is the size of a screen pixel in meters.
+pub fn tactical_icons(snapshot: &Snapshot, pixel_size: f64) -> Vec<Line> {
+    snapshot
+        .ships
+        .iter()
+        .filter_map(|ship| icon(ship, pixel_size))
+        .flat_map(|shape| shape.lines())
+        .collect()
+}
+
+/// Returns an icon for a ship, `scale` meters per pixel.
+fn icon(ship: &ShipSnapshot, scale: f64) -> Option<Shape> {
+    let (sides, radius, angle) = match ship.class {
+        ShipClass::Fighter => (3, 6.0, ship.heading),
+        ShipClass::Frigate => (4, 8.0, ship.heading + std::f64::consts::FRAC_PI_4),
+        ShipClass::Cruiser => (6, 10.0, ship.heading),
+        ShipClass::Missile => (3, 3.0, ship.heading),
+        ShipClass::Torpedo => (4, 4.0, ship.heading),
+        ShipClass::Target => (8, 5.0, 0.0),
+        ShipClass::Planet => return None,
+        ShipClass::Asteroid { .. } | ShipClass::BigAsteroid { .. } => (5, 2.0, 0.0),
+    };
+    Some(Shape {
+        geometry: Geometry::Polygon {
+            center: ship.position,
+            radius: radius * scale,
+            sides,
+            angle,
+        },
+        color: color::team(ship.team),
+        filled: false,
+        layer: 0,
+    })
+}
+
+fn rect(triangles: &mut Vec<Triangle>, min: Point2<f64>, max: Point2<f64>, color: Vector4<f32>) {
+    let (a, b, c, d) = (min, point![max.x, min.y], max, point![min.x, max.y]);
+    triangles.push(([a, b, c], color));
+    triangles.push(([a, c, d], color));
+}
+
+fn outline(lines: &mut Vec<Line>, corners: &[Point2<f64>], color: Vector4<f32>) {
+    for i in 0..corners.len() {
+        lines.push(Line {
+            a: corners[i],
+            b: corners[(i + 1) % corners.len()],
+            color,
+        });
+    }
+}
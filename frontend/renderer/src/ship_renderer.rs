@@ -1,9 +1,9 @@
 use super::{buffer_arena, geometry, glutil};
 use glutil::VertexAttribBuilder;
 use nalgebra::{vector, Matrix4, Vector4};
-use oort_simulator::model;
 use oort_simulator::ship::ShipClass;
 use oort_simulator::snapshot::{ShipSnapshot, Snapshot};
+use oort_simulator::{color, model};
 use wasm_bindgen::prelude::*;
 use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
 use WebGl2RenderingContext as gl;
@@ -91,13 +91,7 @@ void main() {
     }
 
     pub fn team_color(team: i32) -> Vector4<f32> {
-        match team {
-            0 => vector![0.99, 0.98, 0.00, 1.00],
-            1 => vector![0.99, 0.00, 0.98, 1.00],
-            2 => vector![0.13, 0.50, 0.73, 1.00],
-            9 => vector![0.40, 0.40, 0.40, 1.00],
-            _ => vector![1.0, 1.0, 1.0, 1.0],
-        }
+        color::team(team)
     }
 
     pub fn upload(
@@ -107,6 +101,7 @@ void main() {
         base_line_width: f32,
         zoom: f32,
         nlips_enabled: bool,
+        alpha: f32,
     ) -> DrawSet {
         let mut ships_by_class = std::collections::HashMap::<ShipClass, Vec<ShipSnapshot>>::new();
 
@@ -150,6 +145,7 @@ void main() {
                     let p = ship.position.coords.cast::<f32>();
                     let shielded = ship.active_abilities.contains(&oort_api::Ability::Shield);
                     let mut team_color = Self::team_color(ship.team);
+                    team_color.w *= alpha;
                     if nlips_draw {
                         team_color.w *= (nlips_scale / min_nlips_scale - 1.0)
                             .clamp(0.0, 1.0)
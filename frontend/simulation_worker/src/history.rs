@@ -0,0 +1,86 @@
+use oort_simulator::snapshot::Snapshot;
+use oort_simulator::snapshot_encoding::{Decoder, Encoder, Frame};
+
+/// Number of ticks between checkpoints. Looking up a tick decodes the frames
+/// from the checkpoint before it.
+const CHECKPOINT_INTERVAL: u32 = 60;
+
+/// Snapshots of every tick simulated so far, for seeking backwards.
+///
+/// Snapshots are stored as delta encoded frames. Each segment starts with a
+/// full frame, the checkpoint, followed by frames relative to the one
+/// before.
+#[derive(Default)]
+pub struct History {
+    segments: Vec<Segment>,
+    encoder: Encoder,
+}
+
+struct Segment {
+    start: u32,
+    frames: Vec<Frame>,
+}
+
+impl History {
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.encoder.reset();
+    }
+
+    /// The last tick recorded.
+    pub fn end(&self) -> Option<u32> {
+        let segment = self.segments.last()?;
+        Some(segment.start + segment.frames.len() as u32 - 1)
+    }
+
+    /// Records the snapshot for the tick after the last one recorded. Other
+    /// ticks are ignored.
+    pub fn record(&mut self, tick: u32, snapshot: &Snapshot) {
+        if self.end().is_some_and(|end| tick != end + 1) {
+            return;
+        }
+        match self.segments.last_mut() {
+            Some(segment) if segment.frames.len() < CHECKPOINT_INTERVAL as usize => {
+                segment.frames.push(self.encoder.encode(snapshot.clone()));
+            }
+            _ => {
+                self.encoder.reset();
+                self.segments.push(Segment {
+                    start: tick,
+                    frames: vec![self.encoder.encode(snapshot.clone())],
+                });
+            }
+        }
+    }
+
+    /// Forgets the ticks after the given one.
+    pub fn truncate(&mut self, tick: u32) {
+        if !self.end().is_some_and(|end| end > tick) {
+            return;
+        }
+        self.segments.retain(|segment| segment.start <= tick);
+        if let Some(segment) = self.segments.last_mut() {
+            segment.frames.truncate((tick - segment.start + 1) as usize);
+        }
+        // The encoder has moved past the last frame kept, so make the next
+        // frame a full one.
+        self.encoder.reset();
+    }
+
+    pub fn get(&self, tick: u32) -> Option<Snapshot> {
+        if tick > self.end()? {
+            return None;
+        }
+        let i = self
+            .segments
+            .partition_point(|segment| segment.start <= tick)
+            .checked_sub(1)?;
+        let segment = &self.segments[i];
+        let mut decoder = Decoder::default();
+        let mut snapshot = None;
+        for frame in segment.frames[..=(tick - segment.start) as usize].iter() {
+            snapshot = decoder.decode(frame.clone());
+        }
+        snapshot
+    }
+}
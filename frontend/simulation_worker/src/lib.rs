@@ -1,46 +1,156 @@
+mod history;
+
+use history::History;
+use oort_simulator::pilot::PilotInput;
+use oort_simulator::scenario::custom::{self, ScenarioDescription};
 use oort_simulator::scenario::{Status, MAX_TICKS};
 use oort_simulator::simulation::Code;
 use oort_simulator::simulation::Simulation;
 use oort_simulator::snapshot::Snapshot;
+use oort_simulator::snapshot_encoding::{Decoder, Encoder, Frame};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
 use yew_agent::{HandlerId, Private, WorkerLink};
 
+/// Maximum number of ticks re-simulated per snapshot request while catching
+/// up after an edit in the past.
+const CATCH_UP_TICKS: u32 = 100;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     StartScenario {
         scenario_name: String,
+        /// The description of a custom scenario, which needs to be
+        /// registered in the worker before it can be loaded.
+        custom_scenario: Option<ScenarioDescription>,
         seed: u32,
         codes: Vec<Code>,
         nonce: u32,
+        /// Whether to keep snapshots for seeking backwards.
+        record_history: bool,
     },
     Snapshot {
         ticks: u32,
         nonce: u32,
     },
+    /// Moves to the given tick, backwards or forwards. Subsequent snapshots
+    /// continue from there.
+    Seek {
+        tick: u32,
+        nonce: u32,
+    },
+    /// Replaces a team's code at the current tick. The response is a Seek
+    /// response with the state after the reload.
+    ReloadCode {
+        team: i32,
+        code: Code,
+        nonce: u32,
+    },
+    /// Takes manual control of a ship at the current tick, or returns it to
+    /// its AI if `input` is None. The response is a Seek response, so that
+    /// snapshots computed before the input are discarded.
+    SetPilotInput {
+        ship: u64,
+        input: Option<PilotInput>,
+        nonce: u32,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
     Snapshot { snapshot: Snapshot },
+    Seek { snapshot: Snapshot },
+}
+
+/// A `Response` as sent by the worker. Snapshots are delta encoded against
+/// the previous one sent, so bridge callbacks should be wrapped with
+/// `decode_responses`.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum EncodedResponse {
+    Snapshot(Frame),
+    Seek(Frame),
+}
+
+/// Wraps a callback to decode the responses from a worker. Each bridge needs
+/// its own, since frames are relative to the ones before.
+pub fn decode_responses(callback: impl Fn(Response) + 'static) -> Rc<dyn Fn(EncodedResponse)> {
+    let decoder = RefCell::new(Decoder::default());
+    Rc::new(move |response| {
+        let response = match response {
+            EncodedResponse::Snapshot(frame) => decoder
+                .borrow_mut()
+                .decode(frame)
+                .map(|snapshot| Response::Snapshot { snapshot }),
+            EncodedResponse::Seek(frame) => decoder
+                .borrow_mut()
+                .decode(frame)
+                .map(|snapshot| Response::Seek { snapshot }),
+        };
+        if let Some(response) = response {
+            callback(response);
+        }
+    })
+}
+
+struct StartParams {
+    scenario_name: String,
+    seed: u32,
+    codes: Vec<Code>,
+    /// Changes made while running, by the tick they were made at, for
+    /// re-simulating.
+    edits: Vec<(u32, Edit)>,
+}
+
+enum Edit {
+    ReloadCode {
+        team: i32,
+        code: Code,
+    },
+    SetPilotInput {
+        ship: u64,
+        input: Option<PilotInput>,
+    },
+}
+
+impl Edit {
+    fn apply(&self, sim: &mut Simulation) {
+        match self {
+            Edit::ReloadCode { team, code } => sim.reload_code(*team, code),
+            Edit::SetPilotInput { ship, input } => sim.set_pilot_input((*ship).into(), *input),
+        }
+    }
 }
 
 pub struct SimAgent {
     link: WorkerLink<Self>,
     sim: Option<Box<Simulation>>,
     errored: bool,
+    start_params: Option<StartParams>,
+    record_history: bool,
+    history: History,
+    /// The tick being replayed from history, or None if we're at the
+    /// simulation's current tick.
+    cursor: Option<u32>,
+    encoder: Encoder,
 }
 
 impl yew_agent::Worker for SimAgent {
     type Reach = Private<Self>;
     type Message = ();
     type Input = Request;
-    type Output = Response;
+    type Output = EncodedResponse;
 
     fn create(link: WorkerLink<Self>) -> Self {
         Self {
             link,
             sim: None,
             errored: false,
+            start_params: None,
+            record_history: false,
+            history: History::default(),
+            cursor: None,
+            encoder: Encoder::default(),
         }
     }
 
@@ -50,27 +160,80 @@ impl yew_agent::Worker for SimAgent {
         match request {
             Request::StartScenario {
                 scenario_name,
+                custom_scenario,
                 seed,
                 codes,
                 nonce,
+                record_history,
             } => {
-                self.sim = Some(Simulation::new(&scenario_name, seed, &codes));
-                let snapshot = self.sim().snapshot(nonce);
-                self.errored = !snapshot.errors.is_empty();
-                self.link.respond(who, Response::Snapshot { snapshot });
+                if let Some(description) = custom_scenario {
+                    if let Err(e) = custom::register(description) {
+                        log::error!("Invalid custom scenario: {}", e);
+                    }
+                }
+                self.record_history = record_history;
+                self.start_params = Some(StartParams {
+                    scenario_name,
+                    seed,
+                    codes,
+                    edits: vec![],
+                });
+                self.restart();
+                self.history.clear();
+                self.cursor = None;
+                self.encoder.reset();
+                let snapshot = self.take_snapshot(nonce);
+                self.respond(who, Response::Snapshot { snapshot });
             }
-            Request::Snapshot { ticks, nonce } => {
+            Request::Snapshot { mut ticks, nonce } => {
+                self.catch_up(nonce);
+                if let Some(cursor) = self.cursor {
+                    let tick = cursor + ticks;
+                    let end = self.history.end().unwrap_or(0);
+                    if tick <= end || self.catching_up() {
+                        // Wait at the end of the history until the
+                        // simulation has caught up.
+                        let tick = tick.min(end);
+                        if let Some(snapshot) = self.history.get(tick) {
+                            self.cursor = Some(tick);
+                            self.respond(who, Response::Snapshot { snapshot });
+                            return;
+                        }
+                    }
+                    // Continue simulating from the end of the history.
+                    self.cursor = None;
+                    ticks = tick.saturating_sub(self.sim().tick());
+                }
                 if self.errored {
                     return;
                 }
+                let mut snapshot = None;
                 for _ in 0..ticks {
                     if self.sim().status() == Status::Running && self.sim().tick() < MAX_TICKS {
-                        self.sim().step();
+                        snapshot = self.step(nonce);
                     }
                 }
-                let snapshot = self.sim().snapshot(nonce);
-                self.errored = !snapshot.errors.is_empty();
-                self.link.respond(who, Response::Snapshot { snapshot });
+                let snapshot = snapshot.unwrap_or_else(|| self.take_snapshot(nonce));
+                self.respond(who, Response::Snapshot { snapshot });
+            }
+            Request::Seek { tick, nonce } => {
+                let tick = tick.min(MAX_TICKS);
+                if let Some(snapshot) = self.history.get(tick) {
+                    self.cursor = Some(tick);
+                    self.respond(who, Response::Seek { snapshot });
+                    return;
+                }
+                self.seek(tick, nonce);
+                let snapshot = self.take_snapshot(nonce);
+                self.respond(who, Response::Seek { snapshot });
+            }
+            Request::ReloadCode { team, code, nonce } => {
+                let snapshot = self.edit(Edit::ReloadCode { team, code }, nonce);
+                self.respond(who, Response::Seek { snapshot });
+            }
+            Request::SetPilotInput { ship, input, nonce } => {
+                let snapshot = self.edit(Edit::SetPilotInput { ship, input }, nonce);
+                self.respond(who, Response::Seek { snapshot });
             }
         };
     }
@@ -84,4 +247,111 @@ impl SimAgent {
     fn sim(&mut self) -> &mut Simulation {
         self.sim.as_mut().unwrap()
     }
+
+    fn respond(&mut self, who: HandlerId, response: Response) {
+        let response = match response {
+            Response::Snapshot { snapshot } => {
+                EncodedResponse::Snapshot(self.encoder.encode(snapshot))
+            }
+            Response::Seek { snapshot } => EncodedResponse::Seek(self.encoder.encode(snapshot)),
+        };
+        self.link.respond(who, response);
+    }
+
+    fn restart(&mut self) {
+        let params = self.start_params.as_ref().unwrap();
+        self.sim = Some(Simulation::new(
+            &params.scenario_name,
+            params.seed,
+            &params.codes,
+        ));
+        self.errored = false;
+        self.apply_edits();
+    }
+
+    /// Steps the simulation, returning a snapshot if one was recorded.
+    fn step(&mut self, nonce: u32) -> Option<Snapshot> {
+        self.sim().step();
+        self.apply_edits();
+        // Ticks already in the history are being re-simulated.
+        let recorded = self
+            .history
+            .end()
+            .is_some_and(|end| self.sim().tick() <= end);
+        if self.record_history && !recorded {
+            Some(self.take_snapshot(nonce))
+        } else {
+            None
+        }
+    }
+
+    /// Re-applies the edits made at the current tick.
+    fn apply_edits(&mut self) {
+        let sim = self.sim.as_mut().unwrap();
+        for (tick, edit) in self.start_params.as_ref().unwrap().edits.iter() {
+            if *tick == sim.tick() {
+                edit.apply(sim);
+            }
+        }
+    }
+
+    /// Applies an edit at the tick being shown and returns the snapshot to
+    /// show. Editing while rewound replaces the rest of the run.
+    fn edit(&mut self, edit: Edit, nonce: u32) -> Snapshot {
+        let tick = self.cursor.unwrap_or(self.sim().tick());
+        if tick == self.sim().tick() {
+            self.cursor = None;
+            edit.apply(self.sim());
+            self.start_params.as_mut().unwrap().edits.push((tick, edit));
+            return self.take_snapshot(nonce);
+        }
+
+        // The simulation is restarted and catches up to the edit over the
+        // following snapshot requests, applying it on the way.
+        let params = self.start_params.as_mut().unwrap();
+        params.edits.retain(|(t, _)| *t <= tick);
+        params.edits.push((tick, edit));
+        self.history.truncate(tick);
+        if tick < self.sim().tick() {
+            self.restart();
+        }
+        self.cursor = Some(tick);
+        self.history.get(tick).unwrap()
+    }
+
+    /// Whether the simulation is behind the end of the history after an
+    /// edit in the past.
+    fn catching_up(&mut self) -> bool {
+        let end = self.history.end().unwrap_or(0);
+        !self.errored && self.sim().status() == Status::Running && self.sim().tick() < end
+    }
+
+    fn catch_up(&mut self, nonce: u32) {
+        for _ in 0..CATCH_UP_TICKS {
+            if !self.catching_up() {
+                break;
+            }
+            self.step(nonce);
+        }
+    }
+
+    /// Simulates forward to the given tick. Ticks before the simulation's
+    /// current one are only available from the history.
+    fn seek(&mut self, tick: u32, nonce: u32) {
+        self.cursor = None;
+        while !self.errored && self.sim().status() == Status::Running && self.sim().tick() < tick {
+            self.step(nonce);
+        }
+    }
+
+    /// Takes a snapshot of the current tick and records it in the history.
+    fn take_snapshot(&mut self, nonce: u32) -> Snapshot {
+        let snapshot = self.sim().snapshot(nonce);
+        self.errored = !snapshot.errors.is_empty();
+        if self.record_history {
+            let tick = self.sim().tick();
+            self.history.record(tick, &snapshot);
+        }
+        snapshot
+    }
 }
@@ -0,0 +1,213 @@
+// Line-level diffs between versions, using Myers' algorithm.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub change: Change,
+    pub text: String,
+    /// 1-based line number in the old text, unless inserted.
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new text, unless deleted.
+    pub new_line: Option<usize>,
+}
+
+/// Returns a line-level diff transforming `old` into `new`.
+pub fn diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // The common prefix and suffix don't need to go through the search.
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let mut changes = vec![Change::Equal; prefix];
+    changes.extend(myers(
+        &a[prefix..a.len() - suffix],
+        &b[prefix..b.len() - suffix],
+    ));
+    changes.resize(changes.len() + suffix, Change::Equal);
+
+    let mut result = Vec::with_capacity(changes.len());
+    let (mut i, mut j) = (0, 0);
+    for change in changes {
+        let (text, old_line, new_line) = match change {
+            Change::Equal => {
+                i += 1;
+                j += 1;
+                (a[i - 1], Some(i), Some(j))
+            }
+            Change::Delete => {
+                i += 1;
+                (a[i - 1], Some(i), None)
+            }
+            Change::Insert => {
+                j += 1;
+                (b[j - 1], None, Some(j))
+            }
+        };
+        result.push(DiffLine {
+            change,
+            text: text.to_string(),
+            old_line,
+            new_line,
+        });
+    }
+    result
+}
+
+/// Groups changed lines with up to `context` unchanged lines around them.
+pub fn hunks(lines: &[DiffLine], context: usize) -> Vec<&[DiffLine]> {
+    let mut result: Vec<(usize, usize)> = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if line.change == Change::Equal {
+            continue;
+        }
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(lines.len());
+        match result.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => result.push((start, end)),
+        }
+    }
+    result
+        .into_iter()
+        .map(|(start, end)| &lines[start..end])
+        .collect()
+}
+
+/// Returns the edit script for the shortest path from `a` to `b`.
+fn myers(a: &[&str], b: &[&str]) -> Vec<Change> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // The diagonals of `v` that backtracking over step `d` reads, `-d - 1` to
+    // `d + 1`, before each step. Storing only these keeps memory to O(D²).
+    let mut trace = vec![];
+
+    'search: for d in 0..=max as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            changes.push(Change::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            changes.push(if x == prev_x {
+                Change::Insert
+            } else {
+                Change::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    changes.reverse();
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(old: &str, new: &str) -> String {
+        diff(old, new)
+            .iter()
+            .map(|line| {
+                let prefix = match line.change {
+                    Change::Equal => ' ',
+                    Change::Insert => '+',
+                    Change::Delete => '-',
+                };
+                format!("{}{}\n", prefix, line.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(render("a\nb\nc", "a\nb\nc"), " a\n b\n c\n");
+        assert_eq!(render("a\nb\nc", "a\nc"), " a\n-b\n c\n");
+        assert_eq!(render("a\nc", "a\nb\nc"), " a\n+b\n c\n");
+        assert_eq!(render("", "a"), "+a\n");
+        assert_eq!(render("a", ""), "-a\n");
+        assert_eq!(
+            render("a\nb\nc\na\nb\nb\na", "c\nb\na\nb\na\nc"),
+            "-a\n-b\n c\n+b\n a\n b\n-b\n a\n+c\n"
+        );
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let lines = diff("a\nb\nc", "a\nx\nc");
+        let numbers: Vec<_> = lines.iter().map(|l| (l.old_line, l.new_line)).collect();
+        assert_eq!(
+            numbers,
+            vec![
+                (Some(1), Some(1)),
+                (Some(2), None),
+                (None, Some(2)),
+                (Some(3), Some(3))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hunks() {
+        let old = (0..20)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let new = old.replace("\n5\n", "\nfive\n").replace("\n15\n", "\n");
+        let lines = diff(&old, &new);
+        let hunks = hunks(&lines, 2);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].first().unwrap().text, "3");
+        assert_eq!(hunks[0].last().unwrap().text, "7");
+        assert_eq!(hunks[1].len(), 5);
+        assert!(super::hunks(&diff("a", "a"), 3).is_empty());
+    }
+}
@@ -7,9 +7,16 @@ use sha2::{Digest, Sha256};
 use std::io::{Read, Write};
 use wasm_bindgen::prelude::*;
 
-const SCHEMA_VERSION: u32 = 4;
+pub mod diff;
+
+const SCHEMA_VERSION: u32 = 5;
 const VERSIONS: &str = "versions";
 const CODE: &str = "code";
+const TAGS: &str = "tags";
+const BRANCHES: &str = "branches";
+
+pub const DEFAULT_BRANCH: &str = "main";
+const ARCHIVE_FORMAT: u32 = 1;
 
 pub struct VersionControl {
     pub database: Database,
@@ -52,10 +59,26 @@ impl VersionControl {
             database.create_object_store(CODE, store_params)?;
         }
 
+        if !database.store_names().contains(&TAGS.to_string()) {
+            let mut store_params = ObjectStoreParams::new();
+            store_params.key_path(Some(KeyPath::new_single("id")));
+            let store = database.create_object_store(TAGS, store_params)?;
+            store.create_index("scenario_name", KeyPath::new_single("scenario_name"), None)?;
+        }
+
+        // Records the current branch for each scenario.
+        if !database.store_names().contains(&BRANCHES.to_string()) {
+            let mut store_params = ObjectStoreParams::new();
+            store_params.key_path(Some(KeyPath::new_single("scenario_name")));
+            database.create_object_store(BRANCHES, store_params)?;
+        }
+
         Ok(())
     }
 
+    /// Saves a version on the scenario's current branch.
     pub async fn create_version(&self, params: &CreateVersionParams) -> Result<(), Error> {
+        let branch = self.get_current_branch(&params.scenario_name).await?;
         let timestamp = chrono::Utc::now();
         let timestamp_string = timestamp.format("%Y%m%d-%H%M%S");
         let digest = digest(&params.code);
@@ -66,6 +89,7 @@ impl VersionControl {
             timestamp,
             digest: digest.clone(),
             label: params.label.clone(),
+            branch,
         };
         let transaction = self
             .database
@@ -74,25 +98,30 @@ impl VersionControl {
         versions_store
             .add(&serde_wasm_bindgen::to_value(&version)?, None)
             .await?;
+        Self::put_code(&transaction, &digest, &params.code).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
 
+    /// Stores code under its digest, unless it's already present.
+    async fn put_code(transaction: &Transaction, digest: &str, code: &str) -> Result<(), Error> {
         let code_store = transaction.object_store(CODE)?;
         let has_digest = code_store
-            .count(Some(Query::Key(JsValue::from_str(&digest))))
+            .count(Some(Query::Key(JsValue::from_str(digest))))
             .await?
             > 0;
 
         if !has_digest {
             let compressed = {
                 let mut e = DeflateEncoder::new(Vec::new(), Compression::default());
-                e.write_all(params.code.as_bytes())?;
+                e.write_all(code.as_bytes())?;
                 e.finish()?
             };
             let value: js_sys::Uint8Array = compressed[..].into();
             code_store
-                .add(&value, Some(&JsValue::from_str(&digest)))
+                .add(&value, Some(&JsValue::from_str(digest)))
                 .await?;
         }
-        transaction.commit().await?;
         Ok(())
     }
 
@@ -118,8 +147,12 @@ impl VersionControl {
             .transaction(&[CODE], TransactionMode::ReadOnly)?;
         let store = transaction.object_store(CODE)?;
         let key = JsValue::from_str(digest);
-        let Some(value) = store.get(key).await? else { return Err(Error::NotFound) };
-        let Ok(array) = value.dyn_into::<js_sys::Uint8Array>() else { return Err(Error::BadData) };
+        let Some(value) = store.get(key).await? else {
+            return Err(Error::NotFound);
+        };
+        let Ok(array) = value.dyn_into::<js_sys::Uint8Array>() else {
+            return Err(Error::BadData);
+        };
         let vec = array.to_vec();
         let mut deflater = DeflateDecoder::new(vec.as_slice());
         let mut decompressed = String::new();
@@ -169,6 +202,181 @@ impl VersionControl {
         let digest = digest(code);
         self.check_digest_exists(&digest).await
     }
+
+    /// Returns a line-level diff between the code of two versions.
+    pub async fn diff_versions(
+        &self,
+        old_id: &str,
+        new_id: &str,
+    ) -> Result<Vec<diff::DiffLine>, Error> {
+        let old = self.get_version(old_id).await?;
+        let new = self.get_version(new_id).await?;
+        let old_code = self.get_code(&old.digest).await?;
+        let new_code = self.get_code(&new.digest).await?;
+        Ok(diff::diff(&old_code, &new_code))
+    }
+
+    /// Creates a tag pointing at a version, or moves an existing tag.
+    pub async fn create_tag(
+        &self,
+        scenario_name: &str,
+        name: &str,
+        version_id: &str,
+    ) -> Result<(), Error> {
+        let tag = Tag {
+            id: tag_id(scenario_name, name),
+            scenario_name: scenario_name.to_string(),
+            name: name.to_string(),
+            version_id: version_id.to_string(),
+        };
+        let transaction = self
+            .database
+            .transaction(&[TAGS], TransactionMode::ReadWrite)?;
+        let store = transaction.object_store(TAGS)?;
+        store
+            .put(&serde_wasm_bindgen::to_value(&tag)?, None)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn delete_tag(&self, scenario_name: &str, name: &str) -> Result<(), Error> {
+        let transaction = self
+            .database
+            .transaction(&[TAGS], TransactionMode::ReadWrite)?;
+        let store = transaction.object_store(TAGS)?;
+        store
+            .delete(Query::Key(JsValue::from_str(&tag_id(scenario_name, name))))
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn list_tags(&self, scenario_name: &str) -> Result<Vec<Tag>, Error> {
+        let transaction = self
+            .database
+            .transaction(&[TAGS], TransactionMode::ReadOnly)?;
+        let store = transaction.object_store(TAGS)?;
+        let index = store.index("scenario_name")?;
+        let query = Query::Key(JsValue::from_str(scenario_name));
+        let records = index.get_all(Some(query), None).await?;
+        let result = records
+            .into_iter()
+            .filter_map(|r| match serde_wasm_bindgen::from_value(r) {
+                Ok(tag) => Some(tag),
+                Err(e) => {
+                    log::error!("Error deserializing tag: {:?}", e);
+                    None
+                }
+            })
+            .collect();
+        transaction.done().await?;
+        Ok(result)
+    }
+
+    /// Returns the branch that new versions of the scenario are saved on.
+    pub async fn get_current_branch(&self, scenario_name: &str) -> Result<String, Error> {
+        let transaction = self
+            .database
+            .transaction(&[BRANCHES], TransactionMode::ReadOnly)?;
+        let store = transaction.object_store(BRANCHES)?;
+        let stored = store.get(JsValue::from_str(scenario_name)).await?;
+        let result = match stored {
+            Some(stored) => serde_wasm_bindgen::from_value::<CurrentBranch>(stored)?.branch,
+            None => DEFAULT_BRANCH.to_string(),
+        };
+        transaction.done().await?;
+        Ok(result)
+    }
+
+    pub async fn set_current_branch(&self, scenario_name: &str, branch: &str) -> Result<(), Error> {
+        let current = CurrentBranch {
+            scenario_name: scenario_name.to_string(),
+            branch: branch.to_string(),
+        };
+        let transaction = self
+            .database
+            .transaction(&[BRANCHES], TransactionMode::ReadWrite)?;
+        let store = transaction.object_store(BRANCHES)?;
+        store
+            .put(&serde_wasm_bindgen::to_value(&current)?, None)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Returns the names of the scenario's branches, including the current
+    /// branch even if it has no versions yet.
+    pub async fn list_branches(&self, scenario_name: &str) -> Result<Vec<String>, Error> {
+        let mut branches: Vec<String> = self
+            .list_versions(scenario_name)
+            .await?
+            .into_iter()
+            .map(|version| version.branch)
+            .collect();
+        branches.push(DEFAULT_BRANCH.to_string());
+        branches.push(self.get_current_branch(scenario_name).await?);
+        branches.sort();
+        branches.dedup();
+        Ok(branches)
+    }
+
+    /// Serializes all versions and tags of a scenario into a single file.
+    pub async fn export_scenario(&self, scenario_name: &str) -> Result<String, Error> {
+        let mut versions = vec![];
+        for version in self.list_versions(scenario_name).await? {
+            let code = self.get_code(&version.digest).await?;
+            versions.push(ArchivedVersion { version, code });
+        }
+        let archive = Archive {
+            format: ARCHIVE_FORMAT,
+            scenario_name: scenario_name.to_string(),
+            versions,
+            tags: self.list_tags(scenario_name).await?,
+        };
+        Ok(serde_json::to_string_pretty(&archive)?)
+    }
+
+    /// Adds the versions and tags from an exported archive, returning the
+    /// scenario name. Versions that already exist are left unchanged.
+    pub async fn import_archive(&self, data: &str) -> Result<String, Error> {
+        let archive: Archive = serde_json::from_str(data)?;
+        if archive.format != ARCHIVE_FORMAT {
+            return Err(Error::BadData);
+        }
+        let transaction = self
+            .database
+            .transaction(&[VERSIONS, CODE, TAGS], TransactionMode::ReadWrite)?;
+        let versions_store = transaction.object_store(VERSIONS)?;
+        let tags_store = transaction.object_store(TAGS)?;
+        for ArchivedVersion { version, code } in archive.versions.iter() {
+            if version.scenario_name != archive.scenario_name || digest(code) != version.digest {
+                return Err(Error::BadData);
+            }
+            let exists = versions_store
+                .count(Some(Query::Key(JsValue::from_str(&version.id))))
+                .await?
+                > 0;
+            if !exists {
+                versions_store
+                    .add(&serde_wasm_bindgen::to_value(version)?, None)
+                    .await?;
+            }
+            Self::put_code(&transaction, &version.digest, code).await?;
+        }
+        for tag in archive.tags.iter() {
+            if tag.scenario_name != archive.scenario_name
+                || tag.id != tag_id(&tag.scenario_name, &tag.name)
+            {
+                return Err(Error::BadData);
+            }
+            tags_store
+                .put(&serde_wasm_bindgen::to_value(tag)?, None)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(archive.scenario_name)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -178,6 +386,38 @@ pub struct Version {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub digest: String,
     pub label: Option<String>,
+    #[serde(default = "default_branch")]
+    pub branch: String,
+}
+
+/// A name for a version, such as "passing".
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub scenario_name: String,
+    pub name: String,
+    pub version_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CurrentBranch {
+    scenario_name: String,
+    branch: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Archive {
+    format: u32,
+    scenario_name: String,
+    versions: Vec<ArchivedVersion>,
+    tags: Vec<Tag>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedVersion {
+    #[serde(flatten)]
+    version: Version,
+    code: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -187,6 +427,14 @@ pub struct CreateVersionParams {
     pub label: Option<String>,
 }
 
+fn default_branch() -> String {
+    DEFAULT_BRANCH.to_string()
+}
+
+fn tag_id(scenario_name: &str, name: &str) -> String {
+    format!("{scenario_name}/{name}")
+}
+
 fn digest(s: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(s.as_bytes());
@@ -204,6 +452,9 @@ pub enum Error {
     #[error("IO error")]
     IOError(#[from] std::io::Error),
 
+    #[error("JSON error")]
+    JsonError(#[from] serde_json::Error),
+
     #[error("Not found")]
     NotFound,
 
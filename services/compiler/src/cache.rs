@@ -0,0 +1,88 @@
+use oort_compiler::Language;
+use oort_proto::CompilerDiagnostic;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+
+pub type Key = [u8; 32];
+
+#[derive(Clone)]
+pub struct Entry {
+    pub wasm: Vec<u8>,
+    pub diagnostics: Vec<CompilerDiagnostic>,
+}
+
+/// Successful compile results keyed by a hash of the source code and options.
+///
+/// The oldest entry is evicted once `capacity` is reached.
+pub struct Cache {
+    capacity: usize,
+    entries: HashMap<Key, Entry>,
+    order: VecDeque<Key>,
+}
+
+impl Cache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn key(code: &str, language: Option<Language>, debug_info: bool) -> Key {
+        let mut hasher = Sha256::new();
+        hasher.update(language.map(|x| x.name()).unwrap_or_default());
+        hasher.update([0, debug_info as u8]);
+        hasher.update(code.as_bytes());
+        hasher.finalize().into()
+    }
+
+    pub fn get(&self, key: &Key) -> Option<Entry> {
+        self.entries.get(key).cloned()
+    }
+
+    pub fn insert(&mut self, key: Key, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key, entry).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(wasm: &[u8]) -> Entry {
+        Entry {
+            wasm: wasm.to_vec(),
+            diagnostics: vec![],
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = Cache::new(2);
+        let a = Cache::key("a", None, false);
+        let b = Cache::key("b", None, false);
+        let c = Cache::key("c", None, false);
+        assert_ne!(a, Cache::key("a", None, true));
+        assert_ne!(a, Cache::key("a", Some(Language::Rust), false));
+
+        cache.insert(a, entry(b"a"));
+        cache.insert(b, entry(b"b"));
+        assert_eq!(cache.get(&a).unwrap().wasm, b"a");
+
+        cache.insert(c, entry(b"c"));
+        assert!(cache.get(&a).is_none());
+        assert_eq!(cache.get(&b).unwrap().wasm, b"b");
+        assert_eq!(cache.get(&c).unwrap().wasm, b"c");
+    }
+}
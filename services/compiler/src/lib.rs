@@ -1,6 +1,9 @@
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
+pub mod cache;
+pub mod pool;
+
 pub fn error(status_code: StatusCode, msg: String) -> Error {
     Error {
         status_code,
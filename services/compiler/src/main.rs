@@ -1,77 +1,129 @@
-use axum::extract::State;
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
 use axum::Router;
 use bytes::Bytes;
 use clap::Parser as _;
 use http::{Method, StatusCode};
-use once_cell::sync::Lazy;
-use oort_compiler::Compiler;
+use oort_compiler::{Compiler, Language, Limits};
+use oort_compiler_service::cache::{self, Cache};
+use oort_compiler_service::pool::Pool;
 use oort_compiler_service::{error, Error};
-use std::io::Write;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use tempfile::NamedTempFile;
-use tokio::process::Command;
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 
-const MAX_CONCURRENCY: usize = 3;
-static FORMAT_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
-static SEMAPHORE: Lazy<tokio::sync::Semaphore> =
-    Lazy::new(|| tokio::sync::Semaphore::new(MAX_CONCURRENCY));
+const PREPARED_DIR: &str = "/tmp/oort-ai";
+
+struct AppState {
+    pool: Pool,
+    cache: Mutex<Cache>,
+}
 
 async fn post_compile(
-    State(compiler): State<Arc<Mutex<Compiler>>>,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
     mut code: String,
-) -> Result<Bytes, Error> {
-    let permit = SEMAPHORE.try_acquire();
-    if permit.is_err() {
-        return Err(error(
-            StatusCode::SERVICE_UNAVAILABLE,
-            "Service overloaded".to_string(),
-        ));
-    }
-
+) -> Result<Response, Error> {
+    // Debug info would leak symbol names from encrypted code.
+    let mut debug_info = params.get("debug").map(|x| x == "true").unwrap_or(false);
+    let mut want_diagnostics = params
+        .get("diagnostics")
+        .map(|x| x == "true")
+        .unwrap_or(false);
     if oort_code_encryption::is_encrypted(&code) {
         log::debug!("Encrypted code: {}", code);
         code = oort_code_encryption::decrypt(&code)?;
+        debug_info = false;
+        want_diagnostics = false;
     }
+    // Older clients don't specify the language.
+    let language: Option<Language> = match params.get("language") {
+        Some(x) => Some(
+            x.parse()
+                .map_err(|e: anyhow::Error| error(StatusCode::BAD_REQUEST, e.to_string()))?,
+        ),
+        None => None,
+    };
     log::debug!("Code: {}", code);
     let start_time = std::time::Instant::now();
-    let result = tokio::runtime::Handle::current()
-        .spawn_blocking(move || compiler.lock().unwrap().compile(&code))
-        .await?;
+    let key = Cache::key(&code, language, debug_info);
+    let cached = state.cache.lock().unwrap().get(&key);
+    let result = match cached {
+        Some(entry) => {
+            log::info!("Compile cache hit");
+            (Ok(entry.wasm), entry.diagnostics)
+        }
+        None => {
+            let result = state
+                .pool
+                .run(move |compiler| {
+                    compiler.set_debug_info(debug_info);
+                    let result = match language {
+                        Some(language) => compiler.compile_language(&code, language),
+                        None => compiler.compile(&code),
+                    };
+                    (result, compiler.diagnostics().to_vec())
+                })
+                .await?;
+            if let (Ok(wasm), diagnostics) = &result {
+                state.cache.lock().unwrap().insert(
+                    key,
+                    cache::Entry {
+                        wasm: wasm.clone(),
+                        diagnostics: diagnostics.clone(),
+                    },
+                );
+            }
+            result
+        }
+    };
     let elapsed = std::time::Instant::now() - start_time;
     match result {
-        Ok(wasm) => {
+        (Ok(wasm), diagnostics) => {
             log::info!("Compile succeeded in {:?}", elapsed);
-            Ok(Bytes::copy_from_slice(&wasm))
+            if want_diagnostics {
+                let success = oort_proto::CompileSuccess { wasm, diagnostics };
+                Ok(axum::Json(success).into_response())
+            } else {
+                Ok(Bytes::copy_from_slice(&wasm).into_response())
+            }
         }
-        Err(e) => {
+        (Err(e), diagnostics) => {
             log::info!("Compile failed in {:?}", elapsed);
             log::debug!("Compile failed: {}", e);
-            Err(error(StatusCode::BAD_REQUEST, e.to_string()))
+            if want_diagnostics {
+                let failure = oort_proto::CompileFailure {
+                    message: e.to_string(),
+                    diagnostics,
+                };
+                Ok((StatusCode::BAD_REQUEST, axum::Json(failure)).into_response())
+            } else {
+                Err(error(StatusCode::BAD_REQUEST, e.to_string()))
+            }
         }
     }
 }
 
-async fn post_format(code: String) -> Result<String, Error> {
-    let _guard = FORMAT_LOCK.lock().await;
-    let mut tmpfile = NamedTempFile::new()?;
-    tmpfile.write_all(code.as_bytes())?;
+async fn post_format(State(state): State<Arc<AppState>>, code: String) -> Result<String, Error> {
     let start_time = std::time::Instant::now();
-    let output = Command::new("rustfmt")
-        .args([tmpfile.path()])
-        .output()
+    let result = state
+        .pool
+        .run(move |compiler| compiler.format(&code))
         .await?;
     let elapsed = std::time::Instant::now() - start_time;
-    if !output.status.success() {
-        log::info!("Format failed in {:?}", elapsed);
-        let stdout = std::str::from_utf8(&output.stdout)?;
-        let stderr = std::str::from_utf8(&output.stderr)?;
-        log::debug!("Format failed: stderr={}\nstdout={}", stderr, stdout);
-        return Err(error(StatusCode::BAD_REQUEST, stderr.to_string()));
+    match result {
+        Ok(formatted) => {
+            log::info!("Format succeeded in {:?}", elapsed);
+            Ok(formatted)
+        }
+        Err(e) => {
+            log::info!("Format failed in {:?}", elapsed);
+            log::debug!("Format failed: {}", e);
+            Err(error(StatusCode::BAD_REQUEST, e.to_string()))
+        }
     }
-    log::info!("Format succeeded in {:?}", elapsed);
-    let formatted = std::fs::read_to_string(tmpfile.path())?;
-    Ok(formatted)
 }
 
 #[tokio::main]
@@ -82,6 +134,30 @@ async fn main() {
     struct Arguments {
         #[clap(short, long)]
         prepare: bool,
+
+        /// Number of compiles to run concurrently, each in its own directory.
+        #[clap(long, default_value_t = 3)]
+        workers: usize,
+
+        /// Number of compiles allowed to wait for a worker before rejecting requests.
+        #[clap(long, default_value_t = 16)]
+        max_queue: usize,
+
+        /// Wall-clock limit for each compile.
+        #[clap(long, default_value_t = 30)]
+        timeout_secs: u64,
+
+        /// Memory limit for each compiler process.
+        #[clap(long, default_value_t = 2048)]
+        memory_mb: u64,
+
+        /// CPU time limit for each compiler process.
+        #[clap(long, default_value_t = 60)]
+        cpu_secs: u64,
+
+        /// Number of successful compiles to cache.
+        #[clap(long, default_value_t = 256)]
+        cache_size: usize,
     }
     let args = Arguments::parse();
 
@@ -98,11 +174,10 @@ async fn main() {
         Err(_e) => {}
     };
 
-    let dir = "/tmp/oort-ai";
-    std::fs::create_dir_all(dir).unwrap();
-    let mut compiler = Compiler::new_with_dir(std::path::Path::new(dir));
+    std::fs::create_dir_all(PREPARED_DIR).unwrap();
 
     if args.prepare {
+        let mut compiler = Compiler::new_with_dir(Path::new(PREPARED_DIR));
         compiler.enable_online();
         compiler
             .compile(include_str!("../../../shared/builtin_ai/src/empty.rs"))
@@ -110,6 +185,30 @@ async fn main() {
         return;
     }
 
+    let limits = Limits {
+        timeout: Some(Duration::from_secs(args.timeout_secs)),
+        memory: Some(args.memory_mb << 20),
+        cpu_seconds: Some(args.cpu_secs),
+    };
+    let compilers = (0..args.workers.max(1))
+        .map(|i| {
+            let dir = if i == 0 {
+                PREPARED_DIR.to_string()
+            } else {
+                let dir = format!("{PREPARED_DIR}-{i}");
+                copy_dir(PREPARED_DIR, &dir);
+                dir
+            };
+            let mut compiler = Compiler::new_with_dir(Path::new(&dir));
+            compiler.set_limits(limits.clone());
+            compiler
+        })
+        .collect();
+    let state = AppState {
+        pool: Pool::new(compilers, args.max_queue),
+        cache: Mutex::new(Cache::new(args.cache_size)),
+    };
+
     log::info!("Starting oort_compiler_service v1");
 
     let cors = CorsLayer::new()
@@ -124,7 +223,7 @@ async fn main() {
             .route("/format", post(post_format))
             .layer(cors)
             .layer(tower_http::trace::TraceLayer::new_for_http())
-            .with_state(Arc::new(Mutex::new(compiler)))
+            .with_state(Arc::new(state))
     };
 
     axum::Server::bind(&format!("0.0.0.0:{port}").parse().unwrap())
@@ -132,3 +231,13 @@ async fn main() {
         .await
         .unwrap();
 }
+
+/// Gives a worker its own copy of the prebuilt dependencies.
+fn copy_dir(src: &str, dst: &str) {
+    let _ = std::fs::remove_dir_all(dst);
+    let status = std::process::Command::new("cp")
+        .args(["-a", src, dst])
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to copy {src} to {dst}");
+}
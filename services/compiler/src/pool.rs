@@ -0,0 +1,76 @@
+use crate::{error, Error};
+use axum::http::StatusCode;
+use oort_compiler::Compiler;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+
+/// A set of compilers with separate work directories.
+///
+/// Requests wait for a free compiler. Once `max_queue` requests are waiting,
+/// further requests are rejected with 429 Too Many Requests.
+pub struct Pool {
+    compilers: Arc<Mutex<Vec<Compiler>>>,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    max_queue: usize,
+}
+
+/// Counts a request as queued until dropped.
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Pool {
+    pub fn new(compilers: Vec<Compiler>, max_queue: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(compilers.len())),
+            compilers: Arc::new(Mutex::new(compilers)),
+            queued: AtomicUsize::new(0),
+            max_queue,
+        }
+    }
+
+    /// Runs `f` with a free compiler on a blocking thread.
+    ///
+    /// The compiler is returned to the pool by the blocking task itself, so
+    /// it isn't lost if the caller stops waiting, e.g. because the client
+    /// disconnected.
+    pub async fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Compiler) -> T + Send + 'static,
+    {
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        let slot = QueueSlot(&self.queued);
+        if queued >= self.max_queue {
+            return Err(error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many queued compiles".to_string(),
+            ));
+        }
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        drop(slot);
+
+        let compilers = self.compilers.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut compiler = compilers.lock().unwrap().pop().unwrap();
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| f(&mut compiler)));
+            compilers.lock().unwrap().push(compiler);
+            drop(permit);
+            result
+        })
+        .await?;
+        result.map_err(|_| {
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Compiler panicked".to_string(),
+            )
+        })
+    }
+}
@@ -9,13 +9,30 @@ use user::Ship;
 static START: Once = Once::new();
 static mut SHIP: Option<Ship> = None;
 
-#[doc(hidden)]
-#[no_mangle]
-pub unsafe fn tick() {
+fn start() {
     START.call_once(|| {
         oort_api::panic::install();
         oort_api::rng_state::set(oort_api::rng_state::RngState::new());
     });
+}
+
+/// Called once per ship before its first tick, with a larger instruction budget.
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe fn init() {
+    start();
+    oort_api::dbg::reset();
+    oort_api::panic::reset();
+    unsafe {
+        SHIP = Some(Ship::new());
+    }
+    oort_api::dbg::update();
+}
+
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe fn tick() {
+    start();
     oort_api::dbg::reset();
     oort_api::panic::reset();
     unsafe {
@@ -147,6 +147,14 @@ pub enum SystemState {
 
     Id,
 
+    DebugValuesPointer,
+    DebugValuesLength,
+
+    DebugShapesPointer,
+    DebugShapesLength,
+    DebugLayersPointer,
+    DebugLayersLength,
+
     Size,
     MaxSize = 128,
 }
@@ -154,6 +162,12 @@ pub enum SystemState {
 #[allow(missing_docs)]
 pub const MAX_ENVIRONMENT_SIZE: usize = 1024;
 
+/// Version of the [`SystemState`] layout shared with the simulator.
+///
+/// Bump this whenever the layout of [`SystemState`] changes.
+#[doc(hidden)]
+pub const ABI_VERSION: u32 = 3;
+
 /// Identifiers for each class of ship.
 #[allow(missing_docs)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -366,6 +380,55 @@ pub struct Text {
     pub text: [u8; 11],
 }
 
+#[doc(hidden)]
+#[derive(Default, Clone, Debug)]
+pub struct DebugValue {
+    pub value: f64,
+    /// One of the `DEBUG_VALUE_*` constants.
+    pub kind: u8,
+    pub length: u8,
+    pub name: [u8; 22],
+}
+
+#[doc(hidden)]
+#[derive(Default, Clone)]
+pub struct Shape {
+    /// Start of a line, or the center of other shapes.
+    pub x0: f64,
+    pub y0: f64,
+    /// End of a line.
+    pub x1: f64,
+    pub y1: f64,
+    pub radius: f64,
+    /// Start of an arc, or the rotation of a polygon.
+    pub start_angle: f64,
+    pub end_angle: f64,
+    pub color: u32,
+    /// Number of ticks to keep drawing the shape.
+    pub ttl: u16,
+    /// One of the `SHAPE_*` constants.
+    pub kind: u8,
+    pub sides: u8,
+    /// Index into the newline-separated layer names, plus one. Zero is the
+    /// default layer.
+    pub layer: u8,
+    pub filled: u8,
+}
+
+#[doc(hidden)]
+pub const SHAPE_LINE: u8 = 0;
+#[doc(hidden)]
+pub const SHAPE_CIRCLE: u8 = 1;
+#[doc(hidden)]
+pub const SHAPE_ARC: u8 = 2;
+#[doc(hidden)]
+pub const SHAPE_POLYGON: u8 = 3;
+
+#[doc(hidden)]
+pub const DEBUG_VALUE_PLOT: u8 = 0;
+#[doc(hidden)]
+pub const DEBUG_VALUE_WATCH: u8 = 1;
+
 /// Message sent and received on the radio.
 pub type Message = [f64; 4];
 
@@ -399,6 +462,11 @@ pub mod sys {
         write_system_state_u64(index, value.to_bits())
     }
 
+    #[no_mangle]
+    pub extern "C" fn abi_version() -> u32 {
+        crate::ABI_VERSION
+    }
+
     #[no_mangle]
     pub static mut ENVIRONMENT: [u8; MAX_ENVIRONMENT_SIZE] = [0; MAX_ENVIRONMENT_SIZE];
 
@@ -426,9 +494,79 @@ pub mod sys {
     }
 }
 
-mod math {
+/// Math functions computed by the simulator.
+///
+/// These give identical results on every platform and are charged a small
+/// fixed number of instructions per call, which is usually cheaper than the
+/// equivalent functions in `std` or `maths_rs`.
+pub mod math {
     pub use std::f64::consts::{PI, TAU};
 
+    #[cfg(target_arch = "wasm32")]
+    mod host {
+        #[link(wasm_import_module = "oort_math")]
+        extern "C" {
+            pub fn sin(x: f64) -> f64;
+            pub fn cos(x: f64) -> f64;
+            pub fn atan2(y: f64, x: f64) -> f64;
+            pub fn sqrt(x: f64) -> f64;
+            pub fn exp(x: f64) -> f64;
+            pub fn ln(x: f64) -> f64;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod host {
+        pub unsafe fn sin(x: f64) -> f64 {
+            x.sin()
+        }
+        pub unsafe fn cos(x: f64) -> f64 {
+            x.cos()
+        }
+        pub unsafe fn atan2(y: f64, x: f64) -> f64 {
+            y.atan2(x)
+        }
+        pub unsafe fn sqrt(x: f64) -> f64 {
+            x.sqrt()
+        }
+        pub unsafe fn exp(x: f64) -> f64 {
+            x.exp()
+        }
+        pub unsafe fn ln(x: f64) -> f64 {
+            x.ln()
+        }
+    }
+
+    /// Returns the sine of `x` (in radians).
+    pub fn sin(x: f64) -> f64 {
+        unsafe { host::sin(x) }
+    }
+
+    /// Returns the cosine of `x` (in radians).
+    pub fn cos(x: f64) -> f64 {
+        unsafe { host::cos(x) }
+    }
+
+    /// Returns the angle (in radians) of the point (`x`, `y`).
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        unsafe { host::atan2(y, x) }
+    }
+
+    /// Returns the square root of `x`.
+    pub fn sqrt(x: f64) -> f64 {
+        unsafe { host::sqrt(x) }
+    }
+
+    /// Returns `e^x`.
+    pub fn exp(x: f64) -> f64 {
+        unsafe { host::exp(x) }
+    }
+
+    /// Returns the natural logarithm of `x`.
+    pub fn ln(x: f64) -> f64 {
+        unsafe { host::ln(x) }
+    }
+
     /// Returns the smallest rotation between angles `a` and `b`.
     ///
     /// A positive result is a counter-clockwise rotation and negative is clockwise.
@@ -943,14 +1081,18 @@ mod api {
 #[doc(hidden)]
 #[macro_use]
 pub mod dbg {
-    use super::{Line, Text};
+    use super::{DebugValue, Shape, Text};
     use crate::sys::write_system_state;
     use crate::vec::*;
     use std::f64::consts::TAU;
 
     static mut TEXT_BUFFER: String = String::new();
-    static mut LINE_BUFFER: Vec<Line> = Vec::new();
+    static mut SHAPE_BUFFER: Vec<Shape> = Vec::new();
+    static mut LAYER_NAMES: String = String::new();
+    static mut LAYER: u8 = 0;
+    static mut TTL: u16 = 0;
     static mut DRAWN_TEXT_BUFFER: Vec<Text> = Vec::new();
+    static mut VALUE_BUFFER: Vec<DebugValue> = Vec::new();
 
     /// Adds text to be displayed when the ship is selected by clicking on it.
     ///
@@ -985,15 +1127,16 @@ pub mod dbg {
     /// `color` is 24-bit RGB.
     ///
     /// Up to 1024 lines can be drawn per ship, per tick. This quota is also consumed
-    /// by the various shape drawing functions.
+    /// by the various shape drawing functions, with each shape counting once.
     pub fn draw_line(a: Vec2, b: Vec2, color: u32) {
-        let buf = unsafe { &mut LINE_BUFFER };
-        buf.push(Line {
+        push_shape(Shape {
             x0: a.x,
             y0: a.y,
             x1: b.x,
             y1: b.y,
             color,
+            kind: super::SHAPE_LINE,
+            ..Default::default()
         });
     }
 
@@ -1008,16 +1151,7 @@ pub mod dbg {
     /// `center` is a position in world coordinates.
     /// `color` is 24-bit RGB.
     pub fn draw_polygon(center: Vec2, radius: f64, sides: i32, angle: f64, color: u32) {
-        let delta_angle = TAU / sides as f64;
-        let sin = delta_angle.sin();
-        let cos = delta_angle.cos();
-        let rotation: maths_rs::Mat2d = maths_rs::prelude::MatNew2::new(cos, -sin, sin, cos);
-        let mut p = vec2(radius, 0.0).rotate(angle);
-        for _ in 0..sides {
-            let p2 = rotation * p;
-            draw_line(center + p, center + p2, color);
-            p = p2;
-        }
+        push_polygon(center, radius, sides, angle, color, false);
     }
 
     #[deprecated]
@@ -1031,16 +1165,7 @@ pub mod dbg {
     /// `center` is a position in world coordinates.
     /// `color` is 24-bit RGB.
     pub fn draw_triangle(center: Vec2, radius: f64, color: u32) {
-        let x = f64::sqrt(3.0) * radius / 2.0;
-        let y = radius / 2.0;
-        let points = [
-            center + vec2(0.0, radius),
-            center + vec2(-x, -y),
-            center + vec2(x, -y),
-        ];
-        for i in 0..3 {
-            draw_line(points[i], points[(i + 1) % 3], color);
-        }
+        draw_polygon(center, radius, 3, TAU / 4.0, color);
     }
 
     #[deprecated]
@@ -1054,13 +1179,7 @@ pub mod dbg {
     /// `center` is a position in world coordinates.
     /// `color` is 24-bit RGB.
     pub fn draw_square(center: Vec2, radius: f64, color: u32) {
-        let offset = radius / f64::sqrt(2.0);
-        let mut p = vec2(offset, offset);
-        for _ in 0..4 {
-            let p2 = vec2(-p.y, p.x);
-            draw_line(center + p, center + p2, color);
-            p = p2;
-        }
+        draw_polygon(center, radius, 4, TAU / 8.0, color);
     }
 
     #[deprecated]
@@ -1074,12 +1193,7 @@ pub mod dbg {
     /// `center` is a position in world coordinates.
     /// `color` is 24-bit RGB.
     pub fn draw_diamond(center: Vec2, radius: f64, color: u32) {
-        let mut p = vec2(radius, 0.0);
-        for _ in 0..4 {
-            let p2 = vec2(-p.y, p.x);
-            draw_line(center + p, center + p2, color);
-            p = p2;
-        }
+        draw_polygon(center, radius, 4, 0.0, color);
     }
 
     #[deprecated]
@@ -1088,6 +1202,131 @@ pub mod dbg {
         draw_diamond(center, radius, color)
     }
 
+    /// Draws a circle visible in debug mode.
+    ///
+    /// `center` is a position in world coordinates.
+    /// `color` is 24-bit RGB.
+    pub fn draw_circle(center: Vec2, radius: f64, color: u32) {
+        push_shape(Shape {
+            x0: center.x,
+            y0: center.y,
+            radius,
+            color,
+            kind: super::SHAPE_CIRCLE,
+            ..Default::default()
+        });
+    }
+
+    /// Draws an arc visible in debug mode.
+    ///
+    /// The arc runs counter-clockwise from `start_angle` to `end_angle`, in
+    /// radians. `color` is 24-bit RGB.
+    pub fn draw_arc(center: Vec2, radius: f64, start_angle: f64, end_angle: f64, color: u32) {
+        push_shape(Shape {
+            x0: center.x,
+            y0: center.y,
+            radius,
+            start_angle,
+            end_angle,
+            color,
+            kind: super::SHAPE_ARC,
+            ..Default::default()
+        });
+    }
+
+    /// Draws a filled circle visible in debug mode.
+    ///
+    /// The fill is translucent so that ships underneath remain visible.
+    pub fn fill_circle(center: Vec2, radius: f64, color: u32) {
+        push_shape(Shape {
+            x0: center.x,
+            y0: center.y,
+            radius,
+            color,
+            kind: super::SHAPE_CIRCLE,
+            filled: 1,
+            ..Default::default()
+        });
+    }
+
+    /// Draws a filled regular polygon visible in debug mode.
+    ///
+    /// See [draw_polygon].
+    pub fn fill_polygon(center: Vec2, radius: f64, sides: i32, angle: f64, color: u32) {
+        push_polygon(center, radius, sides, angle, color, true);
+    }
+
+    /// Draws a filled triangle visible in debug mode.
+    pub fn fill_triangle(center: Vec2, radius: f64, color: u32) {
+        fill_polygon(center, radius, 3, TAU / 4.0, color);
+    }
+
+    /// Draws a filled square visible in debug mode.
+    pub fn fill_square(center: Vec2, radius: f64, color: u32) {
+        fill_polygon(center, radius, 4, TAU / 8.0, color);
+    }
+
+    /// Draws a filled diamond visible in debug mode.
+    pub fn fill_diamond(center: Vec2, radius: f64, color: u32) {
+        fill_polygon(center, radius, 4, 0.0, color);
+    }
+
+    /// Puts shapes drawn for the rest of this tick on a named layer.
+    ///
+    /// Layers can be shown and hidden in the UI. An empty name selects the
+    /// default layer. Up to 16 layers can be used per ship, per tick.
+    pub fn set_layer(name: &str) {
+        let names = unsafe { &mut LAYER_NAMES };
+        let name = name.replace('\n', " ");
+        let layer = if name.is_empty() {
+            0
+        } else if let Some(i) = names.split('\n').position(|x| x == name) {
+            i + 1
+        } else if names.is_empty() {
+            names.push_str(&name);
+            1
+        } else if names.split('\n').count() < 16 {
+            names.push('\n');
+            names.push_str(&name);
+            names.split('\n').count()
+        } else {
+            0
+        };
+        unsafe { LAYER = layer as u8 };
+    }
+
+    /// Keeps shapes drawn for the rest of this tick visible for `ticks` ticks.
+    ///
+    /// Useful for drawings that don't change every tick, like a predicted
+    /// trajectory. Zero, the default, draws shapes for the current tick only.
+    /// At most 1024 persistent shapes are kept per ship, oldest removed first.
+    pub fn set_ttl(ticks: u32) {
+        unsafe { TTL = ticks.min(u16::MAX as u32) as u16 };
+    }
+
+    fn push_polygon(center: Vec2, radius: f64, sides: i32, angle: f64, color: u32, filled: bool) {
+        push_shape(Shape {
+            x0: center.x,
+            y0: center.y,
+            radius,
+            start_angle: angle,
+            color,
+            kind: super::SHAPE_POLYGON,
+            sides: sides.clamp(3, u8::MAX as i32) as u8,
+            filled: filled as u8,
+            ..Default::default()
+        });
+    }
+
+    fn push_shape(shape: Shape) {
+        let buf = unsafe { &mut SHAPE_BUFFER };
+        buf.push(Shape {
+            layer: unsafe { LAYER },
+            ttl: unsafe { TTL },
+            ..shape
+        });
+    }
+
     /// Adds text to be drawn in the world, visible in debug mode.
     ///
     /// Works like [println!]. Up to 128 strings can be drawn per ship, per tick.
@@ -1120,6 +1359,38 @@ pub mod dbg {
         });
     }
 
+    /// Adds a point to a time-series chart, shown when the ship is selected.
+    ///
+    /// Call this every tick with the same `name` to build up a plot, for
+    /// example of the distance to a target. Names are truncated to 22 bytes.
+    /// Up to 32 values can be plotted or watched per ship, per tick.
+    pub fn plot(name: &str, value: f64) {
+        push_value(super::DEBUG_VALUE_PLOT, name, value);
+    }
+
+    /// Shows the current value of a variable when the ship is selected.
+    ///
+    /// Like [plot] but without the chart. Shares its quota.
+    pub fn watch(name: &str, value: f64) {
+        push_value(super::DEBUG_VALUE_WATCH, name, value);
+    }
+
+    fn push_value(kind: u8, name: &str, value: f64) {
+        let buf = unsafe { &mut VALUE_BUFFER };
+        let mut name_buf = [0u8; 22];
+        let mut length = name.len().min(name_buf.len());
+        while !name.is_char_boundary(length) {
+            length -= 1;
+        }
+        name_buf[..length].copy_from_slice(&name.as_bytes()[..length]);
+        buf.push(DebugValue {
+            value,
+            kind,
+            length: length as u8,
+            name: name_buf,
+        });
+    }
+
     #[doc(hidden)]
     pub fn update() {
         {
@@ -1134,13 +1405,24 @@ pub mod dbg {
             );
         }
         {
-            let slice = unsafe { &mut LINE_BUFFER }.as_slice();
+            let slice = unsafe { &mut SHAPE_BUFFER }.as_slice();
+            write_system_state(
+                super::SystemState::DebugShapesPointer,
+                slice.as_ptr() as u32 as f64,
+            );
+            write_system_state(
+                super::SystemState::DebugShapesLength,
+                slice.len() as u32 as f64,
+            );
+        }
+        {
+            let slice = unsafe { &mut LAYER_NAMES }.as_bytes();
             write_system_state(
-                super::SystemState::DebugLinesPointer,
+                super::SystemState::DebugLayersPointer,
                 slice.as_ptr() as u32 as f64,
             );
             write_system_state(
-                super::SystemState::DebugLinesLength,
+                super::SystemState::DebugLayersLength,
                 slice.len() as u32 as f64,
             );
         }
@@ -1155,14 +1437,29 @@ pub mod dbg {
                 slice.len() as u32 as f64,
             );
         }
+        {
+            let slice = unsafe { &mut VALUE_BUFFER }.as_slice();
+            write_system_state(
+                super::SystemState::DebugValuesPointer,
+                slice.as_ptr() as u32 as f64,
+            );
+            write_system_state(
+                super::SystemState::DebugValuesLength,
+                slice.len() as u32 as f64,
+            );
+        }
     }
 
     #[doc(hidden)]
     pub fn reset() {
         unsafe {
             TEXT_BUFFER.clear();
-            LINE_BUFFER.clear();
+            SHAPE_BUFFER.clear();
+            LAYER_NAMES.clear();
+            LAYER = 0;
+            TTL = 0;
             DRAWN_TEXT_BUFFER.clear();
+            VALUE_BUFFER.clear();
         }
     }
 }
@@ -1218,7 +1515,7 @@ pub mod prelude {
     #[doc(hidden)]
     pub use super::deprecated::*;
     #[doc(inline)]
-    pub use super::math::*;
+    pub use super::math::{angle_diff, PI, TAU};
     #[doc(inline)]
     pub use super::rng::*;
     #[doc(inline)]
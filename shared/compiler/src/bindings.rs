@@ -0,0 +1,178 @@
+// Generates the parts of the C and Zig APIs that mirror oort_api, so that the
+// system state layout and ABI version can't drift between languages.
+use anyhow::{anyhow, bail, Result};
+
+const API_SOURCE: &str = include_str!("../../api/src/lib.rs");
+
+const BEGIN_MARKER: &str = "// BEGIN GENERATED";
+const END_MARKER: &str = "// END GENERATED";
+
+pub const C_HEADER: &str = include_str!("../../cpp-api/oort.h");
+pub const ZIG_API: &str = include_str!("../../zig-api/oort.zig");
+
+struct Variant {
+    name: String,
+    discriminant: Option<String>,
+    // Start a new group, separated by a blank line as in oort_api.
+    group: bool,
+}
+
+struct Api {
+    abi_version: String,
+    system_state: Vec<Variant>,
+}
+
+fn parse_api() -> Result<Api> {
+    let file = syn::parse_file(API_SOURCE)?;
+    let mut abi_version = None;
+    let mut system_state = None;
+    for item in file.items.iter() {
+        match item {
+            syn::Item::Const(item) if item.ident == "ABI_VERSION" => {
+                if let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(lit),
+                    ..
+                }) = item.expr.as_ref()
+                {
+                    abi_version = Some(lit.base10_digits().to_string());
+                }
+            }
+            syn::Item::Enum(item) if item.ident == "SystemState" => {
+                let mut variants = vec![];
+                let mut prev_line = None;
+                for variant in item.variants.iter() {
+                    let line = variant.ident.span().start().line;
+                    let discriminant = match &variant.discriminant {
+                        Some((
+                            _,
+                            syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Int(lit),
+                                ..
+                            }),
+                        )) => Some(lit.base10_digits().to_string()),
+                        Some(_) => bail!("Unsupported discriminant for {}", variant.ident),
+                        None => None,
+                    };
+                    variants.push(Variant {
+                        name: variant.ident.to_string(),
+                        discriminant,
+                        group: prev_line.map(|x| line > x + 1).unwrap_or(false),
+                    });
+                    prev_line = Some(line);
+                }
+                system_state = Some(variants);
+            }
+            _ => {}
+        }
+    }
+    Ok(Api {
+        abi_version: abi_version.ok_or_else(|| anyhow!("ABI_VERSION not found"))?,
+        system_state: system_state.ok_or_else(|| anyhow!("SystemState not found"))?,
+    })
+}
+
+fn enum_body(api: &Api) -> String {
+    let mut body = String::new();
+    for variant in api.system_state.iter() {
+        if variant.group {
+            body.push('\n');
+        }
+        match &variant.discriminant {
+            Some(value) => body.push_str(&format!("    {} = {},\n", variant.name, value)),
+            None => body.push_str(&format!("    {},\n", variant.name)),
+        }
+    }
+    body
+}
+
+fn generate_c(api: &Api) -> String {
+    format!(
+        "#define OORT_ABI_VERSION {}\n\nenum SystemState {{\n{}}};\n",
+        api.abi_version,
+        enum_body(api)
+    )
+}
+
+fn generate_zig(api: &Api) -> String {
+    format!(
+        "pub const ABI_VERSION: u32 = {};\n\npub const SystemState = enum(u8) {{\n{}}};\n",
+        api.abi_version,
+        enum_body(api)
+    )
+}
+
+/// Replaces the lines between the generated-code markers in `template`.
+fn splice(template: &str, generated: &str) -> Result<String> {
+    let begin = template
+        .find(BEGIN_MARKER)
+        .ok_or_else(|| anyhow!("Missing {BEGIN_MARKER}"))?;
+    let begin = begin + template[begin..].find('\n').unwrap_or(0) + 1;
+    let end = template
+        .find(END_MARKER)
+        .ok_or_else(|| anyhow!("Missing {END_MARKER}"))?;
+    if end < begin {
+        bail!("Misplaced {END_MARKER}");
+    }
+    Ok(format!(
+        "{}{}{}",
+        &template[..begin],
+        generated,
+        &template[end..]
+    ))
+}
+
+/// Returns oort.h, for both C and C++.
+pub fn c_header() -> Result<String> {
+    splice(C_HEADER, &generate_c(&parse_api()?))
+}
+
+/// Returns oort.zig.
+pub fn zig_api() -> Result<String> {
+    splice(ZIG_API, &generate_zig(&parse_api()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Run with OORT_UPDATE_BINDINGS=1 to rewrite the checked-in files after
+    // changing oort_api.
+    #[test]
+    fn test_bindings_up_to_date() {
+        let update = std::env::var("OORT_UPDATE_BINDINGS").is_ok();
+        for (path, original, generated) in [
+            ("../cpp-api/oort.h", C_HEADER, c_header().unwrap()),
+            ("../zig-api/oort.zig", ZIG_API, zig_api().unwrap()),
+        ] {
+            if update {
+                std::fs::write(path, &generated).unwrap();
+            } else {
+                assert_eq!(
+                    original, generated,
+                    "{path} is out of date, rerun with OORT_UPDATE_BINDINGS=1"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let api = parse_api().unwrap();
+        let c = generate_c(&api);
+        assert!(c.starts_with("#define OORT_ABI_VERSION "));
+        assert!(c.contains("enum SystemState {\n    Class,\n    Seed,\n"));
+        assert!(c.contains("\n\n    AccelerateX,\n"));
+        assert!(c.contains("    MaxSize = 128,\n};\n"));
+        assert!(generate_zig(&api).contains("pub const SystemState = enum(u8) {\n"));
+    }
+
+    #[test]
+    fn test_splice() {
+        let template = "a\n// BEGIN GENERATED x\nold\n// END GENERATED\nb\n";
+        assert_eq!(
+            splice(template, "new\n").unwrap(),
+            "a\n// BEGIN GENERATED x\nnew\n// END GENERATED\nb\n"
+        );
+        assert!(splice("a\n", "new\n").is_err());
+    }
+}
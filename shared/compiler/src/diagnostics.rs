@@ -0,0 +1,150 @@
+// Converts rustc's JSON diagnostics (--error-format=json) into the structured
+// diagnostics returned by the compiler service.
+use oort_proto::{CompilerDiagnostic, DiagnosticLevel, DiagnosticSpan, DiagnosticSuggestion};
+use serde::Deserialize;
+
+const USER_FILE: &str = "user.rs";
+
+#[derive(Deserialize)]
+struct RustcDiagnostic {
+    message: String,
+    code: Option<RustcCode>,
+    level: String,
+    #[serde(default)]
+    spans: Vec<RustcSpan>,
+    #[serde(default)]
+    children: Vec<RustcDiagnostic>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RustcCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RustcSpan {
+    file_name: String,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+/// Parses rustc's stderr.
+///
+/// `code` is the submitted source, used to map lines back to the original
+/// files if it was joined with `oort_multifile::join`. Lines that aren't JSON
+/// diagnostics are returned separately.
+pub fn parse(stderr: &str, code: &str) -> (Vec<CompilerDiagnostic>, Vec<String>) {
+    let mut diagnostics = vec![];
+    let mut other = vec![];
+    for line in stderr.lines() {
+        match serde_json::from_str::<RustcDiagnostic>(line) {
+            Ok(diagnostic) => {
+                if let Some(diagnostic) = convert(diagnostic, code) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+            Err(_) => other.push(line.to_string()),
+        }
+    }
+    (diagnostics, other)
+}
+
+fn convert(diagnostic: RustcDiagnostic, code: &str) -> Option<CompilerDiagnostic> {
+    let level = convert_level(&diagnostic.level)?;
+    let span = diagnostic
+        .spans
+        .iter()
+        .filter(|span| is_user_span(span))
+        .max_by_key(|span| span.is_primary)
+        .map(|span| convert_span(span, code));
+
+    let mut notes = vec![];
+    let mut suggestions = vec![];
+    for child in diagnostic.children.iter() {
+        for span in child.spans.iter().filter(|span| is_user_span(span)) {
+            if let Some(replacement) = span.suggested_replacement.as_ref() {
+                suggestions.push(DiagnosticSuggestion {
+                    message: child.message.clone(),
+                    span: convert_span(span, code),
+                    replacement: replacement.clone(),
+                    machine_applicable: span.suggestion_applicability.as_deref()
+                        == Some("MachineApplicable"),
+                });
+            }
+        }
+        notes.push(format!("{}: {}", child.level, child.message));
+    }
+
+    Some(CompilerDiagnostic {
+        level,
+        message: diagnostic.message,
+        code: diagnostic.code.map(|x| x.code),
+        span,
+        notes,
+        suggestions,
+        rendered: diagnostic.rendered.unwrap_or_default(),
+    })
+}
+
+fn convert_level(level: &str) -> Option<DiagnosticLevel> {
+    match level {
+        "error" | "error: internal compiler error" => Some(DiagnosticLevel::Error),
+        "warning" => Some(DiagnosticLevel::Warning),
+        "note" => Some(DiagnosticLevel::Note),
+        "help" => Some(DiagnosticLevel::Help),
+        _ => None,
+    }
+}
+
+fn is_user_span(span: &RustcSpan) -> bool {
+    span.file_name.ends_with(USER_FILE)
+}
+
+fn convert_span(span: &RustcSpan, code: &str) -> DiagnosticSpan {
+    let (file, file_line) = oort_multifile::map_line(code, span.line_start);
+    DiagnosticSpan {
+        line_start: span.line_start,
+        column_start: span.column_start,
+        line_end: span.line_end,
+        column_end: span.column_end,
+        file,
+        file_line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let stderr = r#"{"$message_type":"diagnostic","message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"/tmp/oort-ai/ai/src/user.rs","byte_start":60,"byte_end":61,"line_start":4,"line_end":4,"column_start":13,"column_end":14,"is_primary":true,"text":[],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"`#[warn(unused_variables)]` on by default","code":null,"level":"note","spans":[],"children":[],"rendered":null},{"message":"if this is intentional, prefix it with an underscore","code":null,"level":"help","spans":[{"file_name":"/tmp/oort-ai/ai/src/user.rs","byte_start":60,"byte_end":61,"line_start":4,"line_end":4,"column_start":13,"column_end":14,"is_primary":true,"text":[],"label":null,"suggested_replacement":"_x","suggestion_applicability":"MachineApplicable","expansion":null}],"children":[],"rendered":null}],"rendered":"warning: unused variable: `x`\n"}
+{"$message_type":"diagnostic","message":"aborting due to previous error","code":null,"level":"error","spans":[],"children":[],"rendered":"error: aborting due to previous error\n"}
+not json"#;
+        let code = "use foo::*;\nmod foo { // start multifile\nfn foo() {\n    let x = 1;\n}\n} // end multifile\n";
+        let (diagnostics, other) = parse(stderr, code);
+        assert_eq!(other, vec!["not json".to_string()]);
+        assert_eq!(diagnostics.len(), 2);
+
+        let warning = &diagnostics[0];
+        assert_eq!(warning.level, DiagnosticLevel::Warning);
+        assert_eq!(warning.code.as_deref(), Some("unused_variables"));
+        let span = warning.span.as_ref().unwrap();
+        assert_eq!((span.line_start, span.column_start), (4, 13));
+        assert_eq!((span.file.as_str(), span.file_line), ("foo.rs", 2));
+        assert_eq!(warning.notes.len(), 2);
+        assert_eq!(warning.suggestions.len(), 1);
+        assert_eq!(warning.suggestions[0].replacement, "_x");
+        assert!(warning.suggestions[0].machine_applicable);
+
+        let error = &diagnostics[1];
+        assert_eq!(error.level, DiagnosticLevel::Error);
+        assert!(error.span.is_none());
+    }
+}
@@ -1,14 +1,29 @@
+pub mod bindings;
+mod diagnostics;
+mod process;
 mod sanitizer;
+pub mod symbols;
 
 use anyhow::{bail, Result};
+use oort_proto::CompilerDiagnostic;
+pub use process::Limits;
 use std::path::{Path, PathBuf};
 
+/// Third-party crates that user code can `use`.
+///
+/// These are dependencies of the `oort_ai` crate so they're prebuilt along
+/// with `oort_api`.
+pub const ALLOWED_CRATES: &[&str] = &["arrayvec", "nalgebra"];
+
 pub struct Compiler {
     #[allow(dead_code)]
     tmp_dir: Option<tempdir::TempDir>,
     dir: PathBuf,
     offline: bool,
+    debug_info: bool,
     rustc: String,
+    diagnostics: Vec<CompilerDiagnostic>,
+    limits: Limits,
 }
 
 #[allow(clippy::new_without_default)]
@@ -20,7 +35,10 @@ impl Compiler {
             tmp_dir: Some(tmp_dir),
             dir,
             offline: true,
+            debug_info: false,
             rustc: find_rustc(),
+            diagnostics: vec![],
+            limits: Limits::default(),
         }
     }
 
@@ -29,7 +47,10 @@ impl Compiler {
             tmp_dir: None,
             dir: dir.to_path_buf(),
             offline: true,
+            debug_info: false,
             rustc: find_rustc(),
+            diagnostics: vec![],
+            limits: Limits::default(),
         }
     }
 
@@ -37,11 +58,45 @@ impl Compiler {
         self.offline = false;
     }
 
+    /// Include a table mapping functions to source lines in compiled Rust code.
+    ///
+    /// This is used to symbolicate crashes and should be stripped with
+    /// [`symbols::strip`] before the code is shared.
+    pub fn set_debug_info(&mut self, debug_info: bool) {
+        self.debug_info = debug_info;
+    }
+
+    /// Limits applied to the compiler processes for user code.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Errors and warnings from the last Rust compilation.
+    pub fn diagnostics(&self) -> &[CompilerDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Compiles code in a language guessed from its contents.
+    ///
+    /// Only Rust and C++ are recognized. Prefer `compile_language`.
     pub fn compile(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
-        match detect_language(code) {
+        match Language::detect(code) {
+            Some(language) => self.compile_language(code, language),
+            None => bail!("Unknown language"),
+        }
+    }
+
+    pub fn compile_language(
+        &mut self,
+        code: &str,
+        language: Language,
+    ) -> Result<Vec<u8> /* wasm */> {
+        self.diagnostics.clear();
+        match language {
             Language::Rust => self.compile_rust(code),
+            Language::C => self.compile_c(code),
             Language::Cpp => self.compile_cpp(code),
-            Language::Unknown => bail!("Unknown language"),
+            Language::Zig => self.compile_zig(code),
         }
     }
 
@@ -124,7 +179,8 @@ impl Compiler {
         std::fs::write(tmp_path.join("ai/src/user.rs"), code.as_bytes())?;
         let rustc_bin_dir = Path::new(&self.rustc).parent().unwrap();
 
-        let output = std::process::Command::new(&self.rustc)
+        let mut command = std::process::Command::new(&self.rustc);
+        command
             .env(
                 "LD_LIBRARY_PATH",
                 &format!("{}/../lib", rustc_bin_dir.display()),
@@ -144,8 +200,13 @@ impl Compiler {
                     .unwrap(),
                 "--target",
                 "wasm32-unknown-unknown",
+                "--error-format=json",
                 "-C",
-                "strip=debuginfo",
+                if self.debug_info {
+                    "debuginfo=1"
+                } else {
+                    "strip=debuginfo"
+                },
                 "-L",
                 &format!(
                     "dependency={}",
@@ -171,49 +232,96 @@ impl Compiler {
                 "llvm-args=-rng-seed=42",
                 "--remap-path-prefix",
                 &format!("{}=/tmp/oort-ai", tmp_path.display()),
-            ])
-            .output()?;
+            ]);
+        for crate_name in ALLOWED_CRATES {
+            command.arg("--extern").arg(format!(
+                "{crate_name}={}",
+                find_rlib(tmp_path, crate_name).display()
+            ));
+        }
+        let output = process::run(&mut command, &self.limits)?;
+        let (diagnostics, other) = diagnostics::parse(std::str::from_utf8(&output.stderr)?, code);
+        self.diagnostics = diagnostics;
         if !output.status.success() {
-            bail!("rustc failed: {}", std::str::from_utf8(&output.stderr)?);
+            let rendered: Vec<_> = self
+                .diagnostics
+                .iter()
+                .map(|x| x.rendered.trim_end())
+                .chain(other.iter().map(|x| x.as_str()))
+                .collect();
+            bail!("rustc failed: {}", rendered.join("\n"));
         }
 
-        Ok(std::fs::read(tmp_path.join(
-            "target/wasm32-unknown-unknown/release/oort_ai.wasm",
-        ))?)
+        let wasm =
+            std::fs::read(tmp_path.join("target/wasm32-unknown-unknown/release/oort_ai.wasm"))?;
+        if self.debug_info {
+            symbols::process(&wasm)
+        } else {
+            Ok(wasm)
+        }
+    }
+
+    /// Formats Rust code with rustfmt.
+    pub fn format(&mut self, code: &str) -> Result<String> {
+        let path = self.dir.join("format.rs");
+        std::fs::write(&path, code.as_bytes())?;
+        let mut command = std::process::Command::new("rustfmt");
+        command.arg(&path);
+        let output = process::run(&mut command, &self.limits)?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(std::fs::read_to_string(&path)?)
+    }
+
+    pub fn compile_c(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
+        self.compile_with_zig_cc(code, Language::C)
     }
 
     pub fn compile_cpp(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
+        self.compile_with_zig_cc(code, Language::Cpp)
+    }
+
+    fn compile_with_zig_cc(
+        &mut self,
+        code: &str,
+        language: Language,
+    ) -> Result<Vec<u8> /* wasm */> {
         let tmp_path = &self.dir;
-        let src_path = tmp_path.join("user.cpp");
+        let (driver, extension, runtime) = match language {
+            Language::C => ("cc", "c", &include_bytes!("../../cpp-api/oort.c")[..]),
+            Language::Cpp => ("c++", "cpp", &include_bytes!("../../cpp-api/oort.cpp")[..]),
+            _ => unreachable!(),
+        };
+        let src_path = tmp_path.join(format!("user.{extension}"));
+        let runtime_path = tmp_path.join(format!("oort.{extension}"));
         let dst_path = tmp_path.join("user.wasm");
         std::fs::write(&src_path, code.as_bytes())?;
-        std::fs::write(
-            tmp_path.join("oort.h"),
-            include_bytes!("../../cpp-api/oort.h"),
-        )?;
-        std::fs::write(
-            tmp_path.join("oort.cpp"),
-            include_bytes!("../../cpp-api/oort.cpp"),
-        )?;
+        std::fs::write(tmp_path.join("oort.h"), bindings::c_header()?)?;
+        std::fs::write(&runtime_path, runtime)?;
 
-        let output = std::process::Command::new("zig")
-            .args([
-                "c++",
-                "-shared",
-                "-target",
-                "wasm32-wasi",
-                "-fno-exceptions",
-                "-fno-stack-protector",
-                "-Oz",
-                "-Wl,--export=SYSTEM_STATE",
-                "-Wl,--export=ENVIRONMENT",
-                "-Wl,--export=PANIC_BUFFER",
-                tmp_path.join("oort.cpp").as_os_str().to_str().unwrap(),
-                src_path.as_os_str().to_str().unwrap(),
-                "-o",
-                dst_path.as_os_str().to_str().unwrap(),
-            ])
-            .output()?;
+        let mut command = std::process::Command::new("zig");
+        command.args([
+            driver,
+            "-shared",
+            "-target",
+            "wasm32-wasi",
+            "-fno-stack-protector",
+            "-Oz",
+            "-Wl,--export=SYSTEM_STATE",
+            "-Wl,--export=ENVIRONMENT",
+            "-Wl,--export=PANIC_BUFFER",
+        ]);
+        if language == Language::Cpp {
+            command.arg("-fno-exceptions");
+        }
+        command.args([
+            runtime_path.as_os_str().to_str().unwrap(),
+            src_path.as_os_str().to_str().unwrap(),
+            "-o",
+            dst_path.as_os_str().to_str().unwrap(),
+        ]);
+        let output = process::run(&mut command, &self.limits)?;
         if !output.status.success() {
             bail!(
                 "compilation failed: {}",
@@ -221,9 +329,49 @@ impl Compiler {
             );
         }
 
-        let output = std::process::Command::new("wasm-strip")
-            .args([dst_path.as_os_str().to_str().unwrap()])
-            .output()?;
+        self.strip(&dst_path)
+    }
+
+    pub fn compile_zig(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
+        let tmp_path = &self.dir;
+        let main_path = tmp_path.join("main.zig");
+        let dst_path = tmp_path.join("user.wasm");
+        std::fs::write(tmp_path.join("user.zig"), code.as_bytes())?;
+        std::fs::write(tmp_path.join("oort.zig"), bindings::zig_api()?)?;
+        std::fs::write(&main_path, include_bytes!("../../zig-api/main.zig"))?;
+
+        let mut command = std::process::Command::new("zig");
+        command.args([
+            "build-lib",
+            "-dynamic",
+            "-rdynamic",
+            "-target",
+            "wasm32-freestanding",
+            "-O",
+            "ReleaseSmall",
+            "--stack",
+            "16384",
+            main_path.as_os_str().to_str().unwrap(),
+            &format!("-femit-bin={}", dst_path.display()),
+        ]);
+        // Keep zig's cache inside the work directory.
+        command.env("ZIG_LOCAL_CACHE_DIR", tmp_path.join("zig-cache"));
+        command.env("ZIG_GLOBAL_CACHE_DIR", tmp_path.join("zig-cache"));
+        let output = process::run(&mut command, &self.limits)?;
+        if !output.status.success() {
+            bail!(
+                "compilation failed: {}",
+                std::str::from_utf8(&output.stderr)?
+            );
+        }
+
+        self.strip(&dst_path)
+    }
+
+    fn strip(&self, path: &Path) -> Result<Vec<u8> /* wasm */> {
+        let mut command = std::process::Command::new("wasm-strip");
+        command.args([path.as_os_str().to_str().unwrap()]);
+        let output = process::run(&mut command, &self.limits)?;
         if !output.status.success() {
             bail!(
                 "wasm-strip failed: {}",
@@ -231,7 +379,7 @@ impl Compiler {
             );
         }
 
-        Ok(std::fs::read(&dst_path)?)
+        Ok(std::fs::read(path)?)
     }
 }
 
@@ -272,20 +420,68 @@ fn find_rustc() -> String {
     }
 }
 
-enum Language {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
     Rust,
+    C,
     Cpp,
-    Unknown,
+    Zig,
 }
 
-fn detect_language(code: &str) -> Language {
-    log::debug!("Code: {:?}", code);
-    if code.contains("#include") {
-        log::info!("Detected C++");
-        Language::Cpp
-    } else if code.contains("impl Ship") {
-        Language::Rust
-    } else {
-        Language::Unknown
+impl Language {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::C => "c",
+            Language::Cpp => "cpp",
+            Language::Zig => "zig",
+        }
+    }
+
+    /// Guesses the language for clients that don't specify one.
+    pub fn detect(code: &str) -> Option<Language> {
+        log::debug!("Code: {:?}", code);
+        if code.contains("#include") {
+            log::info!("Detected C++");
+            Some(Language::Cpp)
+        } else if code.contains("impl Ship") {
+            Some(Language::Rust)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::str::FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rust" | "rs" => Ok(Language::Rust),
+            "c" => Ok(Language::C),
+            "cpp" | "c++" => Ok(Language::Cpp),
+            "zig" => Ok(Language::Zig),
+            _ => bail!("Unknown language {:?}", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        // Used when the client doesn't send a language.
+        assert_eq!(
+            Language::detect("#include \"oort.h\"\nvoid tick() {}"),
+            Some(Language::Cpp)
+        );
+        assert_eq!(
+            Language::detect("use oort_api::prelude::*;\nimpl Ship {}"),
+            Some(Language::Rust)
+        );
+        assert_eq!(Language::detect("const x = 1;"), None);
+        assert!(Compiler::new().compile("const x = 1;").is_err());
     }
 }
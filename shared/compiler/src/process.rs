@@ -0,0 +1,157 @@
+// Runs compiler subprocesses with a wall-clock timeout and resource limits.
+//
+// Each subprocess is started in its own process group so that compiler
+// drivers like zig can be killed along with the processes they spawn.
+use anyhow::{bail, Result};
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Limits applied to each compiler subprocess.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Wall-clock time after which the process is killed.
+    pub timeout: Option<Duration>,
+    /// Maximum size of the process's data segment in bytes (RLIMIT_DATA).
+    pub memory: Option<u64>,
+    /// Maximum CPU time in seconds (RLIMIT_CPU).
+    pub cpu_seconds: Option<u64>,
+}
+
+pub fn run(command: &mut Command, limits: &Limits) -> Result<Output> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+        let (memory, cpu_seconds) = (limits.memory, limits.cpu_seconds);
+        // SAFETY: setrlimit is async-signal-safe.
+        unsafe {
+            command.pre_exec(move || {
+                let set = |resource, value: u64| {
+                    let limit = libc::rlimit {
+                        rlim_cur: value as libc::rlim_t,
+                        rlim_max: value as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                };
+                if let Some(memory) = memory {
+                    set(libc::RLIMIT_DATA, memory)?;
+                }
+                if let Some(cpu_seconds) = cpu_seconds {
+                    set(libc::RLIMIT_CPU, cpu_seconds)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let start_time = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Some(timeout) = limits.timeout {
+            if start_time.elapsed() > timeout {
+                kill_group(&mut child);
+                let _ = child.wait();
+                bail!("Compilation timed out after {:?}", timeout);
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    // Stragglers would keep the output pipes open.
+    kill_group(&mut child);
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // SAFETY: kill has no memory safety requirements. The group id
+        // isn't reused while any process in the group is alive.
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = child.kill();
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(
+    reader: Option<R>,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut reader) = reader {
+            let _ = reader.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output() {
+        let output = run(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            &Limits::default(),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn test_timeout() {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let start_time = Instant::now();
+        assert!(run(Command::new("sleep").arg("10"), &limits).is_err());
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_timeout_kills_group() {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let dir = tempdir::TempDir::new("oort_process_test").unwrap();
+        let pid_path = dir.path().join("pid");
+        let script = format!("sleep 10 & echo $! > {}; wait", pid_path.display());
+        assert!(run(Command::new("sh").args(["-c", &script]), &limits).is_err());
+
+        let pid = std::fs::read_to_string(&pid_path).unwrap();
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()));
+        // Either reaped or a zombie waiting for init.
+        if let Ok(stat) = stat {
+            assert!(stat.contains(") Z "), "{stat}");
+        }
+    }
+}
@@ -1,23 +1,304 @@
+// Rejects code that could read files or environment variables on the compiler
+// service, or interfere with the interface between the AI and the simulator.
 use anyhow::Result;
-use lazy_static::lazy_static;
-use regex::Regex;
+use proc_macro2::{Delimiter, LineColumn, Spacing, Span, TokenStream, TokenTree};
+use syn::ext::IdentExt;
+use syn::visit::Visit;
+
+/// Macros that read files or the environment at compile time.
+const FORBIDDEN_MACROS: &[&str] = &[
+    "macro_rules",
+    "include",
+    "include_bytes",
+    "include_str",
+    "env",
+    "option_env",
+];
+
+/// Modules whose glob imports bring the macros above into scope.
+const MACRO_MODULES: &[&str] = &[
+    "prelude",
+    "v1",
+    "rust_2015",
+    "rust_2018",
+    "rust_2021",
+    "rust_2024",
+    "macros",
+    "builtin",
+];
+
+/// Attributes that load other files or change how symbols are linked.
+const FORBIDDEN_ATTRIBUTES: &[&str] = &[
+    "path",
+    "link",
+    "link_name",
+    "link_section",
+    "no_mangle",
+    "export_name",
+];
 
 pub fn check(text: &str) -> Result<()> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"\b(macro_rules|include|include_bytes|include_str)(\b|!)").unwrap();
+    let tokens: TokenStream = match text.parse() {
+        Ok(tokens) => tokens,
+        // Unbalanced delimiters. rustc will reject this before expanding macros.
+        Err(_) => return Ok(()),
+    };
+
+    let violation = match syn::parse2::<syn::File>(tokens.clone()) {
+        Ok(file) => {
+            let mut visitor = Visitor { violation: None };
+            visitor.visit_file(&file);
+            visitor.violation
+        }
+        // Not valid Rust. Fall back to checking tokens so that rustc can
+        // report the syntax error.
+        Err(_) => check_tokens(tokens),
+    };
+
+    match violation {
+        Some(Violation { msg, start }) => anyhow::bail!(
+            "Code did not pass sanitizer ({}) at line {}, column {}",
+            msg,
+            start.line,
+            start.column + 1
+        ),
+        None => Ok(()),
     }
-    if let Some(m) = RE.find(text) {
-        anyhow::bail!("Code did not pass sanitizer (found {:?})", m.as_str());
+}
+
+struct Violation {
+    msg: String,
+    start: LineColumn,
+}
+
+impl Violation {
+    fn new(msg: String, span: Span) -> Self {
+        Self {
+            msg,
+            start: span.start(),
+        }
     }
+}
 
-    lazy_static! {
-        static ref RE2: Regex = Regex::new(r"#\[[^]]*path").unwrap();
+struct Visitor {
+    violation: Option<Violation>,
+}
+
+impl Visitor {
+    fn report(&mut self, violation: Option<Violation>) {
+        if self.violation.is_none() {
+            self.violation = violation;
+        }
     }
-    if let Some(m) = RE2.find(text) {
-        anyhow::bail!("Code did not pass sanitizer (found {:?})", m.as_str());
+
+    fn check_ident(&mut self, ident: &syn::Ident) {
+        let name = ident.unraw().to_string();
+        if FORBIDDEN_MACROS.contains(&name.as_str()) {
+            self.report(Some(Violation::new(
+                format!("found \"{name}\""),
+                ident.span(),
+            )));
+        }
+    }
+
+    /// Rejects imports of forbidden macros, under any name, and glob imports
+    /// of the standard library modules that export them.
+    fn check_use_tree(&mut self, tree: &syn::UseTree, prefix: &mut Vec<String>) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                self.check_ident(&path.ident);
+                prefix.push(path.ident.unraw().to_string());
+                self.check_use_tree(&path.tree, prefix);
+                prefix.pop();
+            }
+            syn::UseTree::Name(name) => self.check_ident(&name.ident),
+            syn::UseTree::Rename(rename) => {
+                self.check_ident(&rename.ident);
+                // A renamed std could be glob imported under the new name.
+                if prefix.is_empty() && (rename.ident == "std" || rename.ident == "core") {
+                    self.report(Some(Violation::new(
+                        format!("found rename of \"{}\"", rename.ident),
+                        rename.ident.span(),
+                    )));
+                }
+            }
+            syn::UseTree::Glob(glob) => {
+                let exports_macros =
+                    matches!(prefix.first().map(String::as_str), Some("std" | "core"))
+                        && prefix[1..]
+                            .iter()
+                            .all(|segment| MACRO_MODULES.contains(&segment.as_str()));
+                if exports_macros {
+                    self.report(Some(Violation::new(
+                        format!("found glob import of \"{}\"", prefix.join("::")),
+                        glob.star_token.spans[0],
+                    )));
+                }
+            }
+            syn::UseTree::Group(group) => {
+                for tree in group.items.iter() {
+                    self.check_use_tree(tree, prefix);
+                }
+            }
+        }
     }
-    Ok(())
+}
+
+impl<'ast> Visit<'ast> for Visitor {
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Some(segment) = mac.path.segments.last() {
+            let name = segment.ident.unraw().to_string();
+            if FORBIDDEN_MACROS.contains(&name.as_str()) {
+                self.report(Some(Violation::new(
+                    format!("found \"{name}!\""),
+                    segment.ident.span(),
+                )));
+            }
+        }
+        // Macro arguments aren't parsed, so check them as tokens.
+        self.report(check_tokens(mac.tokens.clone()));
+        syn::visit::visit_macro(self, mac);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        // A single identifier is a local name. Macros are only reachable
+        // through longer paths, invocations and imports.
+        if path.segments.len() > 1 || path.leading_colon.is_some() {
+            for segment in path.segments.iter() {
+                self.check_ident(&segment.ident);
+            }
+        }
+        syn::visit::visit_path(self, path);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let mut prefix = vec![];
+        self.check_use_tree(&item.tree, &mut prefix);
+        syn::visit::visit_item_use(self, item);
+    }
+
+    fn visit_attribute(&mut self, attr: &'ast syn::Attribute) {
+        for segment in attr.path().segments.iter() {
+            let name = segment.ident.unraw().to_string();
+            if FORBIDDEN_ATTRIBUTES.contains(&name.as_str()) {
+                self.report(Some(Violation::new(
+                    format!("found attribute \"{name}\""),
+                    segment.ident.span(),
+                )));
+            }
+        }
+        if let syn::Meta::List(list) = &attr.meta {
+            self.report(check_attribute_tokens(list.tokens.clone()));
+        }
+        syn::visit::visit_attribute(self, attr);
+    }
+
+    fn visit_item_foreign_mod(&mut self, item: &'ast syn::ItemForeignMod) {
+        self.report(Some(Violation::new(
+            "found extern block".to_string(),
+            item.abi.extern_token.span,
+        )));
+        syn::visit::visit_item_foreign_mod(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        if item.content.is_none() {
+            self.report(Some(Violation::new(
+                format!("found out-of-line module \"{}\"", item.ident),
+                item.mod_token.span,
+            )));
+        }
+        syn::visit::visit_item_mod(self, item);
+    }
+}
+
+/// Checks a token stream for forbidden macros, attributes and extern blocks.
+fn check_tokens(tokens: TokenStream) -> Option<Violation> {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1);
+        match token {
+            TokenTree::Ident(ident) => {
+                let name = ident.unraw().to_string();
+                // Invoked, imported, renamed or part of a path.
+                let referenced = matches!(next, Some(TokenTree::Punct(p)) if p.as_char() == '!')
+                    || matches!(next, Some(TokenTree::Ident(next)) if next == "as")
+                    || is_path_separator(&tokens[i + 1..])
+                    || (i >= 2 && is_path_separator(&tokens[i - 2..i]))
+                    || matches!(i.checked_sub(1).map(|j| &tokens[j]), Some(TokenTree::Ident(prev)) if prev == "use");
+                if FORBIDDEN_MACROS.contains(&name.as_str()) && referenced {
+                    return Some(Violation::new(format!("found \"{name}\""), ident.span()));
+                }
+                if name == "extern" {
+                    let block = match next {
+                        Some(TokenTree::Literal(_)) => tokens.get(i + 2),
+                        other => other,
+                    };
+                    if matches!(block, Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace)
+                    {
+                        return Some(Violation::new(
+                            "found extern block".to_string(),
+                            ident.span(),
+                        ));
+                    }
+                }
+            }
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                let attr = match next {
+                    Some(TokenTree::Punct(p)) if p.as_char() == '!' => tokens.get(i + 2),
+                    other => other,
+                };
+                if let Some(TokenTree::Group(g)) = attr {
+                    if g.delimiter() == Delimiter::Bracket {
+                        if let Some(violation) = check_attribute_tokens(g.stream()) {
+                            return Some(violation);
+                        }
+                    }
+                }
+            }
+            TokenTree::Group(g) => {
+                if let Some(violation) = check_tokens(g.stream()) {
+                    return Some(violation);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether the tokens start with `::`.
+fn is_path_separator(tokens: &[TokenTree]) -> bool {
+    match tokens {
+        [TokenTree::Punct(a), TokenTree::Punct(b), ..] => {
+            a.as_char() == ':' && a.spacing() == Spacing::Joint && b.as_char() == ':'
+        }
+        _ => false,
+    }
+}
+
+/// Checks the contents of an attribute, including nested attributes like `cfg_attr`.
+fn check_attribute_tokens(tokens: TokenStream) -> Option<Violation> {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                let name = ident.unraw().to_string();
+                if FORBIDDEN_ATTRIBUTES.contains(&name.as_str()) {
+                    return Some(Violation::new(
+                        format!("found attribute \"{name}\""),
+                        ident.span(),
+                    ));
+                }
+            }
+            TokenTree::Group(g) => {
+                if let Some(violation) = check_attribute_tokens(g.stream()) {
+                    return Some(violation);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
 }
 
 #[cfg(test)]
@@ -38,4 +319,52 @@ mod tests {
         assert!(check("... #[\npath = \"/dev/random\"] ...").is_err());
         assert!(check("... #[\t  path\n= \"/dev/random\"] ...").is_err());
     }
+
+    #[test]
+    fn test_allowed() {
+        assert!(check("fn f() -> &'static str { \"include!(\\\"x\\\")\" }").is_ok());
+        assert!(check("// macro_rules! foo\nfn include() {}").is_ok());
+        assert!(check("#[derive(Clone)] struct Path { path: u32 }").is_ok());
+        assert!(check("mod foo { fn f() {} }").is_ok());
+        assert!(check("fn f() { let v = vec![1, 2]; }").is_ok());
+    }
+
+    #[test]
+    fn test_forbidden() {
+        assert!(check("const S: &str = include_str ! (\"/etc/passwd\");").is_err());
+        assert!(check("const S: &str = std::include_str!(\"/etc/passwd\");").is_err());
+        assert!(check("fn f() { let v = vec![env!(\"HOME\")]; }").is_err());
+        assert!(check("#[cfg_attr(all(), path = \"/dev/random\")] mod foo;").is_err());
+        assert!(check("mod foo;").is_err());
+        assert!(check("extern \"C\" { fn f(); }").is_err());
+        assert!(check("#[link(wasm_import_module = \"env\")] extern {}").is_err());
+        assert!(check("#[no_mangle] pub fn tick() {}").is_err());
+        assert!(check("fn f() { #![no_mangle] }").is_err());
+    }
+
+    #[test]
+    fn test_imports() {
+        assert!(check("use std::include_str as x; const S: &str = x!(\"/etc/passwd\");").is_err());
+        assert!(check("use std::{include as x}; fn f() {}").is_err());
+        assert!(check("use core::include_bytes; fn f() {}").is_err());
+        assert!(check("use std::*; fn f() {}").is_err());
+        assert!(check("use ::core::*; fn f() {}").is_err());
+        assert!(check("use std as s; use s::*; fn f() {}").is_err());
+        assert!(check("use std::prelude::v1::*; fn f() {}").is_err());
+        assert!(check("const S: &str = <std::include_str>;").is_err());
+        assert!(check("fn f() { g(std::env); }").is_err());
+        // Not valid Rust, so only the tokens are checked.
+        assert!(check("use std::include_str as x; fn").is_err());
+        assert!(check("use std::f64::consts::*; use oort_api::prelude::*;").is_ok());
+        assert!(check("fn f(env: u32) -> u32 { let v = vec![S { env }]; env }").is_ok());
+        assert!(check("fn f() { let v = vec![S { env: 1 }]; }").is_ok());
+    }
+
+    #[test]
+    fn test_location() {
+        let err = check("fn f() {}\n\nconst S: &str =\n    include_str!(\"x\");")
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("at line 4, column 5"), "{err}");
+    }
 }
@@ -0,0 +1,163 @@
+// Builds a compact symbol table from the DWARF debug info in a wasm module.
+// The table maps code addresses and functions to lines of user code and is
+// stored in the "oort_symbols" custom section, while the (much larger) DWARF
+// sections are removed.
+use anyhow::Result;
+use gimli::{AttributeValue, EndianSlice, LittleEndian};
+use oort_symbols::{custom_section, normalize_name, parse_sections, Section, Table};
+
+pub use oort_symbols::SECTION_NAME;
+
+type R<'a> = EndianSlice<'a, LittleEndian>;
+
+const USER_FILE: &str = "user.rs";
+
+/// Replaces DWARF debug info with a symbol table for user code.
+pub fn process(wasm: &[u8]) -> Result<Vec<u8>> {
+    let sections = parse_sections(wasm)?;
+    let table = extract_table(&sections)?;
+
+    let mut result = strip(wasm)?;
+    result.extend_from_slice(&custom_section(SECTION_NAME, table.encode().as_bytes()));
+    Ok(result)
+}
+
+/// Removes debug info and symbols added by [`process`].
+pub fn strip(wasm: &[u8]) -> Result<Vec<u8>> {
+    let sections = parse_sections(wasm)?;
+    let mut result = wasm[0..8].to_vec();
+    for section in sections.iter() {
+        match section.name {
+            Some(name) if name.starts_with(".debug") || name == SECTION_NAME => {}
+            _ => result.extend_from_slice(section.raw),
+        }
+    }
+    Ok(result)
+}
+
+fn extract_table(sections: &[Section]) -> Result<Table> {
+    let load = |id: gimli::SectionId| -> Result<R, gimli::Error> {
+        let data = sections
+            .iter()
+            .find(|s| s.name == Some(id.name()))
+            .map(|s| s.data)
+            .unwrap_or(&[]);
+        Ok(EndianSlice::new(data, LittleEndian))
+    };
+    let dwarf = gimli::Dwarf::load(load)?;
+
+    let is_user_file = |unit: &gimli::Unit<R>, file: &gimli::FileEntry<R>| -> Result<bool> {
+        let path = dwarf.attr_string(unit, file.path_name())?;
+        Ok(path.to_string_lossy().ends_with(USER_FILE))
+    };
+
+    let mut lines = vec![];
+    let mut rows = vec![];
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+
+        if let Some(program) = unit.line_program.clone() {
+            let mut program_rows = program.rows();
+            while let Some((header, row)) = program_rows.next_row()? {
+                let mut line = 0;
+                if let (false, Some(file), Some(row_line)) =
+                    (row.end_sequence(), row.file(header), row.line())
+                {
+                    if is_user_file(&unit, file)? {
+                        line = row_line.get() as u32;
+                    }
+                }
+                rows.push((row.address() as u32, line));
+            }
+        }
+
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+
+            // Definitions may refer to a separate declaration for their name and location.
+            let declaration = match entry.attr_value(gimli::DW_AT_specification)? {
+                Some(AttributeValue::UnitRef(offset)) => Some(unit.entry(offset)?),
+                _ => None,
+            };
+            let attr = |name| -> Result<Option<AttributeValue<R>>> {
+                if let Some(value) = entry.attr_value(name)? {
+                    return Ok(Some(value));
+                }
+                match declaration.as_ref() {
+                    Some(declaration) => Ok(declaration.attr_value(name)?),
+                    None => Ok(None),
+                }
+            };
+
+            let Some(line) = attr(gimli::DW_AT_decl_line)?.and_then(|x| x.udata_value()) else {
+                continue;
+            };
+            let Some(AttributeValue::FileIndex(file_index)) = attr(gimli::DW_AT_decl_file)? else {
+                continue;
+            };
+            let Some(file) = unit
+                .line_program
+                .as_ref()
+                .and_then(|x| x.header().file(file_index))
+            else {
+                continue;
+            };
+            if !is_user_file(&unit, file)? {
+                continue;
+            }
+            let Some(name) = attr(gimli::DW_AT_linkage_name)?.or(attr(gimli::DW_AT_name)?) else {
+                continue;
+            };
+            let name = dwarf.attr_string(&unit, name)?;
+            lines.push((line as u32, normalize_name(&name.to_string_lossy())));
+        }
+    }
+
+    let mut table = Table::default();
+    lines.sort();
+    for (line, name) in lines {
+        table.functions.entry(name).or_insert(line);
+    }
+
+    // Where sequences meet, prefer the start of the next one over the end of
+    // the previous one. Then only keep rows where the line changes.
+    rows.sort_by_key(|&(address, line)| (address, line != 0));
+    rows.dedup_by(|next, prev| {
+        if next.0 == prev.0 {
+            *prev = *next;
+            true
+        } else {
+            false
+        }
+    });
+    rows.dedup_by_key(|&mut (_, line)| line);
+    table.rows = rows;
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_section() {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend_from_slice(&custom_section(".debug_str", &[1, 2, 3]));
+        wasm.extend_from_slice(&custom_section("name", &[4, 5]));
+
+        let processed = process(&wasm).unwrap();
+        let sections = parse_sections(&processed).unwrap();
+        let names: Vec<_> = sections.iter().map(|s| s.name.unwrap()).collect();
+        assert_eq!(names, vec!["name", SECTION_NAME]);
+        assert_eq!(sections[0].data, &[4, 5]);
+
+        let stripped = strip(&processed).unwrap();
+        let sections = parse_sections(&stripped).unwrap();
+        let names: Vec<_> = sections.iter().map(|s| s.name.unwrap()).collect();
+        assert_eq!(names, vec!["name"]);
+    }
+}
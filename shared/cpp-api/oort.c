@@ -0,0 +1,29 @@
+#include "oort.h"
+
+uint64_t SYSTEM_STATE[128];
+uint8_t ENVIRONMENT[1024];
+uint8_t PANIC_BUFFER[1024];
+
+int main(void) {
+    return 0;
+}
+
+__attribute__((export_name("abi_version")))
+uint32_t sys_abi_version(void) {
+    return OORT_ABI_VERSION;
+}
+
+void tick(void);
+
+// Optional, called once per ship before the first tick.
+__attribute__((weak)) void init(void) {}
+
+__attribute__((export_name("init")))
+void sys_init(void) {
+    init();
+}
+
+__attribute__((export_name("tick")))
+void sys_tick(void) {
+    tick();
+}
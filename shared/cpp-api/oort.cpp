@@ -15,8 +15,21 @@ int main() {
     return 0;
 }
 
+__attribute__((export_name("abi_version")))
+uint32_t sys_abi_version() {
+    return OORT_ABI_VERSION;
+}
+
 void tick();
 
+// Optional, called once per ship before the first tick.
+__attribute__((weak)) void init() {}
+
+__attribute__((export_name("init")))
+void sys_init() {
+    init();
+}
+
 __attribute__((export_name("tick")))
 void sys_tick() {
     tick();
@@ -1,15 +1,43 @@
 #ifndef OORT_H
 #define OORT_H
 
+// API for C and C++ AIs.
+
+#ifdef __cplusplus
 #include <cstdint>
 #include <cstring>
 #include <cstdlib>
+#else
+#include <stdint.h>
+#include <string.h>
+#include <stdlib.h>
+#endif
 
+#ifdef __cplusplus
 extern "C" {
-    extern uint64_t SYSTEM_STATE[128];
-    extern uint8_t ENVIRONMENT[1024];
-    extern uint8_t PANIC_BUFFER[1024];
+#endif
+
+extern uint64_t SYSTEM_STATE[128];
+extern uint8_t ENVIRONMENT[1024];
+extern uint8_t PANIC_BUFFER[1024];
+
+// Math functions computed by the simulator. These give identical results on
+// every platform and cost a small fixed number of instructions per call.
+#define OORT_MATH_IMPORT(name) __attribute__((import_module("oort_math"), import_name(#name)))
+
+OORT_MATH_IMPORT(sin) double oort_sin(double x);
+OORT_MATH_IMPORT(cos) double oort_cos(double x);
+OORT_MATH_IMPORT(atan2) double oort_atan2(double y, double x);
+OORT_MATH_IMPORT(sqrt) double oort_sqrt(double x);
+OORT_MATH_IMPORT(exp) double oort_exp(double x);
+OORT_MATH_IMPORT(ln) double oort_ln(double x);
+
+#ifdef __cplusplus
 }
+#endif
+
+// BEGIN GENERATED from oort_api by oort_compiler::bindings. Do not edit.
+#define OORT_ABI_VERSION 3
 
 enum SystemState {
     Class,
@@ -64,7 +92,7 @@ enum SystemState {
 
     ActivateAbility,
 
-    Radio0Channel, // TODO collapse into command word
+    Radio0Channel,
     Radio0Send,
     Radio0Receive,
     Radio0Data0,
@@ -128,7 +156,6 @@ enum SystemState {
     Radio7Data2,
     Radio7Data3,
 
-    // TODO not part of interface
     SelectedRadio,
 
     DrawnTextPointer,
@@ -149,28 +176,37 @@ enum SystemState {
 
     Id,
 
+    DebugValuesPointer,
+    DebugValuesLength,
+
+    DebugShapesPointer,
+    DebugShapesLength,
+    DebugLayersPointer,
+    DebugLayersLength,
+
     Size,
     MaxSize = 128,
 };
+// END GENERATED
 
-inline uint64_t read_u64(enum SystemState key) {
+static inline uint64_t read_u64(enum SystemState key) {
     return SYSTEM_STATE[key];
 }
 
-inline double read_f64(enum SystemState key) {
+static inline double read_f64(enum SystemState key) {
     uint64_t u64_value = read_u64(key);
     double f64_value;
-    std::memcpy(&f64_value, &u64_value, sizeof(f64_value));
+    memcpy(&f64_value, &u64_value, sizeof(f64_value));
     return f64_value;
 }
 
-inline void write_u64(enum SystemState key, uint64_t value) {
+static inline void write_u64(enum SystemState key, uint64_t value) {
     SYSTEM_STATE[key] = value;
 }
 
-inline void write_f64(enum SystemState key, double value) {
+static inline void write_f64(enum SystemState key, double value) {
     uint64_t u64_value;
-    std::memcpy(&u64_value, &value, sizeof(u64_value));
+    memcpy(&u64_value, &value, sizeof(u64_value));
     write_u64(key, u64_value);
 }
 
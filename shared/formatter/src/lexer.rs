@@ -0,0 +1,232 @@
+// Splits Rust source into tokens, keeping comments and newlines.
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Ident,
+    Lifetime,
+    Literal,
+    Punct,
+    Open(char),
+    Close(char),
+    LineComment,
+    BlockComment,
+    Newline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: Kind,
+    pub text: &'a str,
+    /// Whether there was whitespace between this token and the previous one.
+    pub space_before: bool,
+}
+
+const PUNCTS: &[&str] = &[
+    ">>=", "<<=", "...", "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..",
+];
+
+pub fn lex(src: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = vec![];
+    let mut i = 0;
+    let mut space_before = false;
+    while i < src.len() {
+        let rest = &src[i..];
+        let c = rest.chars().next().unwrap();
+        if c == '\n' {
+            tokens.push(Token {
+                kind: Kind::Newline,
+                text: &rest[..1],
+                space_before,
+            });
+            space_before = false;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            space_before = true;
+            i += c.len_utf8();
+            continue;
+        }
+
+        let (kind, len) = if rest.starts_with("//") {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            (Kind::LineComment, rest[..len].trim_end().len())
+        } else if rest.starts_with("/*") {
+            (Kind::BlockComment, block_comment_len(rest)?)
+        } else if let Some(len) = raw_string_len(rest)? {
+            (Kind::Literal, len)
+        } else if rest.starts_with('"') || rest.starts_with("b\"") {
+            (Kind::Literal, string_len(rest)?)
+        } else if rest.starts_with("b'") {
+            (Kind::Literal, 1 + char_len(&rest[1..]).unwrap_or(0))
+        } else if c == '\'' {
+            match char_len(rest) {
+                Some(len) => (Kind::Literal, len),
+                None => (Kind::Lifetime, 1 + ident_len(&rest[1..])),
+            }
+        } else if c.is_ascii_digit() {
+            (Kind::Literal, number_len(rest))
+        } else if c == '_' || c.is_alphabetic() {
+            if let Some(raw) = rest.strip_prefix("r#") {
+                (Kind::Ident, 2 + ident_len(raw))
+            } else {
+                (Kind::Ident, ident_len(rest))
+            }
+        } else if let Some(p) = PUNCTS.iter().find(|p| rest.starts_with(**p)) {
+            (Kind::Punct, p.len())
+        } else {
+            let kind = match c {
+                '(' | '[' | '{' => Kind::Open(c),
+                ')' | ']' | '}' => Kind::Close(c),
+                _ => Kind::Punct,
+            };
+            (kind, c.len_utf8())
+        };
+        tokens.push(Token {
+            kind,
+            text: &rest[..len],
+            space_before,
+        });
+        space_before = false;
+        i += len;
+    }
+    Ok(tokens)
+}
+
+fn ident_len(s: &str) -> usize {
+    s.char_indices()
+        .find(|(_, c)| !(*c == '_' || c.is_alphanumeric()))
+        .map(|(i, _)| i)
+        .unwrap_or(s.len())
+}
+
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let hex = s.starts_with("0x") || s.starts_with("0b") || s.starts_with("0o");
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_alphanumeric() || c == b'_' {
+            if !hex && (c == b'e' || c == b'E') && matches!(bytes.get(i + 1), Some(b'+' | b'-')) {
+                i += 1;
+            }
+            i += 1;
+        } else if c == b'.'
+            && !hex
+            && bytes
+                .get(i + 1)
+                .map(|c| c.is_ascii_digit())
+                .unwrap_or(false)
+        {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn block_comment_len(s: &str) -> Result<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < s.len() {
+        if s[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if s[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Ok(i);
+            }
+        } else {
+            i += s[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    bail!("Unterminated block comment")
+}
+
+fn string_len(s: &str) -> Result<usize> {
+    let start = s.find('"').unwrap() + 1;
+    let mut escaped = false;
+    for (i, c) in s[start..].char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            return Ok(start + i + 1);
+        }
+    }
+    bail!("Unterminated string")
+}
+
+fn raw_string_len(s: &str) -> Result<Option<usize>> {
+    let prefix = if s.starts_with("br") {
+        2
+    } else if s.starts_with('r') {
+        1
+    } else {
+        return Ok(None);
+    };
+    let hashes = s[prefix..].chars().take_while(|c| *c == '#').count();
+    if !s[prefix + hashes..].starts_with('"') {
+        return Ok(None);
+    }
+    let start = prefix + hashes + 1;
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    match s[start..].find(&terminator) {
+        Some(i) => Ok(Some(start + i + terminator.len())),
+        None => bail!("Unterminated raw string"),
+    }
+}
+
+/// Returns the length of a character literal, or None if this is a lifetime.
+fn char_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\\') => {
+            // Skip the escaped character, which may be a quote.
+            let end = s.get(3..)?.find('\'')?;
+            Some(3 + end + 1)
+        }
+        (_, c) => match chars.next() {
+            Some((i, '\'')) if c != '\'' => Some(i + 1),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(src: &str) -> Vec<&str> {
+        lex(src).unwrap().iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn test_lex() {
+        assert_eq!(
+            texts("fn f<'a>(x: &'a str) -> char { 'x' }"),
+            vec![
+                "fn", "f", "<", "'a", ">", "(", "x", ":", "&", "'a", "str", ")", "->", "char", "{",
+                "'x'", "}"
+            ]
+        );
+        assert_eq!(texts("a..=1.5e-3f64"), vec!["a", "..=", "1.5e-3f64"]);
+        assert_eq!(texts("x.0.1"), vec!["x", ".", "0.1"]);
+        assert_eq!(texts("0..n"), vec!["0", "..", "n"]);
+        assert_eq!(
+            texts("r#\"a\"b\"# b\"c\" '\\n' b'\\''"),
+            vec!["r#\"a\"b\"#", "b\"c\"", "'\\n'", "b'\\''"]
+        );
+        assert_eq!(
+            texts("/* a /* b */ */ // c  \nd"),
+            vec!["/* a /* b */ */", "// c", "\n", "d"]
+        );
+        assert!(lex("\"abc").is_err());
+    }
+}
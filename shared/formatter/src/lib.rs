@@ -0,0 +1,514 @@
+//! Formats Rust code without rustfmt, so that it can run in the browser.
+//!
+//! Line breaks are kept as written. The formatter re-indents lines, removes
+//! redundant whitespace and blank lines, and spaces tokens following rustfmt's
+//! rules for operators, delimiters, generics and closures. It doesn't split or
+//! join lines, so one-line items that rustfmt would break up stay on one line.
+//! Spacing is decided one token at a time, so unusual code like comparisons
+//! against single-letter constants may come out differently from rustfmt.
+//!
+//! Returns an error for code it can't tokenize, such as unbalanced
+//! delimiters or unterminated strings.
+use anyhow::{bail, Result};
+use lexer::{Kind, Token};
+
+mod lexer;
+
+const INDENT: &str = "    ";
+
+/// An open delimiter.
+#[derive(Default)]
+struct Context {
+    close: char,
+    /// Indentation of the line containing the open delimiter.
+    indent: usize,
+    /// Whether the last line inside this delimiter ended a statement, item,
+    /// field or argument. Otherwise the next line is a continuation.
+    terminated: bool,
+    /// Indentation of the last line in the current statement that didn't
+    /// start with a method call. A method chain is indented relative to it.
+    receiver_indent: usize,
+    /// Indentation of the current method chain.
+    chain_indent: Option<usize>,
+    /// Precedence and indentation of the continuations in the current
+    /// statement that start with a binary operator, in increasing precedence.
+    operators: Vec<(u8, usize)>,
+    /// Whether we're inside a where clause.
+    in_where: bool,
+}
+
+pub fn format(code: &str) -> Result<String> {
+    let code = code.replace("\r\n", "\n");
+    let tokens = lexer::lex(&code)?;
+    let lines: Vec<&[Token]> = tokens.split(|t| t.kind == Kind::Newline).collect();
+
+    let mut output = String::new();
+    let mut stack = vec![Context {
+        close: ' ',
+        terminated: true,
+        ..Default::default()
+    }];
+    let mut pending_blank = false;
+    let mut after_open = true;
+    // Whether the last line ended with an operand, making an operator at the
+    // start of the next line binary.
+    let mut value_end = false;
+
+    // Block comments and string literals may span lines, and are kept as is.
+    for line in lines {
+        let Some(first) = line.first() else {
+            pending_blank = true;
+            continue;
+        };
+
+        let starts_with_close = matches!(first.kind, Kind::Close(_));
+        if pending_blank && !after_open && !starts_with_close {
+            output.push('\n');
+        }
+        pending_blank = false;
+
+        let root = stack.len() == 1;
+        let top = stack.last_mut().unwrap();
+        let mut base_indent = if root { 0 } else { top.indent + 1 };
+        if top.in_where && !matches!(first.text, "where" | "{") {
+            base_indent += 1;
+        }
+        let continuation = !(top.terminated
+            || starts_with_close
+            || first.kind == Kind::Open('{')
+            || matches!(first.text, "where" | "else"));
+        let precedence = precedence(first);
+        let indent = if starts_with_close {
+            top.indent
+        } else if !continuation {
+            base_indent
+        } else if is_comment(first) || first.text == "." {
+            top.chain_indent.unwrap_or(top.receiver_indent + 1)
+        } else {
+            // An operator lines up with the first operator in the statement
+            // that binds at least as tightly.
+            precedence
+                .and_then(|p| top.operators.iter().find(|(q, _)| *q >= p))
+                .map(|(_, indent)| *indent)
+                .unwrap_or(top.receiver_indent + 1)
+        };
+        for _ in 0..indent {
+            output.push_str(INDENT);
+        }
+
+        // Track the structure of the statement this line starts in. A line
+        // starting with a closing delimiter resumes the line that opened it.
+        if !starts_with_close && !is_comment(first) {
+            if first.text == "." {
+                top.chain_indent = Some(indent);
+            } else {
+                if !continuation {
+                    top.operators.clear();
+                    top.in_where = first.text == "where" || (top.in_where && first.text != "{");
+                }
+                top.receiver_indent = indent;
+                top.chain_indent = None;
+                if let Some(p) = precedence {
+                    top.operators.retain(|(q, _)| *q < p);
+                    top.operators.push((p, indent));
+                }
+            }
+        }
+
+        let depth = stack.len();
+        value_end = format_tokens(&mut output, line, &mut stack, continuation && value_end)?;
+        output.push('\n');
+
+        let code_tokens: Vec<_> = line.iter().filter(|t| !is_comment(t)).collect();
+        let opened = stack.len() > depth
+            || code_tokens
+                .last()
+                .map(|t| matches!(t.kind, Kind::Open(_)))
+                .unwrap_or(false);
+        after_open = opened;
+        if let Some(last) = code_tokens.last() {
+            if !opened {
+                let is_attribute = code_tokens[0].text == "#" && last.text == "]";
+                let top = stack.last_mut().unwrap();
+                top.terminated = is_attribute || matches!(last.text, ";" | "," | "}");
+                if last.text == ";" {
+                    top.in_where = false;
+                }
+                // A method call on a multi-line expression lines up with its
+                // closing delimiter.
+                if code_tokens.iter().all(|t| matches!(t.kind, Kind::Close(_))) {
+                    top.chain_indent = Some(indent);
+                }
+            }
+        }
+    }
+
+    if stack.len() > 1 {
+        bail!("Unclosed delimiter {:?}", stack.last().unwrap().close);
+    }
+
+    let output = output.trim_start_matches('\n').trim_end();
+    if output.is_empty() {
+        Ok(String::new())
+    } else {
+        Ok(format!("{output}\n"))
+    }
+}
+
+fn is_comment(token: &Token) -> bool {
+    matches!(token.kind, Kind::LineComment | Kind::BlockComment)
+}
+
+/// Returns the precedence of a binary operator at the start of a line.
+fn precedence(token: &Token) -> Option<u8> {
+    if token.kind != Kind::Punct {
+        return None;
+    }
+    Some(match token.text {
+        "||" => 1,
+        "&&" => 2,
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => 3,
+        "|" => 4,
+        "^" => 5,
+        "&" => 6,
+        "<<" | ">>" => 7,
+        "+" | "-" => 8,
+        "*" | "/" | "%" => 9,
+        _ => return None,
+    })
+}
+
+/// Appends a line of tokens to `output`, updating the delimiter stack.
+/// `value_end` is whether the line continues an expression after an operand.
+/// Returns whether the line ends with an operand.
+fn format_tokens(
+    output: &mut String,
+    line: &[Token],
+    stack: &mut Vec<Context>,
+    value_end: bool,
+) -> Result<bool> {
+    let (roles, value_end) = roles(line, value_end);
+    let mut line_indent = None;
+    for (i, token) in line.iter().enumerate() {
+        if i > 0 && space_between((&line[i - 1], roles[i - 1]), (token, roles[i])) {
+            output.push(' ');
+        }
+        output.push_str(token.text);
+        match token.kind {
+            Kind::Open(c) => {
+                // Indent relative to the line containing the delimiter.
+                let indent = *line_indent.get_or_insert_with(|| current_indent(output));
+                stack.push(Context {
+                    close: matching_close(c),
+                    indent,
+                    terminated: true,
+                    receiver_indent: indent,
+                    ..Default::default()
+                });
+            }
+            Kind::Close(c) => {
+                if stack.len() < 2 || stack.last().unwrap().close != c {
+                    bail!("Unbalanced delimiter {:?}", c);
+                }
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(value_end)
+}
+
+/// Returns the indentation level of the last line in `output`.
+fn current_indent(output: &str) -> usize {
+    let line = &output[output.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+    (line.len() - line.trim_start_matches(' ').len()) / INDENT.len()
+}
+
+fn matching_close(c: char) -> char {
+    match c {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+/// What a token does, which decides the spaces around it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    /// Identifiers, keywords, literals and lifetimes.
+    Word,
+    /// Binary and assignment operators, `=>` and `->`.
+    Binary,
+    /// Prefix `&`, `&&`, `*`, `-` and `!`.
+    Unary,
+    /// `.`, `::` and ranges.
+    Joiner,
+    /// `,` and `;`.
+    Separator,
+    Colon,
+    Question,
+    /// The `!` of a macro invocation.
+    MacroBang,
+    /// The `#` and `!` starting an attribute.
+    Hash,
+    GenericOpen,
+    GenericClose,
+    ClosureOpen,
+    ClosureClose,
+    /// `||` starting a closure without parameters.
+    EmptyClosure,
+    /// Parentheses, brackets and the braces of a `use` group, which have no
+    /// spaces inside.
+    Open,
+    Close,
+    /// Other braces, which have spaces inside unless empty.
+    OpenBrace,
+    CloseBrace,
+    LineComment,
+    /// Block comments and unknown punctuation, which keep the spacing they
+    /// were written with.
+    Other,
+}
+
+/// Keywords that can't end an operand.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "break", "const", "continue", "dyn", "else", "enum", "extern", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "static", "struct", "trait", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// Keywords followed by a space before a parenthesis or bracket.
+const SPACED_KEYWORDS: &[&str] = &[
+    "as", "break", "else", "for", "if", "in", "let", "match", "move", "mut", "ref", "return",
+    "where", "while", "yield",
+];
+
+/// Keywords introducing an item that may have generic parameters.
+const ITEM_KEYWORDS: &[&str] = &["fn", "struct", "enum", "trait", "type", "union"];
+
+const BINARY_OPERATORS: &[&str] = &[
+    "=", "==", "!=", "<", ">", "<=", ">=", "&&", "||", "&", "|", "^", "+", "-", "*", "/", "%",
+    "<<", ">>", "+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=", "<<=", ">>=", "=>", "->", "@",
+];
+
+fn is_keyword(token: &Token) -> bool {
+    token.kind == Kind::Ident && KEYWORDS.contains(&token.text)
+}
+
+/// Whether a name looks like a type, like `Vec` or `T` but not `MAX`.
+fn is_type_name(token: &Token) -> bool {
+    let mut chars = token.text.chars();
+    token.kind == Kind::Ident
+        && chars.next().is_some_and(|c| c.is_uppercase())
+        && (token.text.len() == 1 || chars.any(|c| c.is_lowercase()))
+}
+
+/// Returns the role of each token in a line, and whether the line ends with
+/// an operand. `value_end` is whether the line starts after an operand.
+fn roles(line: &[Token], mut value_end: bool) -> (Vec<Role>, bool) {
+    let mut roles = Vec::with_capacity(line.len());
+    let mut generics = 0;
+    let mut in_closure_params = false;
+    // Whether each open brace starts a `use` group.
+    let mut braces = vec![];
+    let mut prev: Option<&Token> = None;
+    let mut prev2: Option<&Token> = None;
+    for token in line {
+        let role = match token.kind {
+            Kind::LineComment => Role::LineComment,
+            Kind::BlockComment => Role::Other,
+            Kind::Ident | Kind::Literal | Kind::Lifetime => Role::Word,
+            Kind::Open('{') => {
+                let group = prev.is_some_and(|p| p.text == "::");
+                braces.push(group);
+                if group {
+                    Role::Open
+                } else {
+                    Role::OpenBrace
+                }
+            }
+            Kind::Close('}') => {
+                if braces.pop().unwrap_or(false) {
+                    Role::Close
+                } else {
+                    Role::CloseBrace
+                }
+            }
+            Kind::Open(_) => Role::Open,
+            Kind::Close(_) => Role::Close,
+            Kind::Punct | Kind::Newline => match token.text {
+                "," | ";" => Role::Separator,
+                ":" => Role::Colon,
+                "?" => Role::Question,
+                "." | "::" | ".." | "..=" | "..." => Role::Joiner,
+                "#" => Role::Hash,
+                "!" if prev.is_some_and(|p| p.text == "#") => Role::Hash,
+                "!" if value_end && prev.is_some_and(|p| p.kind == Kind::Ident) => Role::MacroBang,
+                "&" | "&&" | "*" | "-" | "!" if !value_end => Role::Unary,
+                "||" if !value_end => Role::EmptyClosure,
+                "|" if in_closure_params => {
+                    in_closure_params = false;
+                    Role::ClosureClose
+                }
+                "|" if !value_end => {
+                    in_closure_params = true;
+                    Role::ClosureOpen
+                }
+                "<" if !value_end
+                    || prev.is_some_and(|p| p.text == "::" || is_type_name(p))
+                    || prev2.is_some_and(|p| ITEM_KEYWORDS.contains(&p.text)) =>
+                {
+                    generics += 1;
+                    Role::GenericOpen
+                }
+                ">" if generics > 0 => {
+                    generics -= 1;
+                    Role::GenericClose
+                }
+                ">>" if generics > 1 => {
+                    generics -= 2;
+                    Role::GenericClose
+                }
+                text if BINARY_OPERATORS.contains(&text) => Role::Binary,
+                _ => Role::Other,
+            },
+        };
+        roles.push(role);
+        if !matches!(role, Role::LineComment | Role::Other) {
+            value_end = match role {
+                Role::Word => !is_keyword(token),
+                Role::Close | Role::CloseBrace | Role::GenericClose | Role::Question => true,
+                _ => false,
+            };
+            prev2 = prev;
+            prev = Some(token);
+        }
+    }
+    (roles, value_end)
+}
+
+/// Whether to put a space between two tokens on the same line.
+fn space_between((a, ra): (&Token, Role), (b, rb): (&Token, Role)) -> bool {
+    use Role::*;
+    match (ra, rb) {
+        (_, LineComment) => true,
+        (Other, _) | (_, Other) => b.space_before,
+        (Open | OpenBrace, Close | CloseBrace) => false,
+        (_, CloseBrace) => true,
+        (_, Separator | Colon | Question | Close | MacroBang | ClosureClose | GenericClose) => {
+            false
+        }
+        (Separator | Colon | OpenBrace, _) => true,
+        (Open, _) => false,
+        (Binary, _) | (_, Binary) => true,
+        (MacroBang, OpenBrace) => true,
+        (Unary | Hash | MacroBang | ClosureOpen | GenericOpen | Joiner, _) => false,
+        (_, Joiner | GenericOpen) => false,
+        (_, Open) => ra == Word && SPACED_KEYWORDS.contains(&a.text),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+
+    #[test]
+    fn test_rustfmt_output_unchanged() {
+        // These files are formatted with rustfmt.
+        for src in [
+            include_str!("../testdata/rustfmt.rs"),
+            include_str!("lexer.rs"),
+            include_str!("lib.rs"),
+        ] {
+            let formatted = format(src).unwrap();
+            for (i, (a, b)) in src.lines().zip(formatted.lines()).enumerate() {
+                assert_eq!(a, b, "line {}", i + 1);
+            }
+            assert_eq!(formatted, src);
+        }
+    }
+
+    #[test]
+    fn test_indentation() {
+        assert_eq!(
+            format("fn f() {\nif x {\n\tfoo(\n1,\n  2,\n);\n}\n}").unwrap(),
+            "fn f() {\n    if x {\n        foo(\n            1,\n            2,\n        );\n    }\n}\n"
+        );
+        assert_eq!(
+            format("fn f() {\nlet x = a\n.b()\n.c(|x| {\nx\n})\n.d();\n}").unwrap(),
+            "fn f() {\n    let x = a\n        .b()\n        .c(|x| {\n            x\n        })\n        .d();\n}\n"
+        );
+        assert_eq!(
+            format("fn f<T>()\nwhere\nT: X,\n{\nif a\n|| b\n.c()\n> 1\n|| d\n{}\n}").unwrap(),
+            "fn f<T>()\nwhere\n    T: X,\n{\n    if a\n        || b\n            .c()\n            > 1\n        || d\n    {}\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(
+            format("\n\nfn f( a: u32 ,b: u32 )   {   \n\n  let x = a .b ;\n\n\n\n  x\n\n}\n\n")
+                .unwrap(),
+            "fn f(a: u32, b: u32) {\n    let x = a.b;\n\n    x\n}\n"
+        );
+        assert_eq!(format("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_spacing() {
+        // Expected outputs are from rustfmt 1.6.0.
+        let cases = [
+            (
+                "fn f(){\nlet x=1+2;\n}\n",
+                "fn f() {\n    let x = 1 + 2;\n}\n",
+            ),
+            (
+                "fn f(x:u32)->u32{\nmatch x{\nA=>1,\n_ =>-1,\n}\n}\n",
+                "fn f(x: u32) -> u32 {\n    match x {\n        A => 1,\n        _ => -1,\n    }\n}\n",
+            ),
+            (
+                "fn f(){\nif x{\nfoo (1);\n}\nlet y=b [0];\n}\n",
+                "fn f() {\n    if x {\n        foo(1);\n    }\n    let y = b[0];\n}\n",
+            ),
+            (
+                "fn f(){\nlet c=& mut d;\nlet e=! f;\nlet g=- h*i;\nlet k=*p;\n}\n",
+                "fn f() {\n    let c = &mut d;\n    let e = !f;\n    let g = -h * i;\n    let k = *p;\n}\n",
+            ),
+            (
+                "fn f(){\nv.iter().map(|a| a+1);\nlet h=move ||x;\nlet o=a|b;\n}\n",
+                "fn f() {\n    v.iter().map(|a| a + 1);\n    let h = move || x;\n    let o = a | b;\n}\n",
+            ),
+            (
+                "struct S<T>{\na:Vec<Vec<T>>,\n}\n",
+                "struct S<T> {\n    a: Vec<Vec<T>>,\n}\n",
+            ),
+            (
+                "impl<T:Clone> S<T>{\nfn new()->Self{\nSelf{a:Vec::<T>::new()}\n}\n}\n",
+                "impl<T: Clone> S<T> {\n    fn new() -> Self {\n        Self { a: Vec::<T>::new() }\n    }\n}\n",
+            ),
+            (
+                "use std::collections::{HashMap,HashSet};\n#[derive(Clone)]\nfn f<'a>(x:&'a str)->Option<&'a str>{\nprintln!(\"{}\",x);\nlet v=vec![0;3];\nx.get(1..)?;\nif a<b&&c>=d{}\nNone\n}\n",
+                "use std::collections::{HashMap, HashSet};\n#[derive(Clone)]\nfn f<'a>(x: &'a str) -> Option<&'a str> {\n    println!(\"{}\", x);\n    let v = vec![0; 3];\n    x.get(1..)?;\n    if a < b && c >= d {}\n    None\n}\n",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(format(input).unwrap(), expected, "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_literals_unchanged() {
+        let src =
+            "const S: &str = \"a ,  b\n  c\";\nconst R: &str = r#\"\n  ( \"#;\n/*\n   x  (\n*/\n";
+        assert_eq!(format(src).unwrap(), src);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(format("fn f() {").is_err());
+        assert!(format("fn f() }").is_err());
+        assert!(format("fn f() { (] }").is_err());
+        assert!(format("const S: &str = \"abc;").is_err());
+    }
+}
@@ -0,0 +1,111 @@
+// Formatted with rustfmt. The formatter must leave this file unchanged.
+use oort_api::prelude::*;
+use std::collections::HashMap;
+
+const BULLET_SPEED: f64 = 1000.0; // m/s
+
+#[derive(Clone, Debug, Default)]
+pub struct Target {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    last_seen: f64,
+}
+
+pub enum State {
+    Search { heading: f64 },
+    Track(Target),
+    Idle,
+}
+
+pub struct Ship {
+    state: State,
+    targets: HashMap<u64, Target>,
+    ticks: u32,
+}
+
+impl Default for Ship {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship {
+            state: State::Search { heading: 0.0 },
+            targets: HashMap::new(),
+            ticks: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        match &mut self.state {
+            State::Search { heading } => {
+                *heading += 0.1;
+                set_radar_heading(*heading);
+                if let Some(contact) = scan() {
+                    self.state = State::Track(Target {
+                        position: contact.position,
+                        velocity: contact.velocity,
+                        last_seen: current_time(),
+                    });
+                }
+            }
+            State::Track(target) => {
+                let dp = target.position - position();
+                let dv = target.velocity - velocity();
+                let t = dp.length() / BULLET_SPEED;
+                let predicted = dp + dv * t;
+                turn_to(predicted.angle());
+                if angle_diff(heading(), predicted.angle()).abs() < 0.01 && reload_ticks(0) == 0 {
+                    fire(0);
+                }
+            }
+            State::Idle => {}
+        }
+    }
+
+    fn closest(&self) -> Option<&Target> {
+        self.targets
+            .values()
+            .filter(|t| current_time() - t.last_seen < 1.0)
+            .min_by(|a, b| {
+                let da = (a.position - position()).length();
+                let db = (b.position - position()).length();
+                da.partial_cmp(&db).unwrap()
+            })
+    }
+}
+
+fn turn_to(target_heading: f64) {
+    let error = angle_diff(heading(), target_heading);
+    torque(20.0 * error - 5.0 * angular_velocity());
+}
+
+pub fn lead<T>(targets: &[T], f: impl Fn(&T) -> Vec2) -> Vec<Vec2>
+where
+    T: Clone,
+{
+    let mut result = vec![];
+    for (i, target) in targets.iter().enumerate() {
+        if i % 2 == 0
+            || targets.len() > 10
+                && f(target).length() > 100.0
+                && f(target).angle() < std::f64::consts::PI
+        {
+            result.push(f(target));
+        }
+    }
+    result
+}
+
+/* Block comments
+are left alone. */
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_nothing() {
+        assert_eq!([1, 2, 3].iter().sum::<i32>(), 6);
+    }
+}
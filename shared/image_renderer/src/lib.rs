@@ -0,0 +1,434 @@
+//! Draws simulation snapshots to PNG or SVG without a GPU.
+//!
+//! This mirrors what the WebGL renderer in `frontend/renderer` shows, minus
+//! the effects: ships, bullets, trails, scenario lines and optionally debug
+//! drawings.
+use nalgebra::{point, vector, Point2, Rotation2, Vector4};
+use oort_simulator::color;
+use oort_simulator::debug::Geometry;
+use oort_simulator::model;
+use oort_simulator::ship::ShipClass;
+use oort_simulator::simulation::PHYSICS_TICK_LENGTH;
+use oort_simulator::snapshot::Snapshot;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+/// Number of positions kept per ship for trails.
+const TRAIL_LENGTH: usize = 120;
+const BACKGROUND: Vector4<f32> = Vector4::new(0.0, 0.0, 0.0, 1.0);
+const BOUNDARY_COLOR: Vector4<f32> = Vector4::new(0.3, 0.3, 0.3, 1.0);
+const FILL_ALPHA: f32 = 0.25;
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub width: u32,
+    pub height: u32,
+    /// Draw debug lines and shapes from all ships, like debug mode in
+    /// the UI.
+    pub debug: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            debug: false,
+        }
+    }
+}
+
+/// The area of the world shown.
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub center: Point2<f64>,
+    /// Width of the image in meters.
+    pub width: f64,
+}
+
+impl View {
+    /// Returns a view containing all ships in the snapshot.
+    pub fn fit(snapshot: &Snapshot, aspect_ratio: f64) -> Self {
+        let (min, max) = snapshot.ships.iter().fold(
+            (point![f64::MAX, f64::MAX], point![f64::MIN, f64::MIN]),
+            |(min, max), ship| (min.inf(&ship.position), max.sup(&ship.position)),
+        );
+        if snapshot.ships.is_empty() {
+            return Self {
+                center: point![0.0, 0.0],
+                width: snapshot.world_size,
+            };
+        }
+        let size = max - min;
+        let width = (size.x.max(size.y * aspect_ratio) * 1.2)
+            .max(1000.0)
+            .min(snapshot.world_size);
+        Self {
+            center: nalgebra::center(&min, &max),
+            width,
+        }
+    }
+}
+
+/// A shape to draw in image coordinates.
+struct Path {
+    outline: Outline,
+    stroke: Vector4<f32>,
+    fill: Option<Vector4<f32>>,
+    width: f64,
+}
+
+enum Outline {
+    /// A polyline, or a polygon if closed.
+    Points {
+        points: Vec<Point2<f64>>,
+        closed: bool,
+    },
+    /// A circle or an arc, with angles increasing clockwise on screen.
+    Arc {
+        center: Point2<f64>,
+        radius: f64,
+        start_angle: f64,
+        span: f64,
+    },
+}
+
+impl Outline {
+    fn is_empty(&self) -> bool {
+        match self {
+            Outline::Points { points, .. } => points.len() < 2,
+            Outline::Arc { radius, span, .. } => *radius <= 0.0 || *span <= 0.0,
+        }
+    }
+}
+
+pub struct Renderer {
+    options: Options,
+    view: Option<View>,
+    trails: HashMap<u64, VecDeque<Point2<f64>>>,
+}
+
+impl Renderer {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            view: None,
+            trails: HashMap::new(),
+        }
+    }
+
+    /// Sets the area of the world shown. By default the view is fit to the
+    /// ships in the first snapshot rendered and then kept fixed.
+    pub fn set_view(&mut self, view: View) {
+        self.view = Some(view);
+    }
+
+    /// Records ship positions for trails. Call this for every snapshot in a
+    /// sequence, including ones that aren't rendered.
+    pub fn update(&mut self, snapshot: &Snapshot) {
+        self.trails
+            .retain(|id, _| snapshot.ships.iter().any(|ship| ship.id == *id));
+        for ship in snapshot.ships.iter() {
+            let trail = self.trails.entry(ship.id).or_default();
+            trail.push_back(ship.position);
+            if trail.len() > TRAIL_LENGTH {
+                trail.pop_front();
+            }
+        }
+    }
+
+    pub fn render_svg(&mut self, snapshot: &Snapshot) -> String {
+        let paths = self.paths(snapshot);
+        let (width, height) = (self.options.width, self.options.height);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}" />"#,
+            svg_color(BACKGROUND)
+        );
+        for path in paths.iter() {
+            let fill = match path.fill {
+                Some(fill) => format!(r#"fill="{}" fill-opacity="{}""#, svg_color(fill), fill.w),
+                None => r#"fill="none""#.to_string(),
+            };
+            let element = match &path.outline {
+                Outline::Points { points, closed } => {
+                    let points = points
+                        .iter()
+                        .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let tag = if *closed { "polygon" } else { "polyline" };
+                    format!(r#"<{tag} points="{points}""#)
+                }
+                Outline::Arc {
+                    center,
+                    radius,
+                    span,
+                    ..
+                } if *span >= std::f64::consts::TAU => format!(
+                    r#"<circle cx="{:.1}" cy="{:.1}" r="{radius:.1}""#,
+                    center.x, center.y
+                ),
+                Outline::Arc {
+                    center,
+                    radius,
+                    start_angle,
+                    span,
+                } => {
+                    let p0 = arc_point(*center, *radius, *start_angle);
+                    let p1 = arc_point(*center, *radius, start_angle + span);
+                    let large_arc = (*span > std::f64::consts::PI) as u8;
+                    format!(
+                        r#"<path d="M {:.1} {:.1} A {radius:.1} {radius:.1} 0 {large_arc} 1 {:.1} {:.1}""#,
+                        p0.x, p0.y, p1.x, p1.y
+                    )
+                }
+            };
+            let _ = writeln!(
+                svg,
+                r#"{element} {fill} stroke="{}" stroke-opacity="{}" stroke-width="{}" />"#,
+                svg_color(path.stroke),
+                path.stroke.w,
+                path.width
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn render_png(&mut self, snapshot: &Snapshot) -> anyhow::Result<Vec<u8>> {
+        use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+        let paths = self.paths(snapshot);
+        let mut pixmap = Pixmap::new(self.options.width, self.options.height)
+            .ok_or_else(|| anyhow::anyhow!("Invalid image size"))?;
+        pixmap.fill(skia_color(BACKGROUND));
+        let mut paint = Paint {
+            anti_alias: true,
+            ..Default::default()
+        };
+        for path in paths.iter() {
+            let mut pb = PathBuilder::new();
+            match &path.outline {
+                Outline::Points { points, closed } => {
+                    pb.move_to(points[0].x as f32, points[0].y as f32);
+                    for p in points[1..].iter() {
+                        pb.line_to(p.x as f32, p.y as f32);
+                    }
+                    if *closed {
+                        pb.close();
+                    }
+                }
+                Outline::Arc {
+                    center,
+                    radius,
+                    span,
+                    ..
+                } if *span >= std::f64::consts::TAU => {
+                    pb.push_circle(center.x as f32, center.y as f32, *radius as f32);
+                }
+                Outline::Arc {
+                    center,
+                    radius,
+                    start_angle,
+                    span,
+                } => {
+                    // Cubic Béziers of at most a quarter turn each.
+                    let n = (span / std::f64::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+                    let step = span / n as f64;
+                    let k = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+                    let p0 = arc_point(*center, *radius, *start_angle);
+                    pb.move_to(p0.x as f32, p0.y as f32);
+                    for i in 0..n {
+                        let a0 = start_angle + step * i as f64;
+                        let a1 = a0 + step;
+                        let p0 = arc_point(*center, *radius, a0);
+                        let p1 = arc_point(*center, *radius, a1);
+                        let c0 = p0 + vector![-a0.sin(), a0.cos()] * k;
+                        let c1 = p1 - vector![-a1.sin(), a1.cos()] * k;
+                        pb.cubic_to(
+                            c0.x as f32,
+                            c0.y as f32,
+                            c1.x as f32,
+                            c1.y as f32,
+                            p1.x as f32,
+                            p1.y as f32,
+                        );
+                    }
+                }
+            }
+            let Some(skia_path) = pb.finish() else {
+                continue;
+            };
+            if let Some(fill) = path.fill {
+                paint.set_color(skia_color(fill));
+                pixmap.fill_path(
+                    &skia_path,
+                    &paint,
+                    FillRule::Winding,
+                    Transform::identity(),
+                    None,
+                );
+            }
+            paint.set_color(skia_color(path.stroke));
+            let stroke = Stroke {
+                width: path.width as f32,
+                ..Default::default()
+            };
+            pixmap.stroke_path(&skia_path, &paint, &stroke, Transform::identity(), None);
+        }
+        Ok(pixmap.encode_png()?)
+    }
+
+    /// Builds the list of paths to draw, back to front.
+    fn paths(&mut self, snapshot: &Snapshot) -> Vec<Path> {
+        let aspect_ratio = self.options.width as f64 / self.options.height as f64;
+        let view = *self
+            .view
+            .get_or_insert_with(|| View::fit(snapshot, aspect_ratio));
+        let scale = self.options.width as f64 / view.width;
+        let image_center = point![
+            self.options.width as f64 / 2.0,
+            self.options.height as f64 / 2.0
+        ];
+        let project = |p: Point2<f64>| {
+            let v = (p - view.center) * scale;
+            image_center + vector![v.x, -v.y]
+        };
+        let line = |points: Vec<Point2<f64>>, closed: bool, color: Vector4<f32>| Path {
+            outline: Outline::Points {
+                points: points.into_iter().map(project).collect(),
+                closed,
+            },
+            stroke: color,
+            fill: None,
+            width: 1.0,
+        };
+
+        let mut paths = vec![];
+
+        let r = snapshot.world_size / 2.0;
+        paths.push(line(
+            vec![point![-r, -r], point![r, -r], point![r, r], point![-r, r]],
+            true,
+            BOUNDARY_COLOR,
+        ));
+
+        for ship in snapshot.ships.iter() {
+            if matches!(
+                ship.class,
+                ShipClass::Asteroid { .. } | ShipClass::BigAsteroid { .. }
+            ) || ship.fuel == Some(0.0)
+            {
+                continue;
+            }
+            let Some(trail) = self.trails.get(&ship.id) else {
+                continue;
+            };
+            let mut color = color::team(ship.team);
+            color.w = match ship.class {
+                ShipClass::Missile => 0.10,
+                ShipClass::Torpedo => 0.15,
+                _ => 0.5,
+            };
+            // Fade out towards the tail, one segment at a time.
+            let n = trail.len();
+            for (i, (a, b)) in trail.iter().zip(trail.iter().skip(1)).enumerate() {
+                let mut color = color;
+                color.w *= (i + 1) as f32 / n as f32;
+                paths.push(line(vec![*a, *b], false, color));
+            }
+        }
+
+        for bullet in snapshot.bullets.iter() {
+            let mut color = color::from_u32(bullet.color);
+            if bullet.ttl < 0.3 {
+                color.w *= bullet.ttl + 0.3;
+            }
+            let tail = bullet.position - 2.0 * bullet.velocity * PHYSICS_TICK_LENGTH;
+            paths.push(line(vec![tail, bullet.position], false, color));
+        }
+
+        for scenario_line in snapshot.scenario_lines.iter() {
+            paths.push(line(
+                vec![scenario_line.a, scenario_line.b],
+                false,
+                scenario_line.color,
+            ));
+        }
+
+        if self.options.debug {
+            for (_, lines) in snapshot.debug_lines.iter() {
+                for l in lines.iter() {
+                    paths.push(line(vec![l.a, l.b], false, l.color));
+                }
+            }
+            for shape in snapshot.debug_shapes.values().flatten() {
+                let mut path = match shape.arc() {
+                    // The y axis is flipped, so angles are negated.
+                    Some((center, radius, start_angle, span)) => Path {
+                        outline: Outline::Arc {
+                            center: project(center),
+                            radius: radius * scale,
+                            start_angle: -(start_angle + span),
+                            span,
+                        },
+                        stroke: shape.color,
+                        fill: None,
+                        width: 1.0,
+                    },
+                    None => line(shape.points(), shape.closed(), shape.color),
+                };
+                if shape.filled && !matches!(shape.geometry, Geometry::Arc { .. }) {
+                    let mut fill = shape.color;
+                    fill.w *= FILL_ALPHA;
+                    path.fill = Some(fill);
+                }
+                paths.push(path);
+            }
+        }
+
+        for ship in snapshot.ships.iter() {
+            let rotation = Rotation2::new(ship.heading);
+            let points = model::load(ship.class)
+                .iter()
+                .map(|v| ship.position + rotation * v.cast::<f64>())
+                .collect();
+            let mut path = line(points, true, color::team(ship.team));
+            path.width = 1.5;
+            paths.push(path);
+        }
+
+        paths.retain(|path| !path.outline.is_empty() && path.stroke.w > 0.0);
+        paths
+    }
+}
+
+fn arc_point(center: Point2<f64>, radius: f64, angle: f64) -> Point2<f64> {
+    center + vector![angle.cos(), angle.sin()] * radius
+}
+
+fn svg_color(c: Vector4<f32>) -> String {
+    let convert = |x: f32| (x.clamp(0.0, 1.0) * 255.0) as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        convert(c.x),
+        convert(c.y),
+        convert(c.z)
+    )
+}
+
+fn skia_color(c: Vector4<f32>) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba(
+        c.x.clamp(0.0, 1.0),
+        c.y.clamp(0.0, 1.0),
+        c.z.clamp(0.0, 1.0),
+        c.w.clamp(0.0, 1.0),
+    )
+    .unwrap_or(tiny_skia::Color::WHITE)
+}
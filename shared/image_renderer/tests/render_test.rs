@@ -0,0 +1,97 @@
+use nalgebra::{point, vector};
+use oort_image_renderer::{Options, Renderer};
+use oort_simulator::debug::{Geometry, Shape};
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation::Code;
+use oort_simulator::{scenario, simulation};
+
+fn snapshots() -> Vec<oort_simulator::snapshot::Snapshot> {
+    let scenario_name = "tutorial_squadron";
+    let scenario = scenario::load(scenario_name);
+    let mut sim = simulation::Simulation::new(scenario_name, 0, &scenario.solution_codes());
+    (0..60)
+        .map(|_| {
+            sim.step();
+            sim.snapshot(0)
+        })
+        .collect()
+}
+
+#[test]
+fn test_render_svg() {
+    let mut renderer = Renderer::new(Options::default());
+    let snapshots = snapshots();
+    for snapshot in snapshots.iter() {
+        renderer.update(snapshot);
+    }
+    let svg = renderer.render_svg(snapshots.last().unwrap());
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("<polygon"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+}
+
+#[test]
+fn test_render_png() {
+    let mut renderer = Renderer::new(Options {
+        width: 320,
+        height: 240,
+        debug: true,
+    });
+    let snapshots = snapshots();
+    for snapshot in snapshots.iter() {
+        renderer.update(snapshot);
+    }
+    let png = renderer.render_png(snapshots.last().unwrap()).unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+}
+
+#[test]
+fn test_render_circles() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    let shape = |geometry| Shape {
+        geometry,
+        color: vector![1.0, 1.0, 1.0, 1.0],
+        filled: true,
+        layer: 0,
+    };
+    sim.emit_debug_shapes(
+        handle,
+        vec![
+            (
+                0,
+                shape(Geometry::Circle {
+                    center: point![0.0, 0.0],
+                    radius: 100.0,
+                }),
+            ),
+            (
+                0,
+                shape(Geometry::Arc {
+                    center: point![0.0, 0.0],
+                    radius: 50.0,
+                    start_angle: 0.0,
+                    end_angle: std::f64::consts::FRAC_PI_2,
+                }),
+            ),
+        ],
+    );
+    let snapshot = sim.snapshot(0);
+
+    let mut renderer = Renderer::new(Options {
+        debug: true,
+        ..Default::default()
+    });
+    renderer.update(&snapshot);
+    let svg = renderer.render_svg(&snapshot);
+    assert_eq!(svg.matches("<circle").count(), 1);
+    assert_eq!(svg.matches("<path").count(), 1);
+    assert!(renderer.render_png(&snapshot).is_ok());
+}
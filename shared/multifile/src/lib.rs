@@ -1,5 +1,6 @@
 use anyhow::bail;
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 pub fn join(mut files: HashMap<String, String>) -> Result<String, anyhow::Error> {
     let re = regex::Regex::new(r"pub (struct|enum) Ship").unwrap();
@@ -21,41 +22,125 @@ pub fn join(mut files: HashMap<String, String>) -> Result<String, anyhow::Error>
         bail!("No lib.rs found");
     };
 
-    let re = regex::Regex::new(r"(pub )?mod (\w+);").unwrap();
-    let new_lib = re
-        .replace_all(&lib, |caps: &regex::Captures| {
-            let pubk = caps.get(1).map(|m| m.as_str()).unwrap_or("");
-            let name = caps.get(2).unwrap().as_str();
-            let filename = format!("{}.rs", name);
-            if let Some(src) = files.get(&filename) {
-                format!(
-                    "{}mod {} {{ // start multifile\n{}\n}} // end multifile",
-                    pubk, name, src
-                )
-            } else {
-                format!("std::compile_error!(\"Missing file: {}\");", filename)
-            }
-        })
-        .into_owned();
-    Ok(new_lib)
+    Ok(join_module(&files, &lib, ""))
+}
+
+const START_MARKER: &str = "// start multifile";
+const END_MARKER: &str = "} // end multifile";
+
+/// Inlines the `mod foo;` declarations in `src`, whose submodules live in
+/// `dir` (e.g. "" for the crate root, "a/" for `a.rs` or `a/mod.rs`).
+fn join_module(files: &HashMap<String, String>, src: &str, dir: &str) -> String {
+    let re = regex::Regex::new(r"((?:pub(?:\([^)]*\))? )?)mod (\w+);").unwrap();
+    re.replace_all(src, |caps: &regex::Captures| {
+        let vis = caps.get(1).unwrap().as_str();
+        let name = caps.get(2).unwrap().as_str();
+        let filename = format!("{dir}{name}.rs");
+        let mod_filename = format!("{dir}{name}/mod.rs");
+        // The default filename is implied, so only mod.rs is recorded.
+        let (src, marker) = if let Some(src) = files.get(&filename) {
+            (src, START_MARKER.to_string())
+        } else if let Some(src) = files.get(&mod_filename) {
+            (src, format!("{START_MARKER} {mod_filename}"))
+        } else {
+            return format!("std::compile_error!(\"Missing file: {}\");", filename);
+        };
+        let src = join_module(files, src, &format!("{dir}{name}/"));
+        format!("{vis}mod {name} {{ {marker}\n{src}\n{END_MARKER}")
+    })
+    .into_owned()
+}
+
+/// Returns the directory containing the files for the submodules of the
+/// module in `path`.
+fn module_dir(path: &str) -> String {
+    if path == "lib.rs" {
+        String::new()
+    } else if let Some(dir) = path.strip_suffix("mod.rs") {
+        dir.to_string()
+    } else {
+        format!("{}/", path.strip_suffix(".rs").unwrap_or(path))
+    }
+}
+
+/// Matches the first line of an inlined module, returning the text before
+/// the `mod` declaration, the declaration itself and the path of the file.
+fn parse_start_line(text: &str, parent_path: &str) -> Option<(String, String, String)> {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        regex::Regex::new(
+            r"^(.*?)((?:pub(?:\([^)]*\))? )?)mod (\w+) \{ // start multifile(?: (\S+))?$",
+        )
+        .unwrap()
+    });
+    let caps = re.captures(text)?;
+    let prefix = caps.get(1).unwrap().as_str();
+    let vis = caps.get(2).unwrap().as_str();
+    let name = caps.get(3).unwrap().as_str();
+    let path = match caps.get(4) {
+        Some(path) => path.as_str().to_string(),
+        None => format!("{}{name}.rs", module_dir(parent_path)),
+    };
+    Some((prefix.to_string(), format!("{vis}mod {name};"), path))
 }
 
 pub fn split(lib: &str) -> HashMap<String, String> {
     let mut files = HashMap::new();
-    let re = regex::Regex::new(
-        r"(pub )?mod (\w+) \{ // start multifile\n(?s:(.*?))\n\} // end multifile",
-    )
-    .unwrap();
-    for caps in re.captures_iter(lib) {
-        let name = caps.get(2).unwrap().as_str();
-        let src = caps.get(3).unwrap().as_str();
-        files.insert(format!("{}.rs", name), src.to_string());
+    // Stack of (file, lines in that file).
+    let mut stack: Vec<(String, Vec<String>)> = vec![("lib.rs".to_string(), vec![])];
+    for text in lib.split('\n') {
+        if stack.len() > 1 {
+            if let Some(rest) = text.strip_prefix(END_MARKER) {
+                let (path, lines) = stack.pop().unwrap();
+                files.insert(path, lines.join("\n"));
+                // The rest of this line continues the line with the "mod" statement.
+                let (_, parent_lines) = stack.last_mut().unwrap();
+                parent_lines.last_mut().unwrap().push_str(rest);
+                continue;
+            }
+        }
+        let (parent_path, parent_lines) = stack.last_mut().unwrap();
+        if let Some((prefix, decl, path)) = parse_start_line(text, parent_path) {
+            parent_lines.push(format!("{prefix}{decl}"));
+            stack.push((path, vec![]));
+        } else {
+            parent_lines.push(text.to_string());
+        }
+    }
+    for (path, lines) in stack {
+        files.insert(path, lines.join("\n"));
     }
-    let lib = re.replace_all(lib, "${1}mod $2;").into_owned();
-    files.insert("lib.rs".to_string(), lib);
     files
 }
 
+/// Maps a 1-based line in the output of [`join`] to the original file and line.
+pub fn map_line(lib: &str, line: usize) -> (String, usize) {
+    line.checked_sub(1)
+        .and_then(|i| line_map(lib).into_iter().nth(i))
+        .unwrap_or_else(|| ("lib.rs".to_string(), line))
+}
+
+/// Returns the original file and 1-based line for each line in the output of
+/// [`join`].
+pub fn line_map(lib: &str) -> Vec<(String, usize)> {
+    let mut result = vec![];
+    // Stack of (file, current line in that file).
+    let mut stack = vec![("lib.rs".to_string(), 0)];
+    for text in lib.lines() {
+        if text.starts_with(END_MARKER) && stack.len() > 1 {
+            // The rest of this line continues the line with the "mod" statement.
+            stack.pop();
+        } else {
+            stack.last_mut().unwrap().1 += 1;
+        }
+        result.push(stack.last().unwrap().clone());
+        if let Some((_, _, path)) = parse_start_line(text, &stack.last().unwrap().0) {
+            stack.push((path, 0));
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -112,6 +197,120 @@ pub struct Ship {}
         );
     }
 
+    #[test]
+    fn test_join_nested() {
+        let mut files = std::collections::HashMap::new();
+        files.insert("lib.rs".to_string(), "mod a;\nmod c;\n".to_string());
+        files.insert("a.rs".to_string(), "pub(crate) mod b;".to_string());
+        files.insert("a/b.rs".to_string(), "fn b() {}".to_string());
+        files.insert("c/mod.rs".to_string(), "mod d;".to_string());
+        files.insert("c/d.rs".to_string(), "fn d() {}".to_string());
+        assert_eq!(
+            super::join(files).unwrap(),
+            "\
+mod a { // start multifile
+pub(crate) mod b { // start multifile
+fn b() {}
+} // end multifile
+} // end multifile
+mod c { // start multifile c/mod.rs
+mod d { // start multifile
+fn d() {}
+} // end multifile
+} // end multifile
+"
+        );
+    }
+
+    #[test]
+    fn test_join_missing_nested_file() {
+        let mut files = std::collections::HashMap::new();
+        files.insert("lib.rs".to_string(), "mod a;".to_string());
+        files.insert("a/mod.rs".to_string(), "mod b;".to_string());
+        assert_eq!(
+            super::join(files).unwrap(),
+            "mod a { // start multifile a/mod.rs\nstd::compile_error!(\"Missing file: a/b.rs\");\n} // end multifile"
+        );
+    }
+
+    #[test]
+    fn test_map_line_nested() {
+        let mut files = std::collections::HashMap::new();
+        files.insert("lib.rs".to_string(), "mod a;\nmod c;\n".to_string());
+        files.insert("a.rs".to_string(), "mod b;\nfn a() {}".to_string());
+        files.insert("a/b.rs".to_string(), "fn b() {}".to_string());
+        files.insert("c/mod.rs".to_string(), "fn c() {}".to_string());
+        let lib = super::join(files).unwrap();
+        let expected = [
+            ("lib.rs", 1),
+            ("a.rs", 1),
+            ("a/b.rs", 1),
+            ("a.rs", 1),
+            ("a.rs", 2),
+            ("lib.rs", 1),
+            ("lib.rs", 2),
+            ("c/mod.rs", 1),
+            ("lib.rs", 2),
+        ];
+        for (i, (file, line)) in expected.iter().enumerate() {
+            assert_eq!(
+                super::map_line(&lib, i + 1),
+                (file.to_string(), *line),
+                "line {}",
+                i + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_map() {
+        let mut files = std::collections::HashMap::new();
+        files.insert("lib.rs".to_string(), "mod a;\nfn f() {}".to_string());
+        files.insert("a/mod.rs".to_string(), "fn a() {}".to_string());
+        let lib = super::join(files).unwrap();
+        assert_eq!(
+            super::line_map(&lib),
+            vec![
+                ("lib.rs".to_string(), 1),
+                ("a/mod.rs".to_string(), 1),
+                ("lib.rs".to_string(), 1),
+                ("lib.rs".to_string(), 2),
+            ]
+        );
+        assert_eq!(super::line_map("x\ny"), super::line_map("x\ny\n"));
+    }
+
+    #[test]
+    fn test_map_line() {
+        let mut files = std::collections::HashMap::new();
+        files.insert(
+            "lib.rs".to_string(),
+            "use foo::*;\nmod foo;\nmod bar;\nfn main() {}\n".to_string(),
+        );
+        files.insert("foo.rs".to_string(), "fn foo() {}\nfn foo2() {}".to_string());
+        files.insert("bar.rs".to_string(), "fn bar() {}".to_string());
+        let lib = super::join(files).unwrap();
+        let expected = [
+            ("lib.rs", 1),
+            ("lib.rs", 2),
+            ("foo.rs", 1),
+            ("foo.rs", 2),
+            ("lib.rs", 2),
+            ("lib.rs", 3),
+            ("bar.rs", 1),
+            ("lib.rs", 3),
+            ("lib.rs", 4),
+        ];
+        for (i, (file, line)) in expected.iter().enumerate() {
+            assert_eq!(
+                super::map_line(&lib, i + 1),
+                (file.to_string(), *line),
+                "line {}",
+                i + 1
+            );
+        }
+    }
+
     #[test]
     fn test_split() {
         let lib = "pub mod foo { // start multifile\nfn foo() {}\n} // end multifile";
@@ -139,6 +338,23 @@ pub struct Ship {}
         assert_eq!(canonicalize(&splitfiles), canonicalize(&files));
     }
 
+    #[test]
+    fn test_roundtrip_nested() {
+        let mut files = std::collections::HashMap::new();
+        files.insert(
+            "lib.rs".to_string(),
+            "mod a; // comment\npub mod c;\npub struct Ship {}\n".to_string(),
+        );
+        files.insert("a.rs".to_string(), "    mod b;\nfn a() {}\n".to_string());
+        files.insert("a/b.rs".to_string(), "fn b() {}\n\n".to_string());
+        files.insert("c/mod.rs".to_string(), "pub(super) mod d;".to_string());
+        files.insert("c/d/mod.rs".to_string(), "".to_string());
+
+        let multifile = super::join(files.clone()).unwrap();
+        let splitfiles = super::split(&multifile);
+        assert_eq!(canonicalize(&splitfiles), canonicalize(&files));
+    }
+
     fn canonicalize(map: &HashMap<String, String>) -> Vec<(String, String)> {
         let mut v: Vec<_> = map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
         v.sort();
@@ -20,14 +20,64 @@ pub struct CompletionItem {
     pub filterText: String,
 }
 
+/// A zero-based range in the editor text.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Range {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Hover {
+    /// Markdown.
+    pub contents: String,
+    pub range: Range,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignatureHelp {
+    pub label: String,
+    pub documentation: Option<String>,
+    pub parameters: Vec<String>,
+    pub active_parameter: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InlayHint {
+    pub line: u32,
+    pub column: u32,
+    pub label: String,
+    /// Monaco's InlayHintKind: 1 for types, 2 for parameters.
+    pub kind: u32,
+    pub padding_left: bool,
+    pub padding_right: bool,
+}
+
+// Positions in requests are one-based, as in Monaco. Hover, definition,
+// signature help and inlay hint requests start with an id that the response
+// echoes, so that responses to stale requests can be dropped.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     Diagnostics(String),
     Completion(u32, u32),
+    Format(String),
+    Hover(u64, u32, u32),
+    Definition(u64, u32, u32),
+    SignatureHelp(u64, u32, u32),
+    InlayHints(u64),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
     Diagnostics(Vec<Diagnostic>),
     Completion(Vec<CompletionItem>),
+    /// None if the code couldn't be formatted.
+    Format(Option<String>),
+    Hover(u64, Option<Hover>),
+    /// Only definitions in the user's code are returned.
+    Definition(u64, Option<Range>),
+    SignatureHelp(u64, Option<SignatureHelp>),
+    InlayHints(u64, Vec<InlayHint>),
 }
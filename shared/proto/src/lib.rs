@@ -108,3 +108,78 @@ pub struct TournamentCompetitor {
     pub shortcode: String,
     pub rating: f64,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// A location in the submitted source code.
+///
+/// `file` and `file_line` refer to the original file if the source was joined
+/// from multiple files with `oort_multifile::join`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiagnosticSpan {
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+    pub file: String,
+    pub file_line: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiagnosticSuggestion {
+    pub message: String,
+    pub span: DiagnosticSpan,
+    pub replacement: String,
+    /// The suggestion can be applied without review.
+    pub machine_applicable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompilerDiagnostic {
+    pub level: DiagnosticLevel,
+    pub message: String,
+    pub code: Option<String>,
+    pub span: Option<DiagnosticSpan>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<DiagnosticSuggestion>,
+    /// Human readable rendering as printed by the compiler.
+    pub rendered: String,
+}
+
+/// Body of a successful `/compile` request with `diagnostics=true`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompileSuccess {
+    #[serde(with = "base64_bytes")]
+    pub wasm: Vec<u8>,
+    pub diagnostics: Vec<CompilerDiagnostic>,
+}
+
+/// Body of a failed `/compile` request with `diagnostics=true`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompileFailure {
+    pub message: String,
+    pub diagnostics: Vec<CompilerDiagnostic>,
+}
+
+mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(s)
+            .map_err(serde::de::Error::custom)
+    }
+}
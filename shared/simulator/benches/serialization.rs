@@ -2,19 +2,22 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use oort_simulator::scenario;
 use oort_simulator::simulation;
 use oort_simulator::snapshot::Snapshot;
+use oort_simulator::snapshot_encoding::{Decoder, Encoder, Frame};
 
-fn make_snapshot() -> Snapshot {
+fn make_snapshots() -> (Snapshot, Snapshot) {
     let scenario_name = "tutorial_squadron";
     let scenario = scenario::load(scenario_name);
     let mut sim = simulation::Simulation::new(scenario_name, 0, &scenario.solution_codes());
     for _ in 0..300 {
         sim.step();
     }
-    sim.snapshot(0)
+    let previous = sim.snapshot(0);
+    sim.step();
+    (previous, sim.snapshot(0))
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
-    let snapshot = make_snapshot();
+    let (previous, snapshot) = make_snapshots();
     c.bench_function("json", |b| {
         b.iter(|| {
             black_box(serde_json::from_str::<Snapshot>(
@@ -29,6 +32,16 @@ fn criterion_benchmark(c: &mut Criterion) {
             ))
         })
     });
+    c.bench_function("delta", |b| {
+        b.iter(|| {
+            let mut encoder = Encoder::default();
+            let mut decoder = Decoder::default();
+            for snapshot in [previous.clone(), snapshot.clone()] {
+                let data = bincode::serialize(&encoder.encode(snapshot)).unwrap();
+                black_box(decoder.decode(bincode::deserialize::<Frame>(&data).unwrap()));
+            }
+        })
+    });
 }
 
 pub fn criterion_config() -> Criterion {
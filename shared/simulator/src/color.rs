@@ -15,6 +15,17 @@ pub fn from_u32(c: u32) -> Vector4<f32> {
     ]
 }
 
+/// The color ships and trails of a team are drawn with.
+pub fn team(team: i32) -> Vector4<f32> {
+    match team {
+        0 => vector![0.99, 0.98, 0.00, 1.00],
+        1 => vector![0.99, 0.00, 0.98, 1.00],
+        2 => vector![0.13, 0.50, 0.73, 1.00],
+        9 => vector![0.40, 0.40, 0.40, 1.00],
+        _ => vector![1.0, 1.0, 1.0, 1.0],
+    }
+}
+
 pub fn from_u24(c: u32) -> Vector4<f32> {
     let extract_color = |k: i64| -> f32 { (((c >> (k * 8)) & 0xff) as f32) / 255.0 };
     vector![extract_color(2), extract_color(1), extract_color(0), 1.0]
@@ -2,16 +2,159 @@ use crate::ship::ShipHandle;
 use crate::simulation::Simulation;
 use nalgebra::{vector, Point2, UnitComplex, Vector4};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 const DEBUG_RADAR_RADIUS: bool = false;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+/// Number of ticks a plotted point is kept for.
+pub const MAX_PLOT_POINTS: u32 = 600;
+/// Maximum number of plot series kept per ship.
+pub const MAX_PLOT_SERIES: usize = 32;
+
+/// Recent points from `dbg::plot` with the same name, as (tick, value)
+/// pairs, oldest first.
+pub type PlotSeries = VecDeque<(u32, f64)>;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Line {
     pub a: Point2<f64>,
     pub b: Point2<f64>,
     pub color: Vector4<f32>,
 }
 
+/// A shape drawn with the `dbg` functions.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Shape {
+    pub geometry: Geometry,
+    pub color: Vector4<f32>,
+    pub filled: bool,
+    /// Zero for the default layer, otherwise one plus an index into
+    /// `Snapshot::debug_layers`.
+    pub layer: u8,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Geometry {
+    Line {
+        a: Point2<f64>,
+        b: Point2<f64>,
+    },
+    Circle {
+        center: Point2<f64>,
+        radius: f64,
+    },
+    Arc {
+        center: Point2<f64>,
+        radius: f64,
+        start_angle: f64,
+        end_angle: f64,
+    },
+    Polygon {
+        center: Point2<f64>,
+        radius: f64,
+        sides: u8,
+        angle: f64,
+    },
+}
+
+impl Shape {
+    /// Returns the outline of a line or polygon as a sequence of points.
+    ///
+    /// Circles and arcs have no points, renderers draw them directly. For
+    /// polygons the last point connects back to the first.
+    pub fn points(&self) -> Vec<Point2<f64>> {
+        match self.geometry {
+            Geometry::Line { a, b } => vec![a, b],
+            Geometry::Circle { .. } | Geometry::Arc { .. } => vec![],
+            Geometry::Polygon {
+                center,
+                radius,
+                sides,
+                angle,
+            } => (0..sides)
+                .map(|i| {
+                    let angle = angle + std::f64::consts::TAU * i as f64 / sides as f64;
+                    center + vector![radius * angle.cos(), radius * angle.sin()]
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the outline returned by [`Shape::points`] is closed.
+    pub fn closed(&self) -> bool {
+        matches!(self.geometry, Geometry::Polygon { .. })
+    }
+
+    /// Returns the center, radius, start angle and counterclockwise span of a
+    /// circle or arc. Circles span a full turn.
+    pub fn arc(&self) -> Option<(Point2<f64>, f64, f64, f64)> {
+        match self.geometry {
+            Geometry::Circle { center, radius } => {
+                Some((center, radius, 0.0, std::f64::consts::TAU))
+            }
+            Geometry::Arc {
+                center,
+                radius,
+                start_angle,
+                end_angle,
+            } => Some((
+                center,
+                radius,
+                start_angle,
+                (end_angle - start_angle).rem_euclid(std::f64::consts::TAU),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the outline as line segments.
+    pub fn lines(&self) -> Vec<Line> {
+        let points = self.points();
+        let mut lines: Vec<Line> = points
+            .windows(2)
+            .map(|w| Line {
+                a: w[0],
+                b: w[1],
+                color: self.color,
+            })
+            .collect();
+        if self.closed() && points.len() > 2 {
+            lines.push(Line {
+                a: points[points.len() - 1],
+                b: points[0],
+                color: self.color,
+            });
+        }
+        lines
+    }
+
+    /// Returns triangles covering a filled shape.
+    pub fn triangles(&self) -> Vec<[Point2<f64>; 3]> {
+        let center = match self.geometry {
+            Geometry::Polygon { center, .. } if self.filled => center,
+            _ => return vec![],
+        };
+        let points = self.points();
+        (0..points.len())
+            .map(|i| [center, points[i], points[(i + 1) % points.len()]])
+            .collect()
+    }
+}
+
+/// A named number from `dbg::plot` or `dbg::watch`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Value {
+    pub name: String,
+    pub value: f64,
+    pub kind: ValueKind,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    Plot,
+    Watch,
+}
+
 pub fn emit_ship(sim: &mut Simulation, handle: ShipHandle) {
     let mut lines = vec![];
     lines.reserve(2 + sim.ship(handle).data().guns.len());
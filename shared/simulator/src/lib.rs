@@ -4,6 +4,7 @@ pub mod color;
 pub mod debug;
 pub mod index_set;
 pub mod model;
+pub mod pilot;
 pub mod radar;
 pub mod radio;
 pub mod rng;
@@ -11,4 +12,5 @@ pub mod scenario;
 pub mod ship;
 pub mod simulation;
 pub mod snapshot;
+pub mod snapshot_encoding;
 pub mod vm;
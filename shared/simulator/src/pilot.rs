@@ -0,0 +1,17 @@
+// Manual piloting, where the player flies a ship with the keyboard while its
+// AI keeps running.
+use serde::{Deserialize, Serialize};
+
+/// Keyboard inputs for a manually piloted ship. Each axis ranges from -1 to 1
+/// and is scaled by the ship's maximum acceleration.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PilotInput {
+    /// Forward (positive) or backward acceleration.
+    pub thrust: f64,
+    /// Leftward (positive) or rightward acceleration.
+    pub strafe: f64,
+    /// Counter-clockwise (positive) or clockwise angular acceleration.
+    pub turn: f64,
+    /// Fires the first gun straight ahead.
+    pub fire: bool,
+}
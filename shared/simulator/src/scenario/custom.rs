@@ -0,0 +1,271 @@
+// Scenarios built in the scenario editor. A custom scenario is named after a
+// hash of its description. Descriptions are registered under that name
+// before the scenario is loaded, so custom scenarios can be loaded anywhere a
+// builtin scenario can.
+use super::prelude::*;
+use super::{check_victory_with_filter, MAX_TICKS};
+use crate::ship::{ShipClass, ShipData};
+use crate::simulation::MAX_WORLD_SIZE;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub const PREFIX: &str = "custom:";
+pub const MAX_SHIPS: usize = 200;
+pub const MAX_TEAMS: i32 = 8;
+
+/// Classes that can be placed in the editor.
+pub const CLASSES: &[ShipClass] = &[
+    ShipClass::Fighter,
+    ShipClass::Frigate,
+    ShipClass::Cruiser,
+    ShipClass::Missile,
+    ShipClass::Torpedo,
+    ShipClass::Target,
+    ShipClass::Asteroid { variant: 0 },
+    ShipClass::BigAsteroid { variant: 0 },
+];
+
+/// Builtin AIs that can control enemy teams.
+pub const ENEMY_AIS: &[&str] = &["reference", "empty"];
+
+/// Registered descriptions by scenario name.
+static REGISTRY: Mutex<BTreeMap<String, ScenarioDescription>> = Mutex::new(BTreeMap::new());
+
+/// Makes a custom scenario loadable and returns its name.
+pub fn register(description: ScenarioDescription) -> Result<String, String> {
+    description.validate()?;
+    let name = description.scenario_name();
+    REGISTRY.lock().unwrap().insert(name.clone(), description);
+    Ok(name)
+}
+
+/// Returns the description of a registered custom scenario.
+pub fn lookup(name: &str) -> Option<ScenarioDescription> {
+    REGISTRY.lock().unwrap().get(name).cloned()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScenarioDescription {
+    pub name: String,
+    pub world_size: f64,
+    pub max_ticks: u32,
+    pub victory: VictoryCondition,
+    pub ships: Vec<ShipPlacement>,
+    /// Builtin AI names for enemy teams. Team 0 is the player.
+    pub enemy_ais: BTreeMap<i32, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShipPlacement {
+    pub class: ShipClass,
+    pub team: i32,
+    pub position: Vector2<f64>,
+    pub velocity: Vector2<f64>,
+    pub heading: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VictoryCondition {
+    /// The player wins by destroying all other ships, excluding missiles and
+    /// torpedos, before time runs out.
+    DestroyEnemies,
+    /// Like a tournament: the last team with fighters or capital ships wins.
+    LastTeamStanding,
+    /// The player wins by having a ship left when time runs out.
+    Survive,
+}
+
+impl Default for ScenarioDescription {
+    fn default() -> Self {
+        Self {
+            name: "Custom".to_string(),
+            world_size: 40000.0,
+            max_ticks: super::DEFAULT_TUTORIAL_MAX_TICKS,
+            victory: VictoryCondition::DestroyEnemies,
+            ships: vec![],
+            enemy_ais: BTreeMap::new(),
+        }
+    }
+}
+
+impl ScenarioDescription {
+    /// Returns the name to load this scenario with once registered, which
+    /// is the same for identical descriptions.
+    pub fn scenario_name(&self) -> String {
+        let json = serde_json::to_string(self).unwrap();
+        let hash = Sha256::digest(json.as_bytes());
+        let id: String = hash[..8].iter().map(|b| format!("{b:02x}")).collect();
+        format!("{PREFIX}{id}")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let description: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        description.validate()?;
+        Ok(description)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1000.0..=MAX_WORLD_SIZE).contains(&self.world_size) {
+            return Err(format!(
+                "World size must be between 1000 and {MAX_WORLD_SIZE}"
+            ));
+        }
+        if self.max_ticks == 0 || self.max_ticks > MAX_TICKS {
+            return Err(format!("Max ticks must be between 1 and {MAX_TICKS}"));
+        }
+        if self.ships.len() > MAX_SHIPS {
+            return Err(format!("At most {MAX_SHIPS} ships are allowed"));
+        }
+        for (i, ship) in self.ships.iter().enumerate() {
+            let pos = ship.position;
+            ship_data(ship.class, ship.team).map_err(|e| format!("Ship {i}: {e}"))?;
+            if !(0..MAX_TEAMS).contains(&ship.team) {
+                return Err(format!("Ship {i} has invalid team {}", ship.team));
+            }
+            if !pos
+                .iter()
+                .chain(ship.velocity.iter())
+                .all(|x| x.is_finite())
+                || !ship.heading.is_finite()
+                || pos.x.abs() > self.world_size / 2.0
+                || pos.y.abs() > self.world_size / 2.0
+            {
+                return Err(format!("Ship {i} is out of bounds"));
+            }
+        }
+        for (team, ai) in self.enemy_ais.iter() {
+            if !(1..MAX_TEAMS).contains(team) {
+                return Err(format!("Invalid enemy team {team}"));
+            }
+            if !ENEMY_AIS.contains(&ai.as_str()) {
+                return Err(format!("Unknown AI {ai:?} for team {team}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A ship class that can't be placed in a custom scenario.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnsupportedClass(pub ShipClass);
+
+impl std::fmt::Display for UnsupportedClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported class {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedClass {}
+
+pub fn ship_data(class: ShipClass, team: i32) -> Result<ShipData, UnsupportedClass> {
+    Ok(match class {
+        ShipClass::Fighter => fighter(team),
+        ShipClass::Frigate => frigate(team),
+        ShipClass::Cruiser => cruiser(team),
+        ShipClass::Missile => missile(team),
+        ShipClass::Torpedo => torpedo(team),
+        ShipClass::Target => target(team),
+        ShipClass::Asteroid { variant } => ShipData {
+            team,
+            ..asteroid(variant)
+        },
+        ShipClass::BigAsteroid { variant } => ShipData {
+            team,
+            ..big_asteroid(variant)
+        },
+        ShipClass::Planet => return Err(UnsupportedClass(class)),
+    })
+}
+
+pub struct CustomScenario {
+    name: String,
+    description: ScenarioDescription,
+}
+
+impl CustomScenario {
+    pub fn load(name: &str) -> Option<Self> {
+        let Some(description) = lookup(name) else {
+            log::warn!("Custom scenario {} is not registered", name);
+            return None;
+        };
+        Some(Self {
+            name: name.to_string(),
+            description,
+        })
+    }
+}
+
+impl Scenario for CustomScenario {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn human_name(&self) -> String {
+        self.description.name.clone()
+    }
+
+    fn init(&mut self, sim: &mut Simulation, _seed: u32) {
+        for ship in self.description.ships.iter() {
+            match ship_data(ship.class, ship.team) {
+                Ok(data) => {
+                    ship::create(sim, ship.position, ship.velocity, ship.heading, data);
+                }
+                Err(e) => log::warn!("Skipping ship: {}", e),
+            }
+        }
+    }
+
+    fn status(&self, sim: &Simulation) -> Status {
+        let max_ticks = self.description.max_ticks;
+        match self.description.victory {
+            VictoryCondition::DestroyEnemies => check_tutorial_victory(sim, max_ticks),
+            VictoryCondition::LastTeamStanding => {
+                check_victory_with_filter(sim, max_ticks, |ship| {
+                    [ShipClass::Fighter, ShipClass::Frigate, ShipClass::Cruiser]
+                        .contains(&ship.data().class)
+                })
+            }
+            VictoryCondition::Survive => {
+                let alive = sim.ships.iter().any(|&handle| {
+                    let ship = sim.ship(handle);
+                    ship.data().team == 0
+                        && ![ShipClass::Missile, ShipClass::Torpedo].contains(&ship.data().class)
+                });
+                if !alive {
+                    Status::Failed
+                } else if sim.tick() >= max_ticks - 1 {
+                    Status::Victory { team: 0 }
+                } else {
+                    Status::Running
+                }
+            }
+        }
+    }
+
+    fn initial_code(&self) -> Vec<Code> {
+        let num_teams = self
+            .description
+            .ships
+            .iter()
+            .map(|ship| ship.team + 1)
+            .chain(self.description.enemy_ais.keys().map(|team| team + 1))
+            .max()
+            .unwrap_or(1);
+        (0..num_teams)
+            .map(|team| match self.description.enemy_ais.get(&team) {
+                Some(ai) if team > 0 => builtin(ai),
+                _ => empty_ai(),
+            })
+            .collect()
+    }
+
+    fn world_size(&self) -> f64 {
+        self.description.world_size
+    }
+}
@@ -1,6 +1,7 @@
 mod asteroid_duel;
 mod belt;
 mod cruiser_duel;
+pub mod custom;
 mod fighter_duel;
 mod fleet;
 mod frigate_duel;
@@ -31,6 +32,7 @@ mod welcome;
 
 use crate::ship::{asteroid, fighter, ShipAccessor, ShipClass, ShipData};
 use crate::simulation::{Code, Line, Simulation};
+use crate::vm::ResourceLimits;
 use nalgebra::{vector, Vector2};
 use rand::{seq::SliceRandom, Rng, RngCore};
 use serde::{Deserialize, Serialize};
@@ -122,6 +124,10 @@ pub trait Scenario {
     fn world_size(&self) -> f64 {
         40000.0
     }
+
+    fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits::default()
+    }
 }
 
 pub fn load_safe(name: &str) -> Option<Box<dyn Scenario>> {
@@ -162,6 +168,7 @@ pub fn load_safe(name: &str) -> Option<Box<dyn Scenario>> {
         "race" => Some(Box::new(race::Race::new())),
         // Testing
         "test" => Some(Box::new(test::TestScenario {})),
+        "limits_test" => Some(Box::new(test::LimitsTestScenario {})),
         "basic" => Some(Box::new(test::BasicScenario {})),
         "missile_test" => Some(Box::new(test::MissileTest::new())),
         "frigate_vs_cruiser" => Some(Box::new(test::FrigateVsCruiser::new())),
@@ -175,6 +182,9 @@ pub fn load_safe(name: &str) -> Option<Box<dyn Scenario>> {
         "missile-stress" => Some(Box::new(stress::MissileStressScenario {})),
         // Miscellaneous
         "welcome" => Some(Box::new(welcome::Welcome::new())),
+        // Scenario editor
+        name if name.starts_with(custom::PREFIX) => custom::CustomScenario::load(name)
+            .map(|scenario| Box::new(scenario) as Box<dyn Scenario>),
         _ => None,
     };
     if let Some(scenario) = scenario.as_ref() {
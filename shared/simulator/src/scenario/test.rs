@@ -1,4 +1,5 @@
 use super::prelude::*;
+use crate::vm::ResourceLimits;
 use crate::{bullet, simulation};
 
 pub struct TestScenario {}
@@ -15,6 +16,28 @@ impl Scenario for TestScenario {
     }
 }
 
+/// Like "test" but with a tiny instruction budget.
+pub struct LimitsTestScenario {}
+
+impl Scenario for LimitsTestScenario {
+    fn name(&self) -> String {
+        "limits_test".into()
+    }
+
+    fn init(&mut self, _sim: &mut Simulation, _seed: u32) {}
+
+    fn world_size(&self) -> f64 {
+        simulation::MAX_WORLD_SIZE
+    }
+
+    fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            gas_per_tick: 100,
+            ..Default::default()
+        }
+    }
+}
+
 pub struct BasicScenario {}
 
 impl Scenario for BasicScenario {
@@ -7,6 +7,7 @@ use crate::radio::Radio;
 use crate::rng;
 use crate::simulation::{self, PHYSICS_TICK_LENGTH};
 use crate::simulation::{Particle, Simulation};
+use crate::vm::Crash;
 use crate::{bullet, collision};
 use bullet::BulletData;
 use nalgebra::{vector, Rotation2, UnitComplex, Vector2};
@@ -32,6 +33,12 @@ impl From<ShipHandle> for u64 {
     }
 }
 
+impl From<u64> for ShipHandle {
+    fn from(id: u64) -> ShipHandle {
+        ShipHandle(Index::from_raw_parts((id >> 32) as u32, id as u32))
+    }
+}
+
 #[derive(Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, Debug, PartialOrd, Ord)]
 pub enum ShipClass {
     Fighter,
@@ -123,7 +130,8 @@ pub struct ShipData {
     pub max_lateral_acceleration: f64,
     pub max_angular_acceleration: f64,
     pub destroyed: bool,
-    pub crash_message: Option<String>,
+    pub crash: Option<Crash>,
+    pub peak_memory: Option<u32>,
     pub ttl: Option<u64>,
     pub fuel: Option<f64>,
     pub guns: Vec<Gun>,
@@ -173,7 +181,8 @@ impl Default for ShipData {
             max_lateral_acceleration: 0.0,
             max_angular_acceleration: 0.0,
             destroyed: false,
-            crash_message: None,
+            crash: None,
+            peak_memory: None,
             ttl: None,
             fuel: None,
             guns: vec![],
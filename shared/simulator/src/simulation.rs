@@ -3,6 +3,7 @@ use crate::collision;
 use crate::debug;
 pub use crate::debug::Line;
 use crate::index_set::{HasIndex, IndexSet};
+use crate::pilot::PilotInput;
 use crate::radar;
 use crate::radio;
 use crate::scenario;
@@ -20,11 +21,13 @@ use rapier2d_f64::data::Coarena;
 use rapier2d_f64::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::rc::Rc;
 
 pub const MAX_WORLD_SIZE: f64 = 200000.0;
 pub const PHYSICS_TICK_LENGTH: f64 = 1.0 / 60.0;
+pub const MAX_PERSISTENT_SHAPES: usize = 1024;
+pub const MAX_DEBUG_LAYERS: usize = 64;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, Hash, PartialEq)]
 pub enum Code {
@@ -32,8 +35,12 @@ pub enum Code {
     Rust(String),
     Wasm(Vec<u8>),
     Builtin(String),
+    /// A module compiled by `vm::precompile` for the given memory limit.
     #[cfg(feature = "precompile")]
-    Precompiled(bytes::Bytes),
+    Precompiled {
+        module: bytes::Bytes,
+        submemory_size: u32,
+    },
 }
 
 pub struct Simulation {
@@ -63,6 +70,14 @@ pub struct Simulation {
     timing: Timing,
     pub(crate) rng: ChaCha8Rng,
     world_size: f64,
+    resource_limits: vm::ResourceLimits,
+    pilot_inputs: HashMap<ShipHandle, PilotInput>,
+    /// Debug shapes drawn with a time-to-live, by ship, with the tick they
+    /// expire on.
+    persistent_shapes: BTreeMap<u64, VecDeque<(u32, debug::Shape)>>,
+    /// Series from `dbg::plot`, by ship and name.
+    plots: BTreeMap<u64, BTreeMap<String, debug::PlotSeries>>,
+    debug_layers: Vec<String>,
 }
 
 impl Simulation {
@@ -102,6 +117,11 @@ impl Simulation {
             timing: Default::default(),
             rng: crate::rng::new_rng(seed),
             world_size: scenario.world_size(),
+            resource_limits: scenario.resource_limits(),
+            pilot_inputs: HashMap::new(),
+            persistent_shapes: BTreeMap::new(),
+            plots: BTreeMap::new(),
+            debug_layers: Vec::new(),
         });
 
         for (team, code) in codes.iter().enumerate() {
@@ -164,6 +184,24 @@ impl Simulation {
     pub fn step(self: &mut Simulation) {
         self.events.clear();
         self.timing = Default::default();
+        let tick = self.tick;
+        self.persistent_shapes.retain(|_, shapes| {
+            shapes.retain(|(expiry, _)| *expiry > tick);
+            !shapes.is_empty()
+        });
+        let ships = &self.ships;
+        self.plots.retain(|&id, series| {
+            series.retain(|_, points| {
+                while points
+                    .front()
+                    .is_some_and(|&(t, _)| t + debug::MAX_PLOT_POINTS <= tick)
+                {
+                    points.pop_front();
+                }
+                !points.is_empty()
+            });
+            !series.is_empty() && ships.contains(id.into())
+        });
 
         let new_ships = std::mem::take(&mut self.new_ships);
         for (team, handle) in new_ships.iter() {
@@ -245,7 +283,7 @@ impl Simulation {
     }
 
     pub fn upload_code(&mut self, team: i32, code: &Code) {
-        match vm::new_team_controller(code) {
+        match vm::new_team_controller(code, &self.resource_limits) {
             Ok(team_ctrl) => {
                 self.team_controllers
                     .insert(team, Rc::new(RefCell::new(team_ctrl)));
@@ -257,6 +295,72 @@ impl Simulation {
         }
     }
 
+    /// Replaces a team's code in a running simulation. The team's existing
+    /// ships start over with fresh memory in the new code, including ships
+    /// that crashed. If the new code fails to load, the old code keeps
+    /// running.
+    pub fn reload_code(&mut self, team: i32, code: &Code) {
+        if matches!(code, Code::None) {
+            self.team_controllers.remove(&team);
+            return;
+        }
+        let mut team_ctrl = match vm::new_team_controller(code, &self.resource_limits) {
+            Ok(team_ctrl) => team_ctrl,
+            Err(e) => {
+                log::warn!("Creating team controller failed: {:?}", e);
+                self.events.errors.push(e);
+                return;
+            }
+        };
+        if let Some(old_team_ctrl) = self.get_team_controller(team) {
+            let environment = old_team_ctrl.borrow().environment().clone();
+            if let Err(e) = team_ctrl.update_environment(&environment) {
+                self.events.errors.push(e);
+                return;
+            }
+        }
+
+        // Ships created this tick are added to the new controller in step().
+        let handles: Vec<ShipHandle> = self
+            .ships
+            .iter()
+            .copied()
+            .filter(|&handle| self.ship(handle).data().team == team)
+            .filter(|&handle| !self.new_ships.contains(&(team, handle)))
+            .collect();
+        for &handle in handles.iter() {
+            if let Err(e) = team_ctrl.add_ship(handle, self) {
+                log::warn!("Ship creation error: {:?}", e);
+                self.events.errors.push(e);
+                return;
+            }
+        }
+        for handle in handles {
+            self.ship_mut(handle).data_mut().crash = None;
+        }
+        self.team_controllers
+            .insert(team, Rc::new(RefCell::new(team_ctrl)));
+    }
+
+    /// Overrides a ship's movement and first gun with keyboard inputs, or
+    /// returns it to AI control if `input` is None. The rest of the ship's
+    /// systems stay under AI control. Piloting a ship counts as cheating.
+    pub fn set_pilot_input(&mut self, handle: ShipHandle, input: Option<PilotInput>) {
+        match input {
+            Some(input) => {
+                self.cheats = true;
+                self.pilot_inputs.insert(handle, input);
+            }
+            None => {
+                self.pilot_inputs.remove(&handle);
+            }
+        }
+    }
+
+    pub fn pilot_input(&self, handle: ShipHandle) -> Option<PilotInput> {
+        self.pilot_inputs.get(&handle).copied()
+    }
+
     pub fn events(&self) -> &SimEvents {
         &self.events
     }
@@ -269,6 +373,35 @@ impl Simulation {
         self.events.debug_lines.push((ship.into(), lines));
     }
 
+    /// Adds debug shapes along with their time-to-live in ticks.
+    pub fn emit_debug_shapes(&mut self, ship: ShipHandle, shapes: Vec<(u32, debug::Shape)>) {
+        let id: u64 = ship.into();
+        for (ttl, shape) in shapes {
+            if ttl <= 1 {
+                self.events.debug_shapes.entry(id).or_default().push(shape);
+            } else {
+                let persistent = self.persistent_shapes.entry(id).or_default();
+                persistent.push_back((self.tick + ttl, shape));
+                if persistent.len() > MAX_PERSISTENT_SHAPES {
+                    persistent.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Returns the layer index for a debug layer name, or the default layer if
+    /// there are too many.
+    pub fn debug_layer(&mut self, name: &str) -> u8 {
+        if let Some(i) = self.debug_layers.iter().position(|x| x == name) {
+            (i + 1) as u8
+        } else if self.debug_layers.len() < MAX_DEBUG_LAYERS {
+            self.debug_layers.push(name.to_string());
+            self.debug_layers.len() as u8
+        } else {
+            0
+        }
+    }
+
     pub fn emit_debug_text(&mut self, ship: ShipHandle, s: String) {
         use std::collections::btree_map::Entry;
         match self.events.debug_text.entry(ship.into()) {
@@ -289,6 +422,26 @@ impl Simulation {
             .extend(texts.iter().cloned());
     }
 
+    pub fn emit_debug_values(&mut self, ship: ShipHandle, values: Vec<debug::Value>) {
+        let tick = self.tick;
+        for value in values.iter().filter(|v| v.kind == debug::ValueKind::Plot) {
+            let series = self.plots.entry(ship.into()).or_default();
+            if !series.contains_key(&value.name) && series.len() >= debug::MAX_PLOT_SERIES {
+                continue;
+            }
+            let points = series.entry(value.name.clone()).or_default();
+            if points.back().is_some_and(|&(t, _)| t == tick) {
+                points.pop_back();
+            }
+            points.push_back((tick, value.value));
+        }
+        self.events
+            .debug_values
+            .entry(ship.into())
+            .or_default()
+            .extend(values);
+    }
+
     pub fn write_target(&mut self, ship: ShipHandle, p: Vector2<f64>, v: Vector2<f64>) {
         self.ship_mut(ship).data_mut().target = Some(Box::new(Target {
             position: p,
@@ -333,13 +486,27 @@ impl Simulation {
             debug_lines: self.events.debug_lines.clone(),
             debug_text: self.events.debug_text.clone(),
             drawn_text: self.events.drawn_text.clone(),
+            debug_values: self.events.debug_values.clone(),
+            plots: self.plots.clone(),
+            debug_shapes: self.events.debug_shapes.clone(),
+            debug_layers: self.debug_layers.clone(),
+            crashes: BTreeMap::new(),
             particles: self.events.particles.clone(),
             errors: self.events.errors.clone(),
             cheats: self.cheats,
             timing: self.timing.clone(),
             world_size: self.world_size,
+            memory_limit: self.resource_limits.submemory_size,
         };
 
+        for (id, shapes) in self.persistent_shapes.iter() {
+            snapshot
+                .debug_shapes
+                .entry(*id)
+                .or_default()
+                .extend(shapes.iter().map(|(_, shape)| shape.clone()));
+        }
+
         for &handle in self.ships.iter() {
             let ship = self.ship(handle);
             let id = handle.into();
@@ -349,6 +516,7 @@ impl Simulation {
             let class = ship.data().class;
             let health = ship.data().health;
             let fuel = ship.data().fuel;
+            let peak_memory = ship.data().peak_memory;
             snapshot.ships.push(ShipSnapshot {
                 id,
                 position,
@@ -361,7 +529,11 @@ impl Simulation {
                 health,
                 fuel,
                 active_abilities: ship.active_abilities(),
+                peak_memory,
             });
+            if let Some(crash) = ship.data().crash.as_ref() {
+                snapshot.crashes.insert(id, crash.clone());
+            }
         }
 
         for &handle in self.bullets.iter() {
@@ -446,6 +618,8 @@ pub struct SimEvents {
     pub debug_lines: Vec<(u64, Vec<Line>)>,
     pub debug_text: BTreeMap<u64, String>,
     pub drawn_text: BTreeMap<Option<u64>, Vec<Text>>,
+    pub debug_values: BTreeMap<u64, Vec<debug::Value>>,
+    pub debug_shapes: BTreeMap<u64, Vec<debug::Shape>>,
 }
 
 impl SimEvents {
@@ -456,6 +630,8 @@ impl SimEvents {
             debug_lines: Vec::new(),
             debug_text: BTreeMap::new(),
             drawn_text: BTreeMap::new(),
+            debug_values: BTreeMap::new(),
+            debug_shapes: BTreeMap::new(),
         }
     }
 
@@ -465,6 +641,8 @@ impl SimEvents {
         self.debug_lines.clear();
         self.debug_text.clear();
         self.drawn_text.clear();
+        self.debug_values.clear();
+        self.debug_shapes.clear();
     }
 }
 
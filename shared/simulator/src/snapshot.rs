@@ -1,3 +1,4 @@
+use crate::debug;
 use crate::scenario::Status;
 use crate::ship::ShipClass;
 use crate::simulation::{Line, Particle};
@@ -22,8 +23,27 @@ pub struct Snapshot {
     pub debug_lines: Vec<(u64, Vec<Line>)>,
     pub debug_text: BTreeMap<u64, String>,
     pub drawn_text: BTreeMap<Option<u64>, Vec<Text>>,
+    /// Values from `dbg::plot` and `dbg::watch` this tick, by ship.
+    #[serde(default)]
+    pub debug_values: BTreeMap<u64, Vec<debug::Value>>,
+    /// Recent values from `dbg::plot`, by ship and name.
+    #[serde(default)]
+    pub plots: BTreeMap<u64, BTreeMap<String, debug::PlotSeries>>,
+    /// Shapes from the `dbg` drawing functions, by ship, including ones
+    /// drawn on earlier ticks that haven't expired.
+    #[serde(default)]
+    pub debug_shapes: BTreeMap<u64, Vec<debug::Shape>>,
+    /// Names of the debug layers, indexed by `debug::Shape::layer` minus one.
+    #[serde(default)]
+    pub debug_layers: Vec<String>,
+    /// Why each crashed ship crashed, by ship.
+    #[serde(default)]
+    pub crashes: BTreeMap<u64, vm::Crash>,
     pub timing: Timing,
     pub world_size: f64,
+    /// Size of each ship's memory in bytes, for showing peak usage.
+    #[serde(default)]
+    pub memory_limit: u32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -39,6 +59,8 @@ pub struct ShipSnapshot {
     pub health: f64,
     pub fuel: Option<f64>,
     pub active_abilities: Vec<Ability>,
+    /// Peak memory used by the ship's AI, in bytes.
+    pub peak_memory: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
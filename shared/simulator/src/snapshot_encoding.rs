@@ -0,0 +1,428 @@
+//! A compact encoding for sending snapshots from the simulation worker to the
+//! UI.
+//!
+//! Each frame is a delta against the previous snapshot sent. Ships that
+//! haven't changed and bullets that kept flying in a straight line are
+//! referenced instead of repeated, plot series only include the points added
+//! since, and positions and velocities are quantized to f32, which is plenty
+//! for rendering. Frames must be decoded in the order they were encoded.
+use crate::debug::PlotSeries;
+use crate::ship::ShipClass;
+use crate::snapshot::{BulletSnapshot, ShipSnapshot, Snapshot};
+use nalgebra::{Point2, Vector2};
+use oort_api::Ability;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How far a bullet can be from where it was predicted to be and still be
+/// encoded as having moved, in meters.
+const BULLET_TOLERANCE: f64 = 0.1;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Frame {
+    /// Whether this frame is relative to the previous one.
+    delta: bool,
+    /// Everything except ships and bullets. Scenario lines and debug shapes
+    /// that are the same as in the previous snapshot are left out.
+    rest: Snapshot,
+    ships: Vec<ShipEntry>,
+    bullets: Vec<BulletRun>,
+    same_scenario_lines: bool,
+    /// Ships whose debug shapes are the same as in the previous snapshot.
+    same_debug_shapes: Vec<u64>,
+    /// Plot series that continue ones in the previous snapshot. They're
+    /// left out of `rest`.
+    plot_deltas: Vec<PlotDelta>,
+}
+
+impl Frame {
+    pub fn is_delta(&self) -> bool {
+        self.delta
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum ShipEntry {
+    /// Same as in the previous snapshot.
+    Same(u64),
+    Changed(QuantizedShip),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct QuantizedShip {
+    id: u64,
+    position: Point2<f32>,
+    velocity: Vector2<f32>,
+    acceleration: Vector2<f32>,
+    heading: f32,
+    angular_velocity: f32,
+    health: f32,
+    fuel: Option<f32>,
+    /// Left out if the same as in the previous snapshot.
+    details: Option<ShipDetails>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ShipDetails {
+    team: i32,
+    class: ShipClass,
+    active_abilities: Vec<Ability>,
+    peak_memory: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PlotDelta {
+    ship: u64,
+    name: String,
+    /// Number of points removed from the start of the previous series.
+    dropped: u32,
+    /// Points appended to the end.
+    added: Vec<(u32, f64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum BulletRun {
+    /// Bullets `start..start + count` of the previous snapshot, moved
+    /// forward to the time of this one.
+    Moved {
+        start: u32,
+        count: u32,
+    },
+    New(Vec<QuantizedBullet>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct QuantizedBullet {
+    position: Point2<f32>,
+    velocity: Vector2<f32>,
+    color: u32,
+    ttl: f32,
+}
+
+/// Encodes snapshots on the sending side.
+#[derive(Default)]
+pub struct Encoder {
+    /// The previous snapshot as the decoder will see it.
+    previous: Option<Snapshot>,
+}
+
+impl Encoder {
+    /// Makes the next frame a full snapshot, e.g. when starting a new
+    /// scenario.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn encode(&mut self, snapshot: Snapshot) -> Frame {
+        let previous = self.previous.take();
+        let frame = encode(previous.as_ref(), snapshot);
+        self.previous = apply(previous, frame.clone());
+        frame
+    }
+}
+
+/// Decodes frames on the receiving side.
+#[derive(Default)]
+pub struct Decoder {
+    previous: Option<Snapshot>,
+}
+
+impl Decoder {
+    /// Returns None if the frame refers to a snapshot this decoder hasn't
+    /// seen.
+    pub fn decode(&mut self, frame: Frame) -> Option<Snapshot> {
+        let snapshot = apply(self.previous.take(), frame)?;
+        self.previous = Some(snapshot.clone());
+        Some(snapshot)
+    }
+}
+
+fn encode(previous: Option<&Snapshot>, mut snapshot: Snapshot) -> Frame {
+    let ships = std::mem::take(&mut snapshot.ships);
+    let bullets = std::mem::take(&mut snapshot.bullets);
+    let Some(previous) = previous else {
+        return Frame {
+            delta: false,
+            rest: snapshot,
+            ships: ships
+                .iter()
+                .map(|ship| ShipEntry::Changed(QuantizedShip::new(ship)))
+                .collect(),
+            bullets: encode_bullets(&[], 0.0, &bullets),
+            same_scenario_lines: false,
+            same_debug_shapes: vec![],
+            plot_deltas: vec![],
+        };
+    };
+
+    let previous_ships: HashMap<u64, &ShipSnapshot> =
+        previous.ships.iter().map(|ship| (ship.id, ship)).collect();
+    let ships = ships
+        .iter()
+        .map(|ship| {
+            let Some(&previous_ship) = previous_ships.get(&ship.id) else {
+                return ShipEntry::Changed(QuantizedShip::new(ship));
+            };
+            let mut quantized = QuantizedShip::new(ship);
+            let previous_quantized = QuantizedShip::new(previous_ship);
+            if quantized == previous_quantized {
+                return ShipEntry::Same(ship.id);
+            }
+            if quantized.details == previous_quantized.details {
+                quantized.details = None;
+            }
+            ShipEntry::Changed(quantized)
+        })
+        .collect();
+
+    let same_scenario_lines = snapshot.scenario_lines == previous.scenario_lines;
+    if same_scenario_lines {
+        snapshot.scenario_lines.clear();
+    }
+
+    let mut same_debug_shapes = vec![];
+    snapshot.debug_shapes.retain(|id, shapes| {
+        if previous.debug_shapes.get(id) == Some(shapes) {
+            same_debug_shapes.push(*id);
+            false
+        } else {
+            true
+        }
+    });
+
+    let mut plot_deltas = vec![];
+    for (&ship, series) in snapshot.plots.iter_mut() {
+        let Some(previous_series) = previous.plots.get(&ship) else {
+            continue;
+        };
+        series.retain(|name, points| {
+            let Some(previous_points) = previous_series.get(name) else {
+                return true;
+            };
+            let Some((dropped, added)) = encode_plot(previous_points, points) else {
+                return true;
+            };
+            plot_deltas.push(PlotDelta {
+                ship,
+                name: name.clone(),
+                dropped,
+                added,
+            });
+            false
+        });
+    }
+
+    let dt = snapshot.time - previous.time;
+    let bullets = encode_bullets(&previous.bullets, dt, &bullets);
+
+    Frame {
+        delta: true,
+        rest: snapshot,
+        ships,
+        bullets,
+        same_scenario_lines,
+        same_debug_shapes,
+        plot_deltas,
+    }
+}
+
+/// Returns how many points were dropped from the start of `previous` and
+/// which points were appended to get `points`, or None if `points` doesn't
+/// continue `previous`.
+fn encode_plot(previous: &PlotSeries, points: &PlotSeries) -> Option<(u32, Vec<(u32, f64)>)> {
+    let last_tick = previous.back()?.0;
+    let kept = points.partition_point(|&(tick, _)| tick <= last_tick);
+    let dropped = previous.len().checked_sub(kept)?;
+    if !previous.range(dropped..).eq(points.range(..kept)) {
+        return None;
+    }
+    Some((dropped as u32, points.range(kept..).copied().collect()))
+}
+
+/// Matches each bullet against where the previous bullets would be now,
+/// grouping matches with consecutive indices into runs.
+fn encode_bullets(
+    previous: &[BulletSnapshot],
+    dt: f64,
+    bullets: &[BulletSnapshot],
+) -> Vec<BulletRun> {
+    // Bullets fly in a straight line, so a bullet's velocity and color
+    // identify it well enough to find candidates.
+    let mut candidates: HashMap<(u32, u32, u32), Vec<usize>> = HashMap::new();
+    for (i, bullet) in previous.iter().enumerate() {
+        candidates
+            .entry(QuantizedBullet::new(bullet).key())
+            .or_default()
+            .push(i);
+    }
+
+    let mut runs: Vec<BulletRun> = vec![];
+    for bullet in bullets.iter() {
+        let quantized = QuantizedBullet::new(bullet);
+        let matched = candidates.get_mut(&quantized.key()).and_then(|indices| {
+            let j = indices.iter().position(|&i| {
+                (advance(&previous[i], dt).position - bullet.position).norm() < BULLET_TOLERANCE
+            })?;
+            Some(indices.remove(j) as u32)
+        });
+        match (matched, runs.last_mut()) {
+            (Some(i), Some(BulletRun::Moved { start, count })) if *start + *count == i => {
+                *count += 1;
+            }
+            (Some(i), _) => runs.push(BulletRun::Moved { start: i, count: 1 }),
+            (None, Some(BulletRun::New(new_bullets))) => new_bullets.push(quantized),
+            (None, _) => runs.push(BulletRun::New(vec![quantized])),
+        }
+    }
+    runs
+}
+
+fn apply(previous: Option<Snapshot>, frame: Frame) -> Option<Snapshot> {
+    let previous = if frame.delta { Some(previous?) } else { None };
+    let mut snapshot = frame.rest;
+
+    let previous_ships: HashMap<u64, &ShipSnapshot> = previous
+        .iter()
+        .flat_map(|previous| previous.ships.iter())
+        .map(|ship| (ship.id, ship))
+        .collect();
+    for entry in frame.ships.into_iter() {
+        let ship = match entry {
+            ShipEntry::Same(id) => (*previous_ships.get(&id)?).clone(),
+            ShipEntry::Changed(quantized) => {
+                let previous_ship = previous_ships.get(&quantized.id).copied();
+                quantized.dequantize(previous_ship)?
+            }
+        };
+        snapshot.ships.push(ship);
+    }
+
+    let dt = previous
+        .as_ref()
+        .map(|previous| snapshot.time - previous.time)
+        .unwrap_or_default();
+    for run in frame.bullets.into_iter() {
+        match run {
+            BulletRun::Moved { start, count } => {
+                let previous_bullets = previous
+                    .as_ref()?
+                    .bullets
+                    .get(start as usize..(start + count) as usize)?;
+                snapshot
+                    .bullets
+                    .extend(previous_bullets.iter().map(|bullet| advance(bullet, dt)));
+            }
+            BulletRun::New(bullets) => {
+                snapshot
+                    .bullets
+                    .extend(bullets.iter().map(QuantizedBullet::dequantize));
+            }
+        }
+    }
+
+    if frame.same_scenario_lines {
+        snapshot.scenario_lines = previous.as_ref()?.scenario_lines.clone();
+    }
+    for id in frame.same_debug_shapes.into_iter() {
+        let shapes = previous.as_ref()?.debug_shapes.get(&id)?.clone();
+        snapshot.debug_shapes.insert(id, shapes);
+    }
+    for delta in frame.plot_deltas.into_iter() {
+        let mut points = previous
+            .as_ref()?
+            .plots
+            .get(&delta.ship)?
+            .get(&delta.name)?
+            .clone();
+        if delta.dropped as usize > points.len() {
+            return None;
+        }
+        points.drain(..delta.dropped as usize);
+        points.extend(delta.added);
+        snapshot
+            .plots
+            .entry(delta.ship)
+            .or_default()
+            .insert(delta.name, points);
+    }
+
+    Some(snapshot)
+}
+
+impl QuantizedShip {
+    fn new(ship: &ShipSnapshot) -> Self {
+        Self {
+            id: ship.id,
+            position: ship.position.cast(),
+            velocity: ship.velocity.cast(),
+            acceleration: ship.acceleration.cast(),
+            heading: ship.heading as f32,
+            angular_velocity: ship.angular_velocity as f32,
+            health: ship.health as f32,
+            fuel: ship.fuel.map(|fuel| fuel as f32),
+            details: Some(ShipDetails {
+                team: ship.team,
+                class: ship.class,
+                active_abilities: ship.active_abilities.clone(),
+                peak_memory: ship.peak_memory,
+            }),
+        }
+    }
+
+    fn dequantize(self, previous: Option<&ShipSnapshot>) -> Option<ShipSnapshot> {
+        let details = match self.details {
+            Some(details) => details,
+            None => QuantizedShip::new(previous?).details?,
+        };
+        Some(ShipSnapshot {
+            id: self.id,
+            position: self.position.cast(),
+            velocity: self.velocity.cast(),
+            acceleration: self.acceleration.cast(),
+            heading: self.heading as f64,
+            angular_velocity: self.angular_velocity as f64,
+            team: details.team,
+            class: details.class,
+            health: self.health as f64,
+            fuel: self.fuel.map(|fuel| fuel as f64),
+            active_abilities: details.active_abilities,
+            peak_memory: details.peak_memory,
+        })
+    }
+}
+
+impl QuantizedBullet {
+    fn new(bullet: &BulletSnapshot) -> Self {
+        Self {
+            position: bullet.position.cast(),
+            velocity: bullet.velocity.cast(),
+            color: bullet.color,
+            ttl: bullet.ttl,
+        }
+    }
+
+    fn key(&self) -> (u32, u32, u32) {
+        (
+            self.velocity.x.to_bits(),
+            self.velocity.y.to_bits(),
+            self.color,
+        )
+    }
+
+    fn dequantize(&self) -> BulletSnapshot {
+        BulletSnapshot {
+            position: self.position.cast(),
+            velocity: self.velocity.cast(),
+            color: self.color,
+            ttl: self.ttl,
+        }
+    }
+}
+
+fn advance(bullet: &BulletSnapshot, dt: f64) -> BulletSnapshot {
+    BulletSnapshot {
+        position: bullet.position + bullet.velocity * dt,
+        ttl: bullet.ttl - dt as f32,
+        ..bullet.clone()
+    }
+}
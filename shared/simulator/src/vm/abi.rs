@@ -0,0 +1,158 @@
+// The SystemState layout shared between the simulator and compiled AIs is an
+// ABI. AIs export the version they were compiled against and the simulator
+// translates older layouts to the current one where possible.
+use super::Error;
+use oort_api::SystemState;
+
+/// ABI version of AIs compiled before the version was exported.
+pub const LEGACY_ABI_VERSION: u32 = 0;
+
+/// Older ABI versions that can still run, with the entries in the current
+/// layout that live at a different index in that version.
+///
+/// A `None` index means the entry didn't exist in that version. Entries not
+/// listed are at the same index. Add a row here when bumping `ABI_VERSION`.
+const LAYOUT_CHANGES: &[(u32, &[(SystemState, Option<usize>)])] = &[
+    // Version 0 has the same layout as version 1.
+    (0, VERSION_1_CHANGES),
+    (1, VERSION_1_CHANGES),
+    (2, VERSION_2_CHANGES),
+];
+
+// Version 2 added debug values (plot and watch).
+const VERSION_1_CHANGES: &[(SystemState, Option<usize>)] = &[
+    (SystemState::DebugValuesPointer, None),
+    (SystemState::DebugValuesLength, None),
+    (SystemState::DebugShapesPointer, None),
+    (SystemState::DebugShapesLength, None),
+    (SystemState::DebugLayersPointer, None),
+    (SystemState::DebugLayersLength, None),
+];
+
+// Version 3 added debug shapes and layers.
+const VERSION_2_CHANGES: &[(SystemState, Option<usize>)] = &[
+    (SystemState::DebugShapesPointer, None),
+    (SystemState::DebugShapesLength, None),
+    (SystemState::DebugLayersPointer, None),
+    (SystemState::DebugLayersLength, None),
+];
+
+/// Maps between the current SystemState layout and an older one.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    // Indexed by current SystemState index.
+    indices: Vec<Option<usize>>,
+    size: usize,
+}
+
+impl Layout {
+    fn new(changes: &[(SystemState, Option<usize>)]) -> Self {
+        let mut indices: Vec<Option<usize>> = (0..SystemState::Size as usize).map(Some).collect();
+        for &(key, index) in changes {
+            indices[key as usize] = index;
+        }
+        let size = indices.iter().flatten().map(|&i| i + 1).max().unwrap_or(0);
+        Self { indices, size }
+    }
+
+    /// Number of u64 entries in the AI's layout.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Converts a state in the current layout to the AI's layout.
+    pub fn encode(&self, state: &[u64]) -> Vec<u64> {
+        let mut raw = vec![0; self.size];
+        for (current, index) in self.indices.iter().enumerate() {
+            if let Some(index) = index {
+                raw[*index] = state[current];
+            }
+        }
+        raw
+    }
+
+    /// Converts a state in the AI's layout back to the current layout.
+    pub fn decode(&self, raw: &[u64], state: &mut [u64]) {
+        for (current, index) in self.indices.iter().enumerate() {
+            state[current] = index.map(|i| raw[i]).unwrap_or(0);
+        }
+    }
+}
+
+/// Checks that an AI compiled against `version` can run.
+///
+/// Returns a layout translation if the AI uses an older layout.
+pub fn check(version: u32) -> Result<Option<Layout>, Error> {
+    if version == oort_api::ABI_VERSION {
+        return Ok(None);
+    }
+
+    if version > oort_api::ABI_VERSION {
+        return Err(Error {
+            msg: format!(
+                "AI was compiled against a newer API (ABI version {}, simulator supports up to {}). Try reloading the page.",
+                version,
+                oort_api::ABI_VERSION
+            ),
+        });
+    }
+
+    match LAYOUT_CHANGES.iter().find(|(v, _)| *v == version) {
+        Some((_, [])) => Ok(None),
+        Some((_, changes)) => Ok(Some(Layout::new(changes))),
+        None => Err(Error {
+            msg: format!(
+                "AI was compiled against an API that is no longer supported (ABI version {}). Recompile it with the current version.",
+                version
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert!(check(oort_api::ABI_VERSION).unwrap().is_none());
+        assert!(check(oort_api::ABI_VERSION + 1).is_err());
+
+        let layout = check(1).unwrap().unwrap();
+        assert_eq!(layout.size(), SystemState::Id as usize + 1);
+        assert_eq!(
+            check(LEGACY_ABI_VERSION).unwrap().unwrap().size(),
+            layout.size()
+        );
+        assert_eq!(
+            check(2).unwrap().unwrap().size(),
+            SystemState::DebugValuesLength as usize + 1
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = Layout::new(&[
+            (SystemState::Class, Some(1)),
+            (SystemState::Seed, Some(0)),
+            (SystemState::Id, None),
+        ]);
+        assert_eq!(layout.size(), SystemState::Id as usize);
+
+        let mut state = vec![0; SystemState::Size as usize];
+        state[SystemState::Class as usize] = 10;
+        state[SystemState::Seed as usize] = 20;
+        state[SystemState::Heading as usize] = 30;
+        state[SystemState::Id as usize] = 40;
+
+        let raw = layout.encode(&state);
+        assert_eq!(raw[0], 20);
+        assert_eq!(raw[1], 10);
+        assert_eq!(raw[SystemState::Heading as usize], 30);
+
+        let mut decoded = vec![0; SystemState::Size as usize];
+        layout.decode(&raw, &mut decoded);
+        state[SystemState::Id as usize] = 0;
+        assert_eq!(decoded, state);
+    }
+}
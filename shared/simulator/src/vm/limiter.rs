@@ -1,5 +1,8 @@
 // Based on https://github.com/scrtlabs/SecretNetwork/blob/621d3899babc4741ef1ba596152c097677d246db/cosmwasm/enclaves/shared/contract-engine/src/wasm3/gas.rs
-use walrus::{ir::*, FunctionBuilder, GlobalId, InitExpr, LocalFunction, ValType};
+use std::collections::HashSet;
+use walrus::{
+    ir::*, FunctionBuilder, FunctionId, GlobalId, ImportKind, InitExpr, LocalFunction, ValType,
+};
 
 pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
     let mut module = match walrus::Module::from_buffer(wasm) {
@@ -15,9 +18,20 @@ pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
         .globals
         .add_local(ValType::I32, true, InitExpr::Value(Value::I32(0)));
 
+    // Host math functions are charged a fixed cost per call.
+    let host_funcs: HashSet<FunctionId> = module
+        .imports
+        .iter()
+        .filter(|import| import.module == super::math::MODULE_NAME)
+        .filter_map(|import| match import.kind {
+            ImportKind::Function(func) => Some(func),
+            _ => None,
+        })
+        .collect();
+
     // Rewrite each block to check and decrement gas.
     for (_, func) in module.funcs.iter_local_mut() {
-        rewrite_function(func, gas_global);
+        rewrite_function(func, gas_global, &host_funcs);
     }
 
     // Create a reset_gas(amount) function.
@@ -40,21 +54,36 @@ pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
     Ok(module.emit_wasm())
 }
 
-fn rewrite_function(func: &mut LocalFunction, gas_global: GlobalId) {
+fn rewrite_function(
+    func: &mut LocalFunction,
+    gas_global: GlobalId,
+    host_funcs: &HashSet<FunctionId>,
+) {
     let block_ids: Vec<_> = func.blocks().map(|(block_id, _block)| block_id).collect();
     for block_id in block_ids {
-        rewrite_block(func, block_id, gas_global);
+        rewrite_block(func, block_id, gas_global, host_funcs);
     }
 }
 
 /// Number of injected metering instructions (needed to calculate final instruction size).
 const METERING_INSTRUCTION_COUNT: usize = 8;
 
-fn rewrite_block(func: &mut LocalFunction, block_id: InstrSeqId, gas_global: GlobalId) {
+fn rewrite_block(
+    func: &mut LocalFunction,
+    block_id: InstrSeqId,
+    gas_global: GlobalId,
+    host_funcs: &HashSet<FunctionId>,
+) {
     let block = func.block_mut(block_id);
     let block_instrs = &mut block.instrs;
     let block_len = block_instrs.len();
-    let block_cost = block_len as i32;
+    let host_calls = block_instrs
+        .iter()
+        .filter(
+            |(instr, _)| matches!(instr, Instr::Call(Call { func }) if host_funcs.contains(func)),
+        )
+        .count();
+    let block_cost = block_len as i32 + host_calls as i32 * super::math::GAS_PER_CALL;
 
     let builder = func.builder_mut();
     let mut builder = builder.dangling_instr_seq(None);
@@ -175,4 +204,26 @@ mod tests {
 ",
         );
     }
+
+    #[test]
+    fn test_host_call() {
+        let wasm = wat2wasm(
+            "
+(module
+    (import \"oort_math\" \"sin\" (func $sin (param f64) (result f64)))
+    (func $f (result f64)
+        f64.const 1
+        call $sin
+    )
+)
+",
+        );
+        let new_wasm = rewrite(&wasm).unwrap();
+        let wat = wasm2wat(&new_wasm);
+        let expected_cost = 2 + super::super::math::GAS_PER_CALL;
+        assert!(
+            wat.contains(&format!("i32.const {expected_cost}\n")),
+            "{wat}"
+        );
+    }
 }
@@ -0,0 +1,23 @@
+// Math functions provided to AIs by the host. These use libm so results are
+// identical in native and browser builds. The limiter charges a fixed gas
+// cost for each call.
+use wasmer::{Function, Imports, Store};
+
+pub const MODULE_NAME: &str = "oort_math";
+
+/// Gas charged for each call to a host math function.
+pub const GAS_PER_CALL: i32 = 10;
+
+pub fn define_imports(store: &mut Store, imports: &mut Imports) {
+    let functions = [
+        ("sin", Function::new_typed(store, libm::sin)),
+        ("cos", Function::new_typed(store, libm::cos)),
+        ("atan2", Function::new_typed(store, libm::atan2)),
+        ("sqrt", Function::new_typed(store, libm::sqrt)),
+        ("exp", Function::new_typed(store, libm::exp)),
+        ("ln", Function::new_typed(store, libm::log)),
+    ];
+    for (name, function) in functions {
+        imports.define(MODULE_NAME, name, function);
+    }
+}
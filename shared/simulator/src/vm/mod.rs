@@ -1,16 +1,20 @@
 // TODO clean up error translation
 // TODO add methods to WasmVm for each exported function
 // TODO shift pointers according to headroom + base
+mod abi;
 pub mod builtin;
 mod limiter;
+mod math;
+mod symbols;
 
 use crate::color;
 use crate::debug;
+use crate::pilot::PilotInput;
 use crate::rng::new_rng;
 use crate::ship::{ShipClass, ShipHandle};
 use crate::simulation::{Code, Particle, Simulation, PHYSICS_TICK_LENGTH};
 use nalgebra::{point, vector, Rotation2};
-use oort_api::{ActiveAbilities, Class, EcmMode, Line, SystemState, Text};
+use oort_api::{ActiveAbilities, Class, DebugValue, EcmMode, Line, Shape, SystemState, Text};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::cell::{Ref, RefCell, RefMut};
@@ -24,16 +28,81 @@ use wasmer::{imports, Function, Instance, MemoryView, Module, Store, WasmPtr};
 pub type Vec2 = nalgebra::Vector2<f64>;
 pub type Environment = BTreeMap<String, String>;
 
-const SUBMEMORY_SIZE: u32 = 2 << 20;
-const GAS_PER_TICK: i32 = 1_000_000;
-const MAX_DEBUG_LINES: u32 = 1024;
-const MAX_DRAWN_TEXT: u32 = 128;
+const DEFAULT_SUBMEMORY_SIZE: u32 = 2 << 20;
+const MEMORY_SAMPLE_INTERVAL: u32 = 60;
+const MEMORY_SAMPLE_CHUNK_SIZE: u32 = 4096;
+const MAX_SHIP_DEBUG_LAYERS: usize = 16;
+const MAX_DEBUG_LAYER_NAMES_LENGTH: f64 = 1024.0;
+
+/// Limits on the resources available to each ship's AI.
+///
+/// Scenarios can override these with `Scenario::resource_limits`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Bytes of memory available to each ship.
+    pub submemory_size: u32,
+    /// Instructions each ship can execute per tick.
+    pub gas_per_tick: i32,
+    /// Instructions available to a ship's init function.
+    pub gas_for_init: i32,
+    pub max_debug_lines: u32,
+    pub max_drawn_text: u32,
+    pub max_debug_values: u32,
+    pub max_debug_shapes: u32,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            submemory_size: DEFAULT_SUBMEMORY_SIZE,
+            gas_per_tick: 1_000_000,
+            gas_for_init: 100_000_000,
+            max_debug_lines: 1024,
+            max_drawn_text: 128,
+            max_debug_values: 32,
+            max_debug_shapes: 1024,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Error {
     pub msg: String,
 }
 
+/// A frame of user code on the stack when a ship crashed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub function: String,
+    pub line: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Crash {
+    pub msg: String,
+    /// Innermost first. Empty if the AI has no symbols.
+    pub frames: Vec<Frame>,
+}
+
+impl std::fmt::Display for Crash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)?;
+        if let Some(frame) = self.frames.first() {
+            write!(f, " at line {} in {}", frame.line, frame.function)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Error> for Crash {
+    fn from(e: Error) -> Self {
+        Crash {
+            msg: e.msg,
+            frames: vec![],
+        }
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 impl From<wasm_bindgen::JsValue> for Error {
     fn from(err: wasm_bindgen::JsValue) -> Self {
@@ -59,13 +128,16 @@ impl From<anyhow::Error> for Error {
     }
 }
 
-pub fn new_team_controller(code: &Code) -> Result<Box<TeamController>, Error> {
+pub fn new_team_controller(
+    code: &Code,
+    limits: &ResourceLimits,
+) -> Result<Box<TeamController>, Error> {
     match code {
-        Code::Wasm(_) => TeamController::create(code),
+        Code::Wasm(_) => TeamController::create(code, limits),
         #[cfg(feature = "precompile")]
-        Code::Precompiled(_) => TeamController::create(code),
+        Code::Precompiled { .. } => TeamController::create(code, limits),
         Code::Builtin(name) => match builtin::load_compiled(name) {
-            Ok(code) => new_team_controller(&code),
+            Ok(code) => new_team_controller(&code, limits),
             Err(e) => Err(Error { msg: e }),
         },
         _ => unreachable!(),
@@ -79,10 +151,13 @@ pub struct ShipController {
     system_state_ptr: WasmPtr<u64>,
     environment_ptr: WasmPtr<u8>,
     panic_buffer_ptr: WasmPtr<u8>,
+    initialized: bool,
+    peak_memory: u32,
 }
 
 pub struct TeamController {
     vm: WasmVm,
+    limits: ResourceLimits,
     ship_controllers: HashMap<ShipHandle, ShipController>,
     next_id: u32,
     free_submemories: Vec<(u32, u32)>, // (index, base_address)
@@ -90,9 +165,10 @@ pub struct TeamController {
 }
 
 impl TeamController {
-    pub fn create(code: &Code) -> Result<Box<TeamController>, Error> {
+    pub fn create(code: &Code, limits: &ResourceLimits) -> Result<Box<TeamController>, Error> {
         Ok(Box::new(TeamController {
-            vm: WasmVm::create(code)?,
+            vm: WasmVm::create(code, limits)?,
+            limits: limits.clone(),
             ship_controllers: HashMap::new(),
             next_id: 1,
             free_submemories: Vec::new(),
@@ -144,6 +220,8 @@ impl TeamController {
                 system_state_ptr,
                 environment_ptr,
                 panic_buffer_ptr,
+                initialized: false,
+                peak_memory: 0,
             },
         );
 
@@ -154,7 +232,7 @@ impl TeamController {
         let ship_controller = self.ship_controllers.remove(&handle).unwrap();
         self.vm
             .reset_gas
-            .call(&mut self.vm.store_mut(), &[GAS_PER_TICK.into()])
+            .call(&mut self.vm.store_mut(), &[self.limits.gas_per_tick.into()])
             .unwrap();
         self.vm.reset_submemory(ship_controller.index).unwrap();
         self.free_submemories
@@ -166,17 +244,17 @@ impl TeamController {
         handles.sort_by_key(|x| x.0);
 
         for handle in handles {
-            if let Err(e) = self.tick_ship(sim, handle) {
-                log::warn!("{}", e.msg);
-                sim.emit_debug_text(handle, format!("Crashed: {}", e.msg.clone()));
-                sim.ship_mut(handle).data_mut().crash_message = Some(e.msg);
+            if let Err(crash) = self.tick_ship(sim, handle) {
+                log::warn!("{}", crash);
+                sim.emit_debug_text(handle, format!("Crashed: {}", crash));
+                sim.ship_mut(handle).data_mut().crash = Some(crash);
             }
         }
     }
 
-    fn tick_ship(&mut self, sim: &mut Simulation, handle: ShipHandle) -> Result<(), Error> {
-        if let Some(msg) = sim.ship(handle).data().crash_message.as_ref() {
-            sim.emit_debug_text(handle, format!("Crashed: {}", msg.clone()));
+    fn tick_ship(&mut self, sim: &mut Simulation, handle: ShipHandle) -> Result<(), Crash> {
+        if let Some(crash) = sim.ship(handle).data().crash.as_ref() {
+            sim.emit_debug_text(handle, format!("Crashed: {}", crash));
             let mut rng = new_rng(sim.tick());
             if rng.gen_range(0.0..1.0) < 0.2 {
                 let color = vector![0.5, 0.5, 0.9, rng.gen_range(0.5..1.0)];
@@ -197,125 +275,112 @@ impl TeamController {
         }
 
         let vm = &mut self.vm;
+        let limits = &self.limits;
         let ship_controller = &mut self.ship_controllers.get_mut(&handle).unwrap();
         let state = &mut ship_controller.state;
 
         {
-            translate_runtime_error(
-                vm.reset_gas
-                    .call(vm.store_mut().deref_mut(), &[GAS_PER_TICK.into()]),
-            )?;
-
             vm.select_submemory(ship_controller.index)?;
 
             generate_system_state(sim, handle, state);
 
             let store = vm.store();
             let memory_view = vm.memory.view(store.deref());
-            let ptr = ship_controller.system_state_ptr;
-            let slice = ptr
-                .slice(&memory_view, SystemState::Size as u32)
-                .expect("system state write");
-            slice.write_slice(&state.state).expect("system state write");
+            WasmVm::write_system_state(
+                &memory_view,
+                ship_controller.system_state_ptr,
+                vm.layout.as_ref(),
+                &state.state,
+            );
         }
 
-        let result = vm.tick_ship.call(vm.store_mut().deref_mut(), &[]);
-        if let Err(e) = result {
-            if let Ok(ret) = vm.get_gas.call(vm.store_mut().deref_mut(), &[]) {
-                if !ret.is_empty() {
-                    let gas: i32 = ret[0].i32().unwrap();
-                    if gas <= 0 {
-                        return Err(Error {
-                            msg: "Ship exceeded maximum number of instructions".to_string(),
-                        });
-                    }
+        let first_tick = !ship_controller.initialized;
+        if first_tick {
+            ship_controller.initialized = true;
+            if let Some(init_ship) = vm.init_ship.as_ref() {
+                translate_runtime_error(
+                    vm.reset_gas
+                        .call(vm.store_mut().deref_mut(), &[limits.gas_for_init.into()]),
+                )?;
+                let result = init_ship.call(vm.store_mut().deref_mut(), &[]);
+                if let Err(e) = result {
+                    let mut crash = vm.translate_ship_error(ship_controller.panic_buffer_ptr, e);
+                    crash.msg = format!("Init failed: {}", crash.msg);
+                    return Err(crash);
                 }
-            }
 
-            {
+                // Keep debug output from init, which would otherwise be
+                // reset by the first tick.
                 let store = vm.store();
                 let memory_view = vm.memory.view(store.deref());
-                if let Some(vec) = WasmVm::read_vec(
+                WasmVm::read_system_state(
                     &memory_view,
-                    ship_controller.panic_buffer_ptr.offset(),
-                    oort_api::panic::PANIC_BUFFER_SIZE as u32,
-                ) {
-                    let null_pos = vec.iter().position(|&x| x == 0).unwrap_or(vec.len());
-                    let msg = String::from_utf8_lossy(&vec[0..null_pos]).to_string();
-                    if msg.is_empty() {
-                        return Err(Error {
-                            msg: "Ship exceeded maximum number of instructions".to_string(),
-                        });
-                    } else {
-                        return Err(Error { msg });
-                    }
-                }
+                    ship_controller.system_state_ptr,
+                    vm.layout.as_ref(),
+                    &mut state.state,
+                );
+                emit_debug_output(
+                    sim,
+                    handle,
+                    &memory_view,
+                    ship_controller.base_address,
+                    limits,
+                    state,
+                );
             }
+        }
 
-            return translate_runtime_error(Err(e));
+        translate_runtime_error(
+            vm.reset_gas
+                .call(vm.store_mut().deref_mut(), &[limits.gas_per_tick.into()]),
+        )?;
+
+        let result = vm.tick_ship.call(vm.store_mut().deref_mut(), &[]);
+        if let Err(e) = result {
+            return Err(vm.translate_ship_error(ship_controller.panic_buffer_ptr, e));
         }
 
         {
             let store = vm.store();
             let memory_view = vm.memory.view(store.deref());
-            let ptr = ship_controller.system_state_ptr;
-            let slice = ptr
-                .slice(&memory_view, SystemState::Size as u32)
-                .expect("system state read");
-            slice
-                .read_slice(&mut state.state)
-                .expect("system state read");
-            apply_system_state(sim, handle, state);
-
-            if state.get(SystemState::DebugTextLength) > 0.0 {
-                let offset =
-                    state.get(SystemState::DebugTextPointer) as u32 + ship_controller.base_address;
-                let length = state.get(SystemState::DebugTextLength) as u32;
-                if let Some(s) = WasmVm::read_string(&memory_view, offset, length) {
-                    sim.emit_debug_text(handle, s);
-                }
+            WasmVm::read_system_state(
+                &memory_view,
+                ship_controller.system_state_ptr,
+                vm.layout.as_ref(),
+                &mut state.state,
+            );
+            if let Some(input) = sim.pilot_input(handle) {
+                apply_pilot_input(sim, handle, &input, state);
             }
+            apply_system_state(sim, handle, state);
 
-            if state.get(SystemState::DebugLinesLength) > 0.0 {
-                let offset =
-                    state.get(SystemState::DebugLinesPointer) as u32 + ship_controller.base_address;
-                let length = state.get(SystemState::DebugLinesLength) as u32;
-                if length <= MAX_DEBUG_LINES {
-                    if let Some(lines) = WasmVm::read_vec::<Line>(&memory_view, offset, length) {
-                        if validate_lines(&lines) {
-                            sim.emit_debug_lines(
-                                handle,
-                                lines
-                                    .iter()
-                                    .map(|v| crate::debug::Line {
-                                        a: point![v.x0, v.y0],
-                                        b: point![v.x1, v.y1],
-                                        color: color::from_u24(v.color),
-                                    })
-                                    .collect::<Vec<debug::Line>>(),
-                            );
-                        }
-                    }
-                }
+            if first_tick || (sim.tick() + ship_controller.index) % MEMORY_SAMPLE_INTERVAL == 0 {
+                ship_controller.peak_memory = WasmVm::memory_high_water_mark(
+                    &memory_view,
+                    ship_controller.base_address,
+                    ship_controller.peak_memory,
+                    limits.submemory_size,
+                );
+                sim.ship_mut(handle).data_mut().peak_memory = Some(ship_controller.peak_memory);
             }
 
-            if state.get(SystemState::DrawnTextLength) > 0.0 {
-                let offset =
-                    state.get(SystemState::DrawnTextPointer) as u32 + ship_controller.base_address;
-                let length = state.get(SystemState::DrawnTextLength) as u32;
-                if length <= MAX_DRAWN_TEXT {
-                    if let Some(texts) = WasmVm::read_vec::<Text>(&memory_view, offset, length) {
-                        if validate_texts(&texts) {
-                            sim.emit_drawn_text(Some(handle), &texts);
-                        }
-                    }
-                }
-            }
+            emit_debug_output(
+                sim,
+                handle,
+                &memory_view,
+                ship_controller.base_address,
+                limits,
+                state,
+            );
         }
 
         Ok(())
     }
 
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
     pub fn update_environment(&mut self, environment: &Environment) -> Result<(), Error> {
         self.environment = environment.clone();
         for (_, ship_controller) in self.ship_controllers.iter_mut() {
@@ -333,7 +398,10 @@ pub struct WasmVm {
     system_state_offset: u32,
     environment_offset: u32,
     panic_buffer_offset: u32,
+    layout: Option<abi::Layout>,
+    symbols: Option<Rc<symbols::Symbols>>,
     tick_ship: wasmer::Function,
+    init_ship: Option<wasmer::Function>,
     reset_gas: wasmer::Function,
     get_gas: wasmer::Function,
     add_submemory: wasmer::Function,
@@ -342,24 +410,42 @@ pub struct WasmVm {
 }
 
 impl WasmVm {
-    pub fn create(code: &Code) -> Result<WasmVm, Error> {
+    pub fn create(code: &Code, limits: &ResourceLimits) -> Result<WasmVm, Error> {
         #[cfg(feature = "js")]
         let mut store = Store::default();
         #[cfg(feature = "sys")]
         let mut store = Store::new(wasmer_compiler_cranelift::Cranelift::new());
+        let mut symbols = None;
         let module = match code {
             Code::Wasm(wasm) => {
-                let wasm = wasm_submemory::rewrite(wasm, SUBMEMORY_SIZE)?;
+                let mut parsed_symbols = symbols::Symbols::parse(wasm);
+                let wasm = wasm_submemory::rewrite(wasm, limits.submemory_size)?;
                 let wasm = limiter::rewrite(&wasm)?;
+                if let Some(parsed_symbols) = parsed_symbols.as_mut() {
+                    parsed_symbols.set_rewritten(&wasm);
+                }
+                symbols = parsed_symbols.map(Rc::new);
                 translate_error(Module::new(&store, wasm))?
             }
             #[cfg(feature = "precompile")]
-            Code::Precompiled(bytes) => {
-                translate_error(unsafe { Module::deserialize(&store, bytes.clone()) })?
+            Code::Precompiled {
+                module,
+                submemory_size,
+            } => {
+                // The memory size is baked into the module by the rewrite.
+                if *submemory_size != limits.submemory_size {
+                    return Err(Error {
+                        msg: format!(
+                            "Code was precompiled for {} bytes of memory but the scenario allows {}",
+                            submemory_size, limits.submemory_size
+                        ),
+                    });
+                }
+                translate_error(unsafe { Module::deserialize(&store, module.clone()) })?
             }
             _ => unreachable!(),
         };
-        let import_object = imports! {
+        let mut import_object = imports! {
             "wasi_snapshot_preview1" => {
                 "fd_write" => Function::new_typed(&mut store, |_a: i32, _b: i32, _c: i32, _d: i32, _e: i32| -> i32 { -1 }),
                 "fd_seek" => Function::new_typed(&mut store, |_a: i32, _b: i64, _c: i32, _d: i32| -> i32 { -1 }),
@@ -367,6 +453,7 @@ impl WasmVm {
                 "proc_exit" => Function::new_typed(&mut store, |_a: i32| {}),
             }
         };
+        math::define_imports(&mut store, &mut import_object);
         let instance = Instance::new(&mut store, &module, &import_object)?;
 
         let memory = translate_error(instance.exports.get_memory("memory"))?.clone();
@@ -384,6 +471,7 @@ impl WasmVm {
             .unwrap() as u32;
 
         let tick_ship = translate_error(instance.exports.get_function("tick"))?.clone();
+        let init_ship = instance.exports.get_function("init").ok().cloned();
         let reset_gas = translate_error(instance.exports.get_function("reset_gas"))?.clone();
         let get_gas = translate_error(instance.exports.get_function("get_gas"))?.clone();
         let add_submemory =
@@ -393,7 +481,23 @@ impl WasmVm {
         let reset_submemory =
             translate_error(instance.exports.get_function("reset_submemory"))?.clone();
 
-        translate_runtime_error(reset_gas.call(&mut store, &[GAS_PER_TICK.into()]))?;
+        translate_runtime_error(reset_gas.call(&mut store, &[limits.gas_per_tick.into()]))?;
+
+        let abi_version = match instance.exports.get_function("abi_version") {
+            Ok(abi_version) => {
+                let ret = translate_runtime_error(abi_version.call(&mut store, &[]))?;
+                match *ret {
+                    [wasmer::Value::I32(version)] => version as u32,
+                    _ => {
+                        return Err(Error {
+                            msg: "unexpected abi_version return value".to_string(),
+                        })
+                    }
+                }
+            }
+            Err(_) => abi::LEGACY_ABI_VERSION,
+        };
+        let layout = abi::check(abi_version)?;
 
         Ok(WasmVm {
             store: Rc::new(RefCell::new(store)),
@@ -401,7 +505,10 @@ impl WasmVm {
             system_state_offset,
             environment_offset,
             panic_buffer_offset,
+            layout,
+            symbols,
             tick_ship,
+            init_ship,
             reset_gas,
             get_gas,
             add_submemory,
@@ -418,6 +525,104 @@ impl WasmVm {
         self.store.borrow_mut()
     }
 
+    fn translate_ship_error(
+        &self,
+        panic_buffer_ptr: WasmPtr<u8>,
+        e: wasmer::RuntimeError,
+    ) -> Crash {
+        let frames = self
+            .symbols
+            .as_ref()
+            .map(|x| x.symbolicate(&e))
+            .unwrap_or_default();
+        let crash = |msg: String| Crash {
+            msg,
+            frames: frames.clone(),
+        };
+
+        if let Ok(ret) = self.get_gas.call(self.store_mut().deref_mut(), &[]) {
+            if !ret.is_empty() {
+                let gas: i32 = ret[0].i32().unwrap();
+                if gas <= 0 {
+                    return crash("Ship exceeded maximum number of instructions".to_string());
+                }
+            }
+        }
+
+        // Traps other than "unreachable" can't come from the instruction limiter.
+        let is_trap = matches!(
+            e.clone().to_trap(),
+            Some(code) if code != wasmer::TrapCode::UnreachableCodeReached
+        );
+
+        {
+            let store = self.store();
+            let memory_view = self.memory.view(store.deref());
+            if let Some(vec) = WasmVm::read_vec(
+                &memory_view,
+                panic_buffer_ptr.offset(),
+                oort_api::panic::PANIC_BUFFER_SIZE as u32,
+            ) {
+                let null_pos = vec.iter().position(|&x| x == 0).unwrap_or(vec.len());
+                let msg = String::from_utf8_lossy(&vec[0..null_pos]).to_string();
+                if !msg.is_empty() {
+                    // The panic message already has the location.
+                    return Crash {
+                        msg,
+                        frames: vec![],
+                    };
+                } else if !is_trap {
+                    return crash("Ship exceeded maximum number of instructions".to_string());
+                }
+            }
+        }
+
+        if frames.is_empty() {
+            crash(format!("Ship runtime error: {e:?}"))
+        } else {
+            crash(format!("Ship runtime error: {}", e.message()))
+        }
+    }
+
+    fn write_system_state(
+        memory_view: &MemoryView,
+        ptr: WasmPtr<u64>,
+        layout: Option<&abi::Layout>,
+        state: &[u64],
+    ) {
+        let translated;
+        let state = match layout {
+            Some(layout) => {
+                translated = layout.encode(state);
+                &translated
+            }
+            None => state,
+        };
+        let slice = ptr
+            .slice(memory_view, state.len() as u32)
+            .expect("system state write");
+        slice.write_slice(state).expect("system state write");
+    }
+
+    fn read_system_state(
+        memory_view: &MemoryView,
+        ptr: WasmPtr<u64>,
+        layout: Option<&abi::Layout>,
+        state: &mut [u64],
+    ) {
+        let size = layout.map(|x| x.size()).unwrap_or(state.len());
+        let slice = ptr
+            .slice(memory_view, size as u32)
+            .expect("system state read");
+        match layout {
+            Some(layout) => {
+                let raw = slice.read_to_vec().expect("system state read");
+                layout.decode(&raw, state);
+            }
+            None => slice.read_slice(state).expect("system state read"),
+        }
+    }
+
     fn read_string(memory_view: &MemoryView, offset: u32, length: u32) -> Option<String> {
         let ptr: WasmPtr<u8> = WasmPtr::new(offset);
         let mut bytes: Vec<u8> = vec![0; length as usize];
@@ -440,6 +645,36 @@ impl WasmVm {
         Some(src_slice.to_vec())
     }
 
+    /// Returns the offset just past the highest non-zero byte in a submemory.
+    ///
+    /// The stack is at the bottom of memory and the heap grows upwards, so
+    /// this approximates the peak memory used. Only the region above `start`
+    /// is scanned.
+    fn memory_high_water_mark(
+        memory_view: &MemoryView,
+        base_address: u32,
+        start: u32,
+        size: u32,
+    ) -> u32 {
+        let mut buf = vec![0; MEMORY_SAMPLE_CHUNK_SIZE as usize];
+        let mut end = size;
+        while end > start {
+            let chunk_start = end.saturating_sub(MEMORY_SAMPLE_CHUNK_SIZE).max(start);
+            let chunk = &mut buf[..(end - chunk_start) as usize];
+            if memory_view
+                .read((base_address + chunk_start) as u64, chunk)
+                .is_err()
+            {
+                return start;
+            }
+            if let Some(i) = chunk.iter().rposition(|&b| b != 0) {
+                return chunk_start + i as u32 + 1;
+            }
+            end = chunk_start;
+        }
+        start
+    }
+
     fn update_environment(&self, ptr: WasmPtr<u8>, environment: &Environment) -> Result<(), Error> {
         let environment_string = environment
             .iter()
@@ -494,6 +729,97 @@ impl WasmVm {
     }
 }
 
+/// Reads the debug text, lines, shapes and values a ship wrote with `dbg`.
+fn emit_debug_output(
+    sim: &mut Simulation,
+    handle: ShipHandle,
+    memory_view: &MemoryView,
+    base_address: u32,
+    limits: &ResourceLimits,
+    state: &LocalSystemState,
+) {
+    if state.get(SystemState::DebugTextLength) > 0.0 {
+        let offset = state.get(SystemState::DebugTextPointer) as u32 + base_address;
+        let length = state.get(SystemState::DebugTextLength) as u32;
+        if let Some(s) = WasmVm::read_string(memory_view, offset, length) {
+            sim.emit_debug_text(handle, s);
+        }
+    }
+
+    if state.get(SystemState::DebugLinesLength) > 0.0 {
+        let offset = state.get(SystemState::DebugLinesPointer) as u32 + base_address;
+        let length = state.get(SystemState::DebugLinesLength) as u32;
+        if length <= limits.max_debug_lines {
+            if let Some(lines) = WasmVm::read_vec::<Line>(memory_view, offset, length) {
+                if validate_lines(&lines) {
+                    sim.emit_debug_lines(
+                        handle,
+                        lines
+                            .iter()
+                            .map(|v| crate::debug::Line {
+                                a: point![v.x0, v.y0],
+                                b: point![v.x1, v.y1],
+                                color: color::from_u24(v.color),
+                            })
+                            .collect::<Vec<debug::Line>>(),
+                    );
+                }
+            }
+        }
+    }
+
+    if state.get(SystemState::DebugShapesLength) > 0.0 {
+        let offset = state.get(SystemState::DebugShapesPointer) as u32 + base_address;
+        let length = state.get(SystemState::DebugShapesLength) as u32;
+        let layer_names = if (1.0..=MAX_DEBUG_LAYER_NAMES_LENGTH)
+            .contains(&state.get(SystemState::DebugLayersLength))
+        {
+            let offset = state.get(SystemState::DebugLayersPointer) as u32 + base_address;
+            let length = state.get(SystemState::DebugLayersLength) as u32;
+            WasmVm::read_string(memory_view, offset, length)
+        } else {
+            None
+        };
+        if length <= limits.max_debug_shapes {
+            if let Some(shapes) = WasmVm::read_vec::<Shape>(memory_view, offset, length) {
+                let layers = layer_names
+                    .iter()
+                    .flat_map(|names| names.split('\n'))
+                    .take(MAX_SHIP_DEBUG_LAYERS)
+                    .map(|name| sim.debug_layer(name))
+                    .collect::<Vec<_>>();
+                if let Some(shapes) = translate_shapes(&shapes, &layers) {
+                    sim.emit_debug_shapes(handle, shapes);
+                }
+            }
+        }
+    }
+
+    if state.get(SystemState::DrawnTextLength) > 0.0 {
+        let offset = state.get(SystemState::DrawnTextPointer) as u32 + base_address;
+        let length = state.get(SystemState::DrawnTextLength) as u32;
+        if length <= limits.max_drawn_text {
+            if let Some(texts) = WasmVm::read_vec::<Text>(memory_view, offset, length) {
+                if validate_texts(&texts) {
+                    sim.emit_drawn_text(Some(handle), &texts);
+                }
+            }
+        }
+    }
+
+    if state.get(SystemState::DebugValuesLength) > 0.0 {
+        let offset = state.get(SystemState::DebugValuesPointer) as u32 + base_address;
+        let length = state.get(SystemState::DebugValuesLength) as u32;
+        if length <= limits.max_debug_values {
+            if let Some(values) = WasmVm::read_vec::<DebugValue>(memory_view, offset, length) {
+                if let Some(values) = translate_debug_values(&values) {
+                    sim.emit_debug_values(handle, values);
+                }
+            }
+        }
+    }
+}
+
 struct LocalSystemState {
     pub state: [u64; SystemState::Size as usize],
 }
@@ -633,6 +959,31 @@ fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut L
     }
 }
 
+fn apply_pilot_input(
+    sim: &Simulation,
+    handle: ShipHandle,
+    input: &PilotInput,
+    state: &mut LocalSystemState,
+) {
+    let data = sim.ship(handle).data();
+    let max_thrust = if input.thrust >= 0.0 {
+        data.max_forward_acceleration
+    } else {
+        data.max_backward_acceleration
+    };
+    state.set(SystemState::AccelerateX, input.thrust * max_thrust);
+    state.set(
+        SystemState::AccelerateY,
+        input.strafe * data.max_lateral_acceleration,
+    );
+    state.set(
+        SystemState::Torque,
+        input.turn * data.max_angular_acceleration,
+    );
+    state.set(SystemState::Aim0, sim.ship(handle).heading());
+    state.set(SystemState::Fire0, if input.fire { 1.0 } else { 0.0 });
+}
+
 fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut LocalSystemState) {
     sim.ship_mut(handle).accelerate(Vec2::new(
         state.get(SystemState::AccelerateX),
@@ -763,19 +1114,101 @@ fn validate_lines(lines: &[Line]) -> bool {
         .all(|l| validate_floats(&[l.x0, l.y0, l.x1, l.y1]))
 }
 
+/// Converts shapes from the `dbg` drawing functions, mapping the ship's layer
+/// indices to the simulation's.
+fn translate_shapes(shapes: &[Shape], layers: &[u8]) -> Option<Vec<(u32, debug::Shape)>> {
+    shapes
+        .iter()
+        .map(|s| {
+            if !validate_floats(&[s.x0, s.y0, s.x1, s.y1, s.radius, s.start_angle, s.end_angle])
+                || s.radius < 0.0
+                || s.filled > 1
+            {
+                return None;
+            }
+            let center = point![s.x0, s.y0];
+            let geometry = match s.kind {
+                oort_api::SHAPE_LINE => debug::Geometry::Line {
+                    a: center,
+                    b: point![s.x1, s.y1],
+                },
+                oort_api::SHAPE_CIRCLE => debug::Geometry::Circle {
+                    center,
+                    radius: s.radius,
+                },
+                oort_api::SHAPE_ARC => debug::Geometry::Arc {
+                    center,
+                    radius: s.radius,
+                    start_angle: s.start_angle,
+                    end_angle: s.end_angle,
+                },
+                oort_api::SHAPE_POLYGON if s.sides >= 3 => debug::Geometry::Polygon {
+                    center,
+                    radius: s.radius,
+                    sides: s.sides,
+                    angle: s.start_angle,
+                },
+                _ => return None,
+            };
+            let layer = match s.layer {
+                0 => 0,
+                i => *layers.get(i as usize - 1)?,
+            };
+            let shape = debug::Shape {
+                geometry,
+                color: color::from_u24(s.color),
+                filled: s.filled == 1,
+                layer,
+            };
+            Some((s.ttl as u32, shape))
+        })
+        .collect()
+}
+
+/// Converts values from `dbg::plot` and `dbg::watch`, skipping non-finite
+/// ones.
+fn translate_debug_values(values: &[DebugValue]) -> Option<Vec<debug::Value>> {
+    values
+        .iter()
+        .filter(|v| v.value.is_finite())
+        .map(|v| {
+            let kind = match v.kind {
+                oort_api::DEBUG_VALUE_PLOT => debug::ValueKind::Plot,
+                oort_api::DEBUG_VALUE_WATCH => debug::ValueKind::Watch,
+                _ => return None,
+            };
+            let name = v.name.get(..v.length as usize)?;
+            Some(debug::Value {
+                name: std::str::from_utf8(name).ok()?.to_string(),
+                value: v.value,
+                kind,
+            })
+        })
+        .collect()
+}
+
 fn validate_texts(texts: &[Text]) -> bool {
     texts
         .iter()
         .all(|t| validate_floats(&[t.x, t.y]) && t.length as usize <= t.text.len())
 }
 
+/// Compiles an AI ahead of time for scenarios using the default limits.
 #[cfg(feature = "precompile")]
 pub fn precompile(wasm: &[u8]) -> Result<Code, Error> {
-    let wasm = wasm_submemory::rewrite(wasm, SUBMEMORY_SIZE)?;
+    precompile_with_limits(wasm, &ResourceLimits::default())
+}
+
+#[cfg(feature = "precompile")]
+pub fn precompile_with_limits(wasm: &[u8], limits: &ResourceLimits) -> Result<Code, Error> {
+    let wasm = wasm_submemory::rewrite(wasm, limits.submemory_size)?;
     let wasm = limiter::rewrite(&wasm)?;
     let store = Store::default();
     let module = translate_error(Module::new(&store, wasm))?;
-    Ok(Code::Precompiled(translate_error(module.serialize())?))
+    Ok(Code::Precompiled {
+        module: translate_error(module.serialize())?,
+        submemory_size: limits.submemory_size,
+    })
 }
 
 fn make_seed(sim_seed: u32, handle: ShipHandle) -> i64 {
@@ -788,3 +1221,90 @@ fn make_seed(sim_seed: u32, handle: ShipHandle) -> i64 {
     s.write_u32(j);
     s.finish() as i64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ship::{self, fighter};
+    use oort_api::{DEBUG_VALUE_PLOT, DEBUG_VALUE_WATCH};
+
+    /// Builds a value the way `dbg::plot` and `dbg::watch` do.
+    fn value(kind: u8, name: &str, value: f64) -> DebugValue {
+        let mut v = DebugValue {
+            value,
+            kind,
+            length: name.len() as u8,
+            ..Default::default()
+        };
+        v.name[..name.len()].copy_from_slice(name.as_bytes());
+        v
+    }
+
+    #[test]
+    fn test_translate_debug_values() {
+        assert_eq!(
+            translate_debug_values(&[
+                value(DEBUG_VALUE_PLOT, "distance", 1.5),
+                value(DEBUG_VALUE_WATCH, "speed", 2.0),
+                value(DEBUG_VALUE_PLOT, "nan", f64::NAN),
+            ]),
+            Some(vec![
+                debug::Value {
+                    name: "distance".to_string(),
+                    value: 1.5,
+                    kind: debug::ValueKind::Plot,
+                },
+                debug::Value {
+                    name: "speed".to_string(),
+                    value: 2.0,
+                    kind: debug::ValueKind::Watch,
+                },
+            ])
+        );
+        assert!(translate_debug_values(&[value(7, "x", 1.0)]).is_none());
+        let mut long = value(DEBUG_VALUE_PLOT, "x", 1.0);
+        long.length = 23;
+        assert!(translate_debug_values(&[long]).is_none());
+        let mut invalid = value(DEBUG_VALUE_PLOT, "x", 1.0);
+        invalid.name[0] = 0xff;
+        assert!(translate_debug_values(&[invalid]).is_none());
+    }
+
+    #[test]
+    fn test_plots() {
+        let mut sim = Simulation::new("test", 0, &[Code::None]);
+        let handle = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            fighter(0),
+        );
+        let id: u64 = handle.into();
+        for i in 0..3 {
+            sim.step();
+            let values = translate_debug_values(&[
+                value(DEBUG_VALUE_PLOT, "distance", i as f64),
+                value(DEBUG_VALUE_WATCH, "speed", 2.0),
+            ])
+            .unwrap();
+            sim.emit_debug_values(handle, values);
+        }
+        let snapshot = sim.snapshot(0);
+        assert_eq!(snapshot.debug_values[&id].len(), 2);
+        let plots = &snapshot.plots[&id];
+        assert_eq!(plots.keys().collect::<Vec<_>>(), vec!["distance"]);
+        assert_eq!(
+            plots["distance"]
+                .iter()
+                .map(|&(_, v)| v)
+                .collect::<Vec<_>>(),
+            vec![0.0, 1.0, 2.0]
+        );
+
+        for _ in 0..=debug::MAX_PLOT_POINTS {
+            sim.step();
+        }
+        assert!(sim.snapshot(0).plots.get(&id).is_none());
+    }
+}
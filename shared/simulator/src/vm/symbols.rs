@@ -0,0 +1,80 @@
+// Maps wasm trap locations back to user source lines using the table the
+// compiler stores in the "oort_symbols" custom section.
+use super::Frame;
+use oort_symbols::{code_section_start, find_custom_section, normalize_name, Table};
+
+const USER_MODULE_PREFIX: &str = "oort_ai::user::";
+
+pub struct Symbols {
+    table: Table,
+    /// The module the table was generated for.
+    original: Vec<u8>,
+    /// The module after the simulator's rewrites, which trap offsets refer to.
+    rewritten: Vec<u8>,
+}
+
+impl Symbols {
+    pub fn parse(wasm: &[u8]) -> Option<Symbols> {
+        let data = find_custom_section(wasm, oort_symbols::SECTION_NAME)?;
+        let text = std::str::from_utf8(data).ok()?;
+        Some(Symbols {
+            table: Table::parse(text),
+            original: wasm.to_vec(),
+            rewritten: vec![],
+        })
+    }
+
+    pub fn set_rewritten(&mut self, wasm: &[u8]) {
+        self.rewritten = wasm.to_vec();
+    }
+
+    /// Returns the frames in user code, innermost first.
+    pub fn symbolicate(&self, e: &wasmer::RuntimeError) -> Vec<Frame> {
+        e.trace()
+            .iter()
+            .filter_map(|frame| {
+                let name = normalize_name(frame.function_name()?);
+                let decl_line = *self.table.functions.get(&name)?;
+                let line = self.line_at(frame.module_offset()).unwrap_or(decl_line);
+                let name = name.strip_prefix(USER_MODULE_PREFIX).unwrap_or(&name);
+                Some(Frame {
+                    function: name.to_string(),
+                    line,
+                })
+            })
+            .collect()
+    }
+
+    fn line_at(&self, offset: usize) -> Option<u32> {
+        let offset = oort_symbols::original_offset(&self.original, &self.rewritten, offset)?;
+        let address = offset.checked_sub(code_section_start(&self.original)?)?;
+        self.table.line_at(address as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let table = "12\toort_ai::user::Ship::tick\n30\toort_ai::user::helper\n@10\t13\n";
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend_from_slice(&oort_symbols::custom_section(
+            oort_symbols::SECTION_NAME,
+            table.as_bytes(),
+        ));
+
+        let symbols = Symbols::parse(&wasm).unwrap();
+        assert_eq!(
+            symbols.table.functions.get("oort_ai::user::Ship::tick"),
+            Some(&12)
+        );
+        assert_eq!(
+            symbols.table.functions.get("oort_ai::user::helper"),
+            Some(&30)
+        );
+        assert_eq!(symbols.table.line_at(0x10), Some(13));
+        assert!(Symbols::parse(b"\0asm\x01\0\0\0").is_none());
+    }
+}
@@ -99,3 +99,26 @@ fn test_id() {
     check(ship_handles[1], 2);
     check(ship_handles[2], 1);
 }
+
+#[test]
+fn test_peak_memory() {
+    let mut sim =
+        simulation::Simulation::new("test", 0, &[Code::Builtin("test".to_string()), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    let snapshot = sim.snapshot(0);
+    let ship = snapshot
+        .ships
+        .iter()
+        .find(|ship| ship.id == u64::from(ship0))
+        .unwrap();
+    let peak_memory = ship.peak_memory.expect("Missing peak memory");
+    assert!(peak_memory > 0);
+    assert!(peak_memory <= snapshot.memory_limit);
+}
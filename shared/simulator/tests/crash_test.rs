@@ -71,3 +71,36 @@ fn test_infinite_loop() {
         );
     });
 }
+
+#[test]
+#[serial]
+fn test_scenario_gas_limit() {
+    let create = |scenario_name: &str| {
+        let mut sim = simulation::Simulation::new(
+            scenario_name,
+            0,
+            &[Code::Builtin("test".to_string()), Code::None],
+        );
+        let mut env = BTreeMap::new();
+        env.insert("TESTCASE".to_string(), "id".to_string());
+        sim.update_environment(0, env);
+        let handle = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            fighter(0),
+        );
+        sim.step();
+        (sim, handle)
+    };
+
+    let (sim, handle) = create("test");
+    assert!(sim.ship(handle).data().crash.is_none());
+
+    let (sim, handle) = create("limits_test");
+    assert_eq!(
+        sim.events().debug_text.get(&handle.into()).unwrap(),
+        "Crashed: Ship exceeded maximum number of instructions"
+    );
+}
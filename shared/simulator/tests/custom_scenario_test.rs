@@ -0,0 +1,105 @@
+use nalgebra::vector;
+use oort_simulator::scenario::custom::{
+    self, ScenarioDescription, ShipPlacement, UnsupportedClass, VictoryCondition,
+};
+use oort_simulator::scenario::{self, Status};
+use oort_simulator::ship::ShipClass;
+use oort_simulator::simulation::{self, Code};
+use std::collections::BTreeMap;
+
+fn duel() -> ScenarioDescription {
+    ScenarioDescription {
+        name: "Duel".to_string(),
+        ships: vec![
+            ShipPlacement {
+                class: ShipClass::Fighter,
+                team: 0,
+                position: vector![-1000.0, 0.0],
+                velocity: vector![0.0, 0.0],
+                heading: 0.0,
+            },
+            ShipPlacement {
+                class: ShipClass::Target,
+                team: 1,
+                position: vector![1000.0, 0.0],
+                velocity: vector![0.0, 10.0],
+                heading: std::f64::consts::PI,
+            },
+        ],
+        enemy_ais: BTreeMap::from([(1, "empty".to_string())]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_custom_scenario_roundtrip() {
+    let description = duel();
+    let name = custom::register(description.clone()).unwrap();
+    assert_eq!(name, description.scenario_name());
+    assert!(name.starts_with(custom::PREFIX));
+    assert_eq!(name.len(), custom::PREFIX.len() + 16);
+    assert_eq!(custom::lookup(&name).unwrap(), description);
+    assert_eq!(
+        ScenarioDescription::from_json(&description.to_json()).unwrap(),
+        description
+    );
+
+    let scenario = scenario::load(&name);
+    assert_eq!(scenario.name(), name);
+    assert_eq!(scenario.human_name(), "Duel");
+    assert_eq!(
+        scenario.initial_code(),
+        vec![Code::Builtin("empty".to_string()); 2]
+    );
+}
+
+#[test]
+fn test_custom_scenario_invalid() {
+    assert!(scenario::load_safe("custom:garbage").is_none());
+
+    let mut description = duel();
+    description.ships[0].position.x = 1e9;
+    assert!(description.validate().is_err());
+    assert!(custom::register(description.clone()).is_err());
+    assert!(scenario::load_safe(&description.scenario_name()).is_none());
+
+    let mut description = duel();
+    description.ships[0].class = ShipClass::Planet;
+    assert_eq!(
+        custom::ship_data(ShipClass::Planet, 0).unwrap_err(),
+        UnsupportedClass(ShipClass::Planet)
+    );
+    assert!(description.validate().is_err());
+
+    let mut description = duel();
+    description.enemy_ais.insert(1, "nonexistent".to_string());
+    assert!(description.validate().is_err());
+}
+
+#[test]
+fn test_custom_scenario_run() {
+    let name = custom::register(duel()).unwrap();
+    let scenario = scenario::load(&name);
+    let mut sim = simulation::Simulation::new(&name, 0, &scenario.initial_code());
+    assert_eq!(sim.ships.len(), 2);
+    sim.step();
+    assert_eq!(sim.status(), Status::Running);
+    while sim.status() == Status::Running {
+        sim.step();
+    }
+    assert_eq!(sim.status(), Status::Draw);
+}
+
+#[test]
+fn test_custom_scenario_survive() {
+    let mut description = duel();
+    description.victory = VictoryCondition::Survive;
+    description.max_ticks = 10;
+    let name = custom::register(description).unwrap();
+    let scenario = scenario::load(&name);
+    let mut sim = simulation::Simulation::new(&name, 0, &scenario.initial_code());
+    while sim.status() == Status::Running {
+        sim.step();
+    }
+    assert_eq!(sim.status(), Status::Victory { team: 0 });
+}
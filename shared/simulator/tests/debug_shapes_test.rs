@@ -0,0 +1,100 @@
+use nalgebra::{point, vector};
+use oort_simulator::debug::{Geometry, Shape};
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation::{self, Code};
+
+fn circle(layer: u8) -> Shape {
+    Shape {
+        geometry: Geometry::Circle {
+            center: point![0.0, 0.0],
+            radius: 100.0,
+        },
+        color: vector![1.0, 1.0, 1.0, 1.0],
+        filled: true,
+        layer,
+    }
+}
+
+#[test]
+fn test_persistent_shapes() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    let layer = sim.debug_layer("trajectory");
+    assert_eq!(sim.debug_layer("trajectory"), layer);
+    sim.emit_debug_shapes(handle, vec![(0, circle(0)), (3, circle(layer))]);
+    let id: u64 = handle.into();
+    let count = |sim: &simulation::Simulation| {
+        sim.snapshot(0)
+            .debug_shapes
+            .get(&id)
+            .map(|shapes| shapes.len())
+            .unwrap_or(0)
+    };
+    assert_eq!(count(&sim), 2);
+    assert_eq!(sim.snapshot(0).debug_layers, vec!["trajectory".to_string()]);
+
+    sim.step();
+    assert_eq!(count(&sim), 1);
+
+    for _ in 0..10 {
+        sim.step();
+    }
+    assert_eq!(count(&sim), 0);
+}
+
+#[test]
+fn test_shape_geometry() {
+    let polygon = Shape {
+        geometry: Geometry::Polygon {
+            center: point![0.0, 0.0],
+            radius: 10.0,
+            sides: 5,
+            angle: 0.0,
+        },
+        ..circle(0)
+    };
+    assert_eq!(polygon.lines().len(), 5);
+    assert_eq!(polygon.triangles().len(), 5);
+
+    let arc = Shape {
+        geometry: Geometry::Arc {
+            center: point![0.0, 0.0],
+            radius: 10.0,
+            start_angle: 0.0,
+            end_angle: std::f64::consts::FRAC_PI_2,
+        },
+        ..circle(0)
+    };
+    assert!(arc.lines().is_empty());
+    assert!(arc.triangles().is_empty());
+    let (center, radius, start_angle, span) = arc.arc().unwrap();
+    assert_eq!(center, point![0.0, 0.0]);
+    assert_eq!(radius, 10.0);
+    assert_eq!(start_angle, 0.0);
+    assert!((span - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+
+    let wrapped = Shape {
+        geometry: Geometry::Arc {
+            center: point![0.0, 0.0],
+            radius: 10.0,
+            start_angle: 1.0,
+            end_angle: 0.5,
+        },
+        ..circle(0)
+    };
+    let (_, _, _, span) = wrapped.arc().unwrap();
+    assert!((span - (std::f64::consts::TAU - 0.5)).abs() < 1e-9);
+
+    assert!(circle(0).lines().is_empty());
+    assert!(circle(0).triangles().is_empty());
+    assert_eq!(circle(0).arc().unwrap().3, std::f64::consts::TAU);
+    assert!(polygon.arc().is_none());
+}
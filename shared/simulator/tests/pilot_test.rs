@@ -0,0 +1,65 @@
+use nalgebra::vector;
+use oort_simulator::pilot::PilotInput;
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation::{self, Code};
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn test_pilot_input() {
+    let mut sim =
+        simulation::Simulation::new("test", 0, &[Code::Builtin("empty".to_string()), Code::None]);
+    let piloted = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let unpiloted = ship::create(
+        &mut sim,
+        vector![0.0, 1000.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.set_pilot_input(
+        piloted,
+        Some(PilotInput {
+            thrust: 1.0,
+            turn: 1.0,
+            fire: true,
+            ..Default::default()
+        }),
+    );
+    for _ in 0..10 {
+        sim.step();
+    }
+
+    assert!(sim.ship(piloted).velocity().x > 0.0);
+    assert!(sim.ship(piloted).angular_velocity() > 0.0);
+    assert!(!sim.bullets.is_empty());
+    assert_eq!(sim.ship(unpiloted).velocity(), vector![0.0, 0.0]);
+    assert!(sim.snapshot(0).cheats);
+
+    // Returning the ship to its AI stops the thrust.
+    sim.set_pilot_input(piloted, None);
+    sim.step();
+    let velocity = sim.ship(piloted).velocity();
+    sim.step();
+    assert_eq!(sim.ship(piloted).velocity(), velocity);
+}
+
+#[test]
+fn test_ship_handle_id_roundtrip() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let id: u64 = handle.into();
+    assert_eq!(ship::ShipHandle::from(id), handle);
+}
@@ -0,0 +1,53 @@
+use nalgebra::vector;
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation::{self, Code};
+use serial_test::serial;
+use std::collections::BTreeMap;
+
+#[test]
+#[serial]
+fn test_reload_existing_ships() {
+    let mut sim =
+        simulation::Simulation::new("test", 0, &[Code::Builtin("test".to_string()), Code::None]);
+    let mut env = BTreeMap::new();
+    env.insert("TESTCASE".to_string(), "panic".to_string());
+    sim.update_environment(0, env);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    assert!(sim.ship(handle).data().crash.is_some());
+
+    // The reloaded code keeps the environment and controls the existing ship.
+    sim.reload_code(0, &Code::Builtin("test".to_string()));
+    assert!(sim.ship(handle).data().crash.is_none());
+    sim.step();
+    assert!(sim.events().errors.is_empty());
+    assert_eq!(
+        sim.events().debug_text.get(&handle.into()).unwrap(),
+        "Crashed: ship panicked at 'Panic!', lib.rs:17:24"
+    );
+}
+
+#[test]
+#[serial]
+fn test_reload_invalid_code() {
+    let mut sim =
+        simulation::Simulation::new("test", 0, &[Code::Builtin("test".to_string()), Code::None]);
+    ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    sim.reload_code(0, &Code::Wasm(vec![0, 1, 2, 3]));
+    assert_eq!(sim.events().errors.len(), 1);
+    assert!(sim.get_team_controller(0).is_some());
+}
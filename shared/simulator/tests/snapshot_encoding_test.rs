@@ -0,0 +1,120 @@
+use nalgebra::vector;
+use oort_simulator::debug::{self, ValueKind};
+use oort_simulator::scenario;
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation;
+use oort_simulator::snapshot::Snapshot;
+use oort_simulator::snapshot_encoding::{Decoder, Encoder, Frame};
+use test_log::test;
+
+fn roundtrip(frame: &Frame) -> (Frame, usize) {
+    let data = bincode::serialize(frame).unwrap();
+    (bincode::deserialize(&data).unwrap(), data.len())
+}
+
+fn assert_close(decoded: &Snapshot, snapshot: &Snapshot) {
+    assert_eq!(decoded.time, snapshot.time);
+    assert_eq!(decoded.status, snapshot.status);
+    assert_eq!(decoded.ships.len(), snapshot.ships.len());
+    for (a, b) in decoded.ships.iter().zip(snapshot.ships.iter()) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.team, b.team);
+        assert_eq!(a.class, b.class);
+        assert!((a.position - b.position).norm() < 0.1);
+        assert!((a.velocity - b.velocity).norm() < 0.1);
+    }
+    assert_eq!(decoded.bullets.len(), snapshot.bullets.len());
+    for (a, b) in decoded.bullets.iter().zip(snapshot.bullets.iter()) {
+        assert_eq!(a.color, b.color);
+        assert!((a.position - b.position).norm() < 0.1);
+        assert!((a.velocity - b.velocity).norm() < 0.1);
+    }
+    assert_eq!(decoded.scenario_lines, snapshot.scenario_lines);
+    assert_eq!(decoded.debug_shapes, snapshot.debug_shapes);
+    assert_eq!(decoded.plots, snapshot.plots);
+}
+
+#[test]
+fn test_roundtrip() {
+    let scenario_name = "fighter_duel";
+    let scenario = scenario::load(scenario_name);
+    let mut sim = simulation::Simulation::new(scenario_name, 0, &scenario.solution_codes());
+    let mut encoder = Encoder::default();
+    let mut decoder = Decoder::default();
+    let mut max_bullets = 0;
+
+    for i in 0..600 {
+        sim.step();
+        let snapshot = sim.snapshot(0);
+        let full_size = bincode::serialize(&snapshot).unwrap().len();
+        let (frame, size) = roundtrip(&encoder.encode(snapshot.clone()));
+        assert_eq!(frame.is_delta(), i > 0);
+        let decoded = decoder.decode(frame).unwrap();
+        assert_close(&decoded, &snapshot);
+        if i > 0 && !snapshot.bullets.is_empty() {
+            assert!(size < full_size, "{size} >= {full_size}");
+        }
+        max_bullets = max_bullets.max(snapshot.bullets.len());
+    }
+    assert!(max_bullets > 0);
+
+    // After a reset the next frame stands on its own.
+    encoder.reset();
+    sim.step();
+    let frame = encoder.encode(sim.snapshot(0));
+    assert!(!frame.is_delta());
+    assert!(Decoder::default().decode(frame).is_some());
+}
+
+#[test]
+fn test_delta_without_previous() {
+    let mut sim = simulation::Simulation::new("test", 0, &[simulation::Code::None]);
+    let mut encoder = Encoder::default();
+    encoder.encode(sim.snapshot(0));
+    sim.step();
+    let frame = encoder.encode(sim.snapshot(0));
+    assert!(Decoder::default().decode(frame).is_none());
+}
+
+#[test]
+fn test_plots() {
+    let mut sim = simulation::Simulation::new("test", 0, &[simulation::Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let mut encoder = Encoder::default();
+    let mut decoder = Decoder::default();
+
+    for i in 0..(debug::MAX_PLOT_POINTS + 100) {
+        sim.step();
+        let mut values = vec![debug::Value {
+            name: "x".to_string(),
+            value: i as f64,
+            kind: ValueKind::Plot,
+        }];
+        if i % 2 == 0 {
+            values.push(debug::Value {
+                name: "y".to_string(),
+                value: -(i as f64),
+                kind: ValueKind::Plot,
+            });
+        }
+        sim.emit_debug_values(handle, values);
+        let snapshot = sim.snapshot(0);
+        let (frame, size) = roundtrip(&encoder.encode(snapshot.clone()));
+        let decoded = decoder.decode(frame).unwrap();
+        assert_close(&decoded, &snapshot);
+        if i > 0 {
+            assert!(size < 1000, "{size}");
+        }
+    }
+    let id: u64 = handle.into();
+    let x = &sim.snapshot(0).plots[&id]["x"];
+    // Old points have been dropped.
+    assert!(x.len() < (debug::MAX_PLOT_POINTS + 100) as usize);
+    assert_eq!(x.back().unwrap().1, (debug::MAX_PLOT_POINTS + 99) as f64);
+}
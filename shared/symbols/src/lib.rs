@@ -0,0 +1,344 @@
+//! The symbol table that the compiler stores in AIs and the simulator uses to
+//! map wasm traps back to lines of user code.
+//!
+//! The table is kept in the "oort_symbols" custom section as text. Each user
+//! function has a line with its declaration line and name separated by a tab.
+//! Each row of the DWARF line program has a line starting with '@' with the
+//! code address in hex and the source line, or 0 outside of user code.
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use wasmparser::{Operator, Parser, Payload};
+
+pub const SECTION_NAME: &str = "oort_symbols";
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Table {
+    /// Declaration line by normalized function name.
+    pub functions: BTreeMap<String, u32>,
+    /// Source line by code address, sorted by address. Addresses are
+    /// relative to the start of the code section, as in DWARF.
+    pub rows: Vec<(u32, u32)>,
+}
+
+impl Table {
+    pub fn parse(text: &str) -> Table {
+        let mut table = Table::default();
+        for entry in text.lines() {
+            let Some((a, b)) = entry.split_once('\t') else {
+                continue;
+            };
+            if let Some(address) = a.strip_prefix('@') {
+                if let (Ok(address), Ok(line)) = (u32::from_str_radix(address, 16), b.parse()) {
+                    table.rows.push((address, line));
+                }
+            } else if let Ok(line) = a.parse() {
+                table.functions.entry(b.to_string()).or_insert(line);
+            }
+        }
+        table.rows.sort_by_key(|&(address, _)| address);
+        table
+    }
+
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        for (name, line) in self.functions.iter() {
+            text.push_str(&format!("{line}\t{name}\n"));
+        }
+        for (address, line) in self.rows.iter() {
+            text.push_str(&format!("@{address:x}\t{line}\n"));
+        }
+        text
+    }
+
+    /// Returns the user source line for a code address.
+    pub fn line_at(&self, address: u32) -> Option<u32> {
+        let i = self.rows.partition_point(|&(a, _)| a <= address);
+        match self.rows.get(i.checked_sub(1)?) {
+            Some(&(_, line)) if line > 0 => Some(line),
+            _ => None,
+        }
+    }
+}
+
+/// Normalizes a function name so that mangled and demangled symbols match.
+pub fn normalize_name(name: &str) -> String {
+    let name = format!("{:#}", rustc_demangle::demangle(name));
+    match name.rsplit_once("::h") {
+        Some((prefix, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            prefix.to_string()
+        }
+        _ => name,
+    }
+}
+
+pub struct Section<'a> {
+    pub id: u8,
+    /// Set for custom sections.
+    pub name: Option<&'a str>,
+    /// The contents, after the name for custom sections.
+    pub data: &'a [u8],
+    /// The whole section including its header.
+    pub raw: &'a [u8],
+}
+
+pub fn parse_sections(wasm: &[u8]) -> Result<Vec<Section>> {
+    if wasm.len() < 8 || &wasm[0..4] != b"\0asm" {
+        bail!("Invalid wasm module");
+    }
+    let mut sections = vec![];
+    let mut offset = 8;
+    while offset < wasm.len() {
+        let start = offset;
+        let id = wasm[offset];
+        offset += 1;
+        let size = read_leb128(wasm, &mut offset)? as usize;
+        let end = offset + size;
+        if end > wasm.len() {
+            bail!("Truncated wasm section");
+        }
+        let mut name = None;
+        let mut data = &wasm[offset..end];
+        if id == 0 {
+            let name_len = read_leb128(wasm, &mut offset)? as usize;
+            if offset + name_len > end {
+                bail!("Truncated wasm custom section name");
+            }
+            name = Some(std::str::from_utf8(&wasm[offset..(offset + name_len)])?);
+            data = &wasm[(offset + name_len)..end];
+        }
+        sections.push(Section {
+            id,
+            name,
+            data,
+            raw: &wasm[start..end],
+        });
+        offset = end;
+    }
+    Ok(sections)
+}
+
+pub fn find_custom_section<'a>(wasm: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    parse_sections(wasm)
+        .ok()?
+        .into_iter()
+        .find(|section| section.name == Some(name))
+        .map(|section| section.data)
+}
+
+pub fn custom_section(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut payload = vec![];
+    write_leb128(&mut payload, name.len() as u32);
+    payload.extend_from_slice(name.as_bytes());
+    payload.extend_from_slice(data);
+
+    let mut section = vec![0];
+    write_leb128(&mut section, payload.len() as u32);
+    section.extend_from_slice(&payload);
+    section
+}
+
+fn read_leb128(bytes: &[u8], offset: &mut usize) -> Result<u32> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let Some(&byte) = bytes.get(*offset) else {
+            bail!("Truncated LEB128");
+        };
+        *offset += 1;
+        result |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+        if shift >= 32 {
+            bail!("Invalid LEB128");
+        }
+    }
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Returns the offset of the code section's contents, which DWARF addresses
+/// are relative to.
+pub fn code_section_start(wasm: &[u8]) -> Option<usize> {
+    Parser::new(0)
+        .parse_all(wasm)
+        .find_map(|payload| match payload {
+            Ok(Payload::CodeSectionStart { range, .. }) => Some(range.start),
+            _ => None,
+        })
+}
+
+/// An instruction, compared by opcode and the immediates that the
+/// simulator's rewrites leave alone.
+struct Instruction<'a> {
+    kind: std::mem::Discriminant<Operator<'a>>,
+    immediate: i64,
+    offset: usize,
+}
+
+impl<'a> Instruction<'a> {
+    fn matches(&self, other: &Instruction<'a>) -> bool {
+        self.kind == other.kind && self.immediate == other.immediate
+    }
+}
+
+fn functions(wasm: &[u8]) -> Option<Vec<Vec<Instruction>>> {
+    let mut functions = vec![];
+    for payload in Parser::new(0).parse_all(wasm) {
+        let Payload::CodeSectionEntry(body) = payload.ok()? else {
+            continue;
+        };
+        let mut instructions = vec![];
+        for op in body.get_operators_reader().ok()?.into_iter_with_offsets() {
+            let (op, offset) = op.ok()?;
+            let immediate = match op {
+                Operator::GlobalGet { global_index } | Operator::GlobalSet { global_index } => {
+                    global_index as i64
+                }
+                Operator::I32Const { value } => value as i64,
+                Operator::I64Const { value } => value,
+                _ => 0,
+            };
+            instructions.push(Instruction {
+                kind: std::mem::discriminant(&op),
+                immediate,
+                offset,
+            });
+        }
+        functions.push(instructions);
+    }
+    Some(functions)
+}
+
+/// Maps an offset in a rewritten module back to the original module.
+///
+/// The simulator rewrites AIs to add memory isolation and instruction
+/// counting. Those rewrites only insert instructions, keeping the existing
+/// functions in order, so the original instructions can be matched up in
+/// sequence. An offset in inserted code maps to the original instruction
+/// before it.
+pub fn original_offset(original: &[u8], rewritten: &[u8], offset: usize) -> Option<usize> {
+    let original = functions(original)?;
+    let rewritten = functions(rewritten)?;
+    if rewritten.len() < original.len() {
+        return None;
+    }
+    let index = original
+        .iter()
+        .zip(rewritten.iter())
+        .rposition(|(_, r)| r.first().is_some_and(|i| i.offset <= offset))?;
+    let (original, rewritten) = (&original[index], &rewritten[index]);
+
+    let mut next = 0;
+    let mut matched = None;
+    for instruction in rewritten.iter().take_while(|i| i.offset <= offset) {
+        if let Some(o) = original.get(next) {
+            if o.matches(instruction) {
+                matched = Some(o.offset);
+                next += 1;
+            }
+        }
+    }
+    matched.or(original.first().map(|i| i.offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(
+            normalize_name("_ZN7oort_ai4user4Ship4tick17h0123456789abcdefE"),
+            "oort_ai::user::Ship::tick"
+        );
+        assert_eq!(
+            normalize_name("oort_ai::user::Ship::tick::h0123456789abcdef"),
+            "oort_ai::user::Ship::tick"
+        );
+        assert_eq!(normalize_name("tick"), "tick");
+    }
+
+    #[test]
+    fn test_table() {
+        let table = Table::parse("12\toort_ai::user::Ship::tick\n@10\t13\n@20\t0\n@18\t14\n");
+        assert_eq!(table.functions.get("oort_ai::user::Ship::tick"), Some(&12));
+        assert_eq!(table.rows, vec![(0x10, 13), (0x18, 14), (0x20, 0)]);
+        assert_eq!(table.line_at(0x0f), None);
+        assert_eq!(table.line_at(0x10), Some(13));
+        assert_eq!(table.line_at(0x1f), Some(14));
+        assert_eq!(table.line_at(0x20), None);
+        assert_eq!(Table::parse(&table.encode()), table);
+    }
+
+    #[test]
+    fn test_custom_section() {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend_from_slice(&custom_section("name", &[4, 5]));
+        wasm.extend_from_slice(&custom_section(SECTION_NAME, b"12\tf\n"));
+        assert_eq!(find_custom_section(&wasm, "name"), Some(&[4u8, 5][..]));
+        assert_eq!(
+            find_custom_section(&wasm, SECTION_NAME),
+            Some(&b"12\tf\n"[..])
+        );
+        assert_eq!(find_custom_section(&wasm, "other"), None);
+        assert!(parse_sections(b"\0asm\x01\0\0\0\0\x10").is_err());
+    }
+
+    fn module(bodies: &[&[u8]]) -> Vec<u8> {
+        let mut code = vec![bodies.len() as u8];
+        for body in bodies {
+            // No locals.
+            code.push(body.len() as u8 + 1);
+            code.push(0);
+            code.extend_from_slice(body);
+        }
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.push(10);
+        wasm.push(code.len() as u8);
+        wasm.extend_from_slice(&code);
+        wasm
+    }
+
+    #[test]
+    fn test_original_offset() {
+        const NOP: u8 = 0x01;
+        const DROP: u8 = 0x1a;
+        const END: u8 = 0x0b;
+        const I32_CONST: u8 = 0x41;
+        const GLOBAL_GET: u8 = 0x23;
+        let original = module(&[&[NOP, END], &[I32_CONST, 1, DROP, I32_CONST, 2, DROP, END]]);
+        // Inserts "global.get 5; drop" before each constant.
+        let rewritten = module(&[
+            &[NOP, END],
+            &[
+                GLOBAL_GET, 5, DROP, I32_CONST, 1, DROP, GLOBAL_GET, 5, DROP, I32_CONST, 2, DROP,
+                END,
+            ],
+        ]);
+        let start = code_section_start(&original).unwrap();
+        assert_eq!(start, 10);
+        // Offsets of the second function's instructions.
+        let (o, r) = (start + 7, start + 7);
+        assert_eq!(original_offset(&original, &rewritten, r), Some(o));
+        assert_eq!(original_offset(&original, &rewritten, r + 3), Some(o));
+        assert_eq!(original_offset(&original, &rewritten, r + 6), Some(o + 2));
+        assert_eq!(original_offset(&original, &rewritten, r + 9), Some(o + 3));
+        assert_eq!(original_offset(&original, &rewritten, r + 11), Some(o + 5));
+        assert_eq!(
+            original_offset(&original, &rewritten, start + 3),
+            Some(start + 3)
+        );
+    }
+}
@@ -0,0 +1,32 @@
+// Root of a Zig AI. The user's code is in user.zig and must have a public
+// `tick` function, and optionally an `init` function.
+const std = @import("std");
+const oort = @import("oort.zig");
+const user = @import("user.zig");
+
+comptime {
+    _ = &oort.SYSTEM_STATE;
+    _ = &oort.ENVIRONMENT;
+    _ = &oort.PANIC_BUFFER;
+}
+
+export fn abi_version() u32 {
+    return oort.ABI_VERSION;
+}
+
+export fn init() void {
+    if (@hasDecl(user, "init")) {
+        user.init();
+    }
+}
+
+export fn tick() void {
+    user.tick();
+}
+
+pub fn panic(msg: []const u8, _: ?*std.builtin.StackTrace, _: ?usize) noreturn {
+    var buf = std.io.fixedBufferStream(oort.PANIC_BUFFER[0 .. oort.PANIC_BUFFER.len - 1]);
+    buf.writer().print("ship panicked at '{s}'", .{msg}) catch {};
+    oort.PANIC_BUFFER[buf.pos] = 0;
+    @trap();
+}
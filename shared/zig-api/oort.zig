@@ -0,0 +1,183 @@
+// API for Zig AIs.
+
+pub export var SYSTEM_STATE: [128]u64 = [_]u64{0} ** 128;
+pub export var ENVIRONMENT: [1024]u8 = [_]u8{0} ** 1024;
+pub export var PANIC_BUFFER: [1024]u8 = [_]u8{0} ** 1024;
+
+// Math functions computed by the simulator. These give identical results on
+// every platform and cost a small fixed number of instructions per call.
+pub extern "oort_math" fn sin(x: f64) f64;
+pub extern "oort_math" fn cos(x: f64) f64;
+pub extern "oort_math" fn atan2(y: f64, x: f64) f64;
+pub extern "oort_math" fn sqrt(x: f64) f64;
+pub extern "oort_math" fn exp(x: f64) f64;
+pub extern "oort_math" fn ln(x: f64) f64;
+
+// BEGIN GENERATED from oort_api by oort_compiler::bindings. Do not edit.
+pub const ABI_VERSION: u32 = 3;
+
+pub const SystemState = enum(u8) {
+    Class,
+    Seed,
+    PositionX,
+    PositionY,
+    VelocityX,
+    VelocityY,
+    Heading,
+    AngularVelocity,
+
+    AccelerateX,
+    AccelerateY,
+    Torque,
+
+    Aim0,
+    Aim1,
+    Aim2,
+    Aim3,
+
+    Fire0,
+    Fire1,
+    Fire2,
+    Fire3,
+
+    Explode,
+
+    RadarHeading,
+    RadarWidth,
+    RadarContactFound,
+    RadarContactClass,
+    RadarContactPositionX,
+    RadarContactPositionY,
+    RadarContactVelocityX,
+    RadarContactVelocityY,
+
+    DebugTextPointer,
+    DebugTextLength,
+
+    MaxForwardAcceleration,
+    MaxLateralAcceleration,
+    MaxAngularAcceleration,
+
+    DebugLinesPointer,
+    DebugLinesLength,
+
+    RadarMinDistance,
+    RadarMaxDistance,
+
+    CurrentTick,
+    MaxBackwardAcceleration,
+
+    ActivateAbility,
+
+    Radio0Channel,
+    Radio0Send,
+    Radio0Receive,
+    Radio0Data0,
+    Radio0Data1,
+    Radio0Data2,
+    Radio0Data3,
+
+    Radio1Channel,
+    Radio1Send,
+    Radio1Receive,
+    Radio1Data0,
+    Radio1Data1,
+    Radio1Data2,
+    Radio1Data3,
+
+    Radio2Channel,
+    Radio2Send,
+    Radio2Receive,
+    Radio2Data0,
+    Radio2Data1,
+    Radio2Data2,
+    Radio2Data3,
+
+    Radio3Channel,
+    Radio3Send,
+    Radio3Receive,
+    Radio3Data0,
+    Radio3Data1,
+    Radio3Data2,
+    Radio3Data3,
+
+    Radio4Channel,
+    Radio4Send,
+    Radio4Receive,
+    Radio4Data0,
+    Radio4Data1,
+    Radio4Data2,
+    Radio4Data3,
+
+    Radio5Channel,
+    Radio5Send,
+    Radio5Receive,
+    Radio5Data0,
+    Radio5Data1,
+    Radio5Data2,
+    Radio5Data3,
+
+    Radio6Channel,
+    Radio6Send,
+    Radio6Receive,
+    Radio6Data0,
+    Radio6Data1,
+    Radio6Data2,
+    Radio6Data3,
+
+    Radio7Channel,
+    Radio7Send,
+    Radio7Receive,
+    Radio7Data0,
+    Radio7Data1,
+    Radio7Data2,
+    Radio7Data3,
+
+    SelectedRadio,
+
+    DrawnTextPointer,
+    DrawnTextLength,
+
+    RadarEcmMode,
+
+    Health,
+    Fuel,
+
+    RadarContactRssi,
+    RadarContactSnr,
+
+    ReloadTicks0,
+    ReloadTicks1,
+    ReloadTicks2,
+    ReloadTicks3,
+
+    Id,
+
+    DebugValuesPointer,
+    DebugValuesLength,
+
+    DebugShapesPointer,
+    DebugShapesLength,
+    DebugLayersPointer,
+    DebugLayersLength,
+
+    Size,
+    MaxSize = 128,
+};
+// END GENERATED
+
+pub fn readU64(key: SystemState) u64 {
+    return SYSTEM_STATE[@intFromEnum(key)];
+}
+
+pub fn readF64(key: SystemState) f64 {
+    return @bitCast(readU64(key));
+}
+
+pub fn writeU64(key: SystemState, value: u64) void {
+    SYSTEM_STATE[@intFromEnum(key)] = value;
+}
+
+pub fn writeF64(key: SystemState, value: f64) void {
+    writeU64(key, @bitCast(value));
+}
@@ -0,0 +1,117 @@
+use clap::{Parser, ValueEnum};
+use oort_image_renderer::{Options, Renderer};
+use oort_simulator::{scenario, simulation};
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Png,
+    Svg,
+}
+
+/// Renders a battle to a numbered sequence of images.
+///
+/// Assemble the frames into an animation with e.g.
+/// `ffmpeg -framerate 30 -i frame%05d.png battle.mp4`.
+#[derive(Parser, Debug)]
+#[clap()]
+struct Arguments {
+    scenario: String,
+    /// AIs to use for each team. Defaults to the scenario's solution.
+    shortcodes: Vec<String>,
+
+    #[clap(short, long, default_value = "0")]
+    seed: u32,
+
+    #[clap(short, long, default_value = "/tmp/oort-frames")]
+    output: PathBuf,
+
+    #[clap(short, long, value_enum, default_value = "png")]
+    format: Format,
+
+    /// Render one frame every this many ticks.
+    #[clap(long, default_value = "2")]
+    every: u32,
+
+    #[clap(long, default_value = "1280")]
+    width: u32,
+
+    #[clap(long, default_value = "720")]
+    height: u32,
+
+    /// Draw debug lines and shapes from all ships.
+    #[clap(long)]
+    debug: bool,
+
+    #[clap(long, default_value_t = scenario::MAX_TICKS)]
+    max_ticks: u32,
+
+    #[clap(short, long)]
+    dev: bool,
+
+    #[clap(long, default_value = "/tmp/oort-wasm-cache")]
+    wasm_cache: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args = Arguments::parse();
+    let scenario = scenario::load_safe(&args.scenario).expect("Unknown scenario");
+
+    let codes = if args.shortcodes.is_empty() {
+        scenario.solution_codes()
+    } else {
+        log::info!("Compiling AIs");
+        let http = reqwest::Client::new();
+        oort_tools::fetch_and_compile_multiple(
+            &http,
+            &args.shortcodes,
+            args.dev,
+            args.wasm_cache.as_deref(),
+        )
+        .await?
+        .into_iter()
+        .map(|ai| ai.compiled_code)
+        .collect()
+    };
+
+    std::fs::create_dir_all(&args.output)?;
+    let mut renderer = Renderer::new(Options {
+        width: args.width,
+        height: args.height,
+        debug: args.debug,
+    });
+    let mut sim = simulation::Simulation::new(&args.scenario, args.seed, &codes);
+    let mut frame = 0;
+    log::info!("Rendering to {:?}", args.output);
+    while sim.status() == scenario::Status::Running && sim.tick() < args.max_ticks {
+        sim.step();
+        let snapshot = sim.snapshot(0);
+        if let Some(error) = snapshot.errors.first() {
+            log::warn!("Simulation error: {}", error.msg);
+        }
+        renderer.update(&snapshot);
+        if sim.tick() % args.every.max(1) != 0 && sim.status() == scenario::Status::Running {
+            continue;
+        }
+        let (data, extension) = match args.format {
+            Format::Png => (renderer.render_png(&snapshot)?, "png"),
+            Format::Svg => (renderer.render_svg(&snapshot).into_bytes(), "svg"),
+        };
+        std::fs::write(
+            args.output.join(format!("frame{frame:05}.{extension}")),
+            data,
+        )?;
+        frame += 1;
+    }
+    log::info!(
+        "Wrote {} frames; status {:?} at tick {}",
+        frame,
+        sim.status(),
+        sim.tick()
+    );
+
+    Ok(())
+}
@@ -107,7 +107,9 @@ async fn cmd_run(
     dry_run: bool,
 ) -> anyhow::Result<()> {
     let db = FirestoreDb::new(project_id).await?;
-    scenario::load_safe(scenario_name).expect("Unknown scenario");
+    let limits = scenario::load_safe(scenario_name)
+        .expect("Unknown scenario")
+        .resource_limits();
 
     let mut compiler = oort_compiler::Compiler::new();
     let entrants = get_entrants(&db, scenario_name, usernames).await?;
@@ -116,7 +118,8 @@ async fn cmd_run(
         .map(|entrant| {
             log::info!("Compiling {:?}", entrant.username);
             let compiled_code = compiler.compile(&entrant.source_code)?;
-            let compiled_code = oort_simulator::vm::precompile(&compiled_code).unwrap();
+            let compiled_code =
+                oort_simulator::vm::precompile_with_limits(&compiled_code, &limits).unwrap();
             Ok(AI {
                 name: entrant.username.clone(),
                 source_code: entrant.source_code.clone(),
@@ -135,7 +135,8 @@ impl ObjFunc for ObjectiveFunction {
 
         let compile_start_time = std::time::Instant::now();
         let player_code = if let Some(wasm) = compile("player code".to_string(), player_src_code) {
-            oort_simulator::vm::precompile(&wasm).unwrap()
+            let limits = scenario::load(&self.scenario_name).resource_limits();
+            oort_simulator::vm::precompile_with_limits(&wasm, &limits).unwrap()
         } else {
             panic!("Failed to compile player source code");
         };
@@ -1,4 +1,5 @@
 use oort_compiler::Compiler;
+pub use oort_formatter::format;
 use std::sync::Mutex;
 use std::{fs, path::Path, path::PathBuf};
 